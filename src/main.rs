@@ -11,33 +11,46 @@ mod tracer;
 use tracer::primitives::Primitive;
 use tracer::primitives::sphere::Sphere;
 use tracer::primitives::triangle::Triangle;
-use tracer::primitives::light::Light;
-use tracer::primitives::CanSample;
+use tracer::primitives::triangle_mesh::TriangleMesh;
+use tracer::primitives::plane::Plane;
+use tracer::lights::{Light, AreaLight, LightSet, LightSelection};
+use tracer::materials::{Material, Lambertian, Mirror, Dielectric, Emissive};
+use tracer::integrators::{Integrator, DirectLighting, PathTracer};
 
-use tracer::utils::scene::Scene;
+use tracer::utils::scene::{Scene, RenderSettings};
 use tracer::utils::color::Color;
 use tracer::utils::ray::Ray;
 use tracer::utils::camera::Camera;
-use tracer::utils::bounding_volume_hierarchy::BoundingVolumeHierarchy;
-use tracer::utils::bounding_volume_hierarchy::HitInfo;
-
-use image::{DynamicImage, Rgba, GenericImage, Pixel};
-use nalgebra::{Point3, Vector3, distance};
+use tracer::utils::bounding_volume_hierarchy::BVHBuildOptions;
+use tracer::utils::top_level_bvh::TopLevelBVH;
+use tracer::utils::transform::Transform;
+use tracer::utils::sampler::{Sampler, SamplerKind, create_sampler};
+use tracer::utils::filter::{Filter, FilterSampler, create_filter};
+use tracer::utils::film::{Film, FilmSample, Layer};
+use tracer::io::{import_obj, load_scene, save_film};
+use tracer::io::exr::ExrPixelType;
+use tracer::io::output::OutputFormat;
+
+use nalgebra::{Point3, Vector3};
 
 use rand::distributions::{IndependentSample, Range};
-use rand::{thread_rng, Rng};
-use std::sync::{Arc, Mutex, mpsc};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::cmp::{min, max};
 use std::path::Path;
 use std::f32;
 use std::thread;
 use std::env;
-use std::io::BufReader;
-use std::io::BufRead;
-use std::fs::File;
 
-const NB_RAY: u32 = 1; //Per pixel
+const DEFAULT_SAMPLES_PER_PIXEL: u32 = 1;
+// Width and height of the blocks of pixels the threads take
+const TILE_SIZE: u32 = 32;
 const NB_LIGHT_SAMPLE: u32 = 100;
-const NB_RAND_SAMPLE: u32 = 2000000;
+const DEFAULT_MAX_DEPTH: u32 = 8;
+
+// About PI * d² / area, a white diffuse surface 300 units under the light
+// faces roughly its own albedo
+const LIGHT_RADIANCE: f32 = 3000.0;
 
 #[allow(dead_code)]
 fn gen_random_spheres() -> Vec<Primitive> {
@@ -48,17 +61,29 @@ fn gen_random_spheres() -> Vec<Primitive> {
     let between_0_500 = Range::new(400.0, 500.0);
 
     for _ in 0..1 {
+        let color = Color::new(between_0_1.ind_sample(&mut rng),
+                               between_0_1.ind_sample(&mut rng),
+                               between_0_1.ind_sample(&mut rng));
+        let pick: f32 = between_0_1.ind_sample(&mut rng);
+        let material: Arc<Material> = if pick < 0.33 {
+            Arc::new(Lambertian::new(color))
+        }
+        else if pick < 0.66 {
+            Arc::new(Mirror::new(color))
+        }
+        else {
+            Arc::new(Dielectric::new(1.5))
+        };
+
         primitives.push(
             Primitive::Sphere(
-                Sphere::new(
+                Box::new(Sphere::new(
                         between_0_500.ind_sample(&mut rng), 
                         Point3::new(0.0/*between_n500_500.ind_sample(&mut rng)*/, 
                                     0.0/*between_n500_500.ind_sample(&mut rng)*/, 
                                     -1000.0), 
-                        Color::new(between_0_1.ind_sample(&mut rng),
-                                   between_0_1.ind_sample(&mut rng),
-                                   between_0_1.ind_sample(&mut rng))
-                )
+                        material
+                ))
             )
         );
     }
@@ -78,19 +103,20 @@ fn gen_random_triangles() -> Vec<Primitive> {
     for _ in 0..10 {
         primitives.push(
             Primitive::Triangle(
-                Triangle::new(Point3::new(between_n500_500.ind_sample(&mut rng), 
-                                          between_n500_500.ind_sample(&mut rng), 
-                                          between_n500_n1000.ind_sample(&mut rng)),
-                              Point3::new(between_n500_500.ind_sample(&mut rng), 
-                                          between_n500_500.ind_sample(&mut rng), 
-                                          between_n500_n1000.ind_sample(&mut rng)),
-                              Point3::new(between_n500_500.ind_sample(&mut rng),
-                                          between_n500_500.ind_sample(&mut rng),
-                                          between_n500_n1000.ind_sample(&mut rng)), 
-                              Color::new(between_0_1.ind_sample(&mut rng),
-                                         between_0_1.ind_sample(&mut rng),
-                                         between_0_1.ind_sample(&mut rng))
-                )
+                Box::new(Triangle::new(Point3::new(between_n500_500.ind_sample(&mut rng), 
+                                                   between_n500_500.ind_sample(&mut rng), 
+                                                   between_n500_n1000.ind_sample(&mut rng)),
+                                       Point3::new(between_n500_500.ind_sample(&mut rng), 
+                                                   between_n500_500.ind_sample(&mut rng), 
+                                                   between_n500_n1000.ind_sample(&mut rng)),
+                                       Point3::new(between_n500_500.ind_sample(&mut rng),
+                                                   between_n500_500.ind_sample(&mut rng),
+                                                   between_n500_n1000.ind_sample(&mut rng)), 
+                                       Arc::new(Lambertian::new(
+                                           Color::new(between_0_1.ind_sample(&mut rng),
+                                                      between_0_1.ind_sample(&mut rng),
+                                                      between_0_1.ind_sample(&mut rng))))
+                ))
             )
         );
     }
@@ -100,220 +126,123 @@ fn gen_random_triangles() -> Vec<Primitive> {
 
 #[allow(dead_code)]
 fn create_ground() -> Vec<Primitive> {
-    return vec![Primitive::Triangle(
-                Triangle::new(
-                    Point3::new(-10000.0, 0.0, -10000.0),
-                    Point3::new(10000.0, 0.0, -10000.0),
-                    Point3::new(0.0, 0.0, 10000.0),
-                    Color::new(0.5, 0.5, 0.5)
-                )
+    return vec![Primitive::Plane(
+                Box::new(Plane::new(
+                    Point3::new(0.0, 0.0, 0.0),
+                    Vector3::new(0.0, 1.0, 0.0),
+                    Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+                ))
             )];
 }
 
-#[allow(dead_code)]
-fn import_obj(path: &std::string::String) -> Vec<Primitive> {
-    let f = match File::open(path) {
-        Ok(file) => file,
-        Err(_) => {
-            println!("Not a valid path: {}", path);
-            return vec![];
-        }
-    };
-
-    let mut primitives: Vec<Primitive> = Vec::new();
-    let mut vertices: Vec<Point3<f32>> = Vec::new();
-    let file = BufReader::new(&f);
-    for line in file.lines() {
-        let l = line.unwrap();
-        let tokens: Vec<&str> = l.split(" ").collect();
-
-        if tokens[0] == "v" {
-            let x: f32 = tokens[1].parse().unwrap();
-            let y: f32 = tokens[2].parse().unwrap();
-            let z: f32 = tokens[3].parse().unwrap();
-
-            vertices.push(Point3::new(x, y, z));
-        }
-        else if tokens[0] == "f" { //We expect face to be triangles
-            let idx0: usize = tokens[1].parse().unwrap();
-            let idx1: usize = tokens[2].parse().unwrap();
-            let idx2: usize = tokens[3].parse().unwrap();
-            let t = Primitive::Triangle(
-                        Triangle::new(vertices[idx0 - 1], vertices[idx1 - 1], vertices[idx2 - 1],
-                                      Color::new(1.0, 1.0, 1.0)));
-            primitives.push(t);
-        }
-    } 
-
-    return primitives;
-}
-
-fn create_rays(px: u32, py: u32, scene: &Scene, random_samples: &Vec<(f32, f32)>) -> Vec<Ray> {
-    
-    let mut rays: Vec<Ray> = Vec::with_capacity(NB_RAY as usize);
-    let o_x: f32 = px as f32;
-    let o_y: f32 = py as f32;
+// offset is the position of the ray inside the pixel
+fn create_ray(px: u32, py: u32, scene: &Scene, offset: (f32, f32)) -> Ray {
     let w: f32 = scene.width as f32;
     let h: f32 = scene.height as f32;
 
-    for i in  0..NB_RAY {
-        let direction = 
-                (o_x - w / 2.0 +
-                    random_samples[((px * scene.width + py + i) % NB_RAND_SAMPLE) as usize].0) * 
-                    scene.camera.u.as_ref() +
-                (o_y - h / 2.0 + 
-                    random_samples[((px * scene.width + py + i) % NB_RAND_SAMPLE) as usize].1) * 
-                    scene.camera.v.as_ref() -
-                scene.camera.distance * scene.camera.w.as_ref();
-
-        rays.push(
-            Ray::new(
-                scene.camera.eye,
-                direction
-            )
-        );
-    }
+    let direction =
+            (px as f32 - w / 2.0 + offset.0) * scene.camera.u.as_ref() +
+            (py as f32 - h / 2.0 + offset.1) * scene.camera.v.as_ref() -
+            scene.camera.distance * scene.camera.w.as_ref();
 
-    return rays;
+    return Ray::new(scene.camera.eye, direction);
 }
 
-pub fn render_pixel(px: u32, py: u32, scene: &Scene, random_samples: &Vec<(f32, f32)>) -> Color {
-
-    let mut avg_col = Color::new_black();
-
-    // Will not trace more rays per pixel than allowed to fit in the vector
-    let rays: Vec<Ray> = create_rays(px, py, scene, random_samples);
-    for r in 0..rays.len() {
-        let hit_info: Option<HitInfo> = scene.bvh.intersect(&rays[r]);
-        match hit_info {
-            Some(hit_info) => {
-
-               let color: Color = hit_info.color;
-               let r_to_light_orig = hit_info.p_hit;
-               for i in 0..NB_LIGHT_SAMPLE {
-                  let idx = (r * NB_RAY as usize + i as usize) % random_samples.len();
-                  let random_u_v = random_samples[idx];
-                  let p: Point3<f32> = scene.light.get_sample(random_u_v.0, random_u_v.1);
-
-                  // Now that we have closest intersection, trace ray to light
-                  // Add small delta so the origin of the new ray does not intersect with the object
-                  // immediatly
-                  let r_to_light = Ray::new(r_to_light_orig, p - r_to_light_orig); 
-                  let distance_to_light: f32 = distance(&p, &r_to_light_orig);
-
-                  let hit_info_light: Option<HitInfo> = scene.bvh.intersect(&r_to_light);
-
-                  let normal = hit_info.normal;
-                  let light_norm_dot: f32 = normal.dot(&r_to_light.direction).abs();
-
-                  let mut do_shading = |color: &Color, light_norm_dot: f32| {
-                     avg_col.red = avg_col.red + 
-                        ((color.red * light_norm_dot) / ((NB_RAY * NB_LIGHT_SAMPLE) as f32));
-                     avg_col.green = avg_col.green + 
-                        ((color.green * light_norm_dot) / ((NB_RAY * NB_LIGHT_SAMPLE) as f32));
-                     avg_col.blue = avg_col.blue + 
-                        ((color.blue * light_norm_dot) / ((NB_RAY * NB_LIGHT_SAMPLE) as f32));
-                  };
-
-                  match hit_info_light {
-                     Some(x) => {
-                        if distance(&r_to_light_orig, &x.p_hit) > distance_to_light
-                        {
-                           do_shading(&color, light_norm_dot);
-                        }
-                        else
-                        {
-                           do_shading(&Color::new_black(), 1.0);
-                        }
-                     },
-                     None => {
-                        do_shading(&color, light_norm_dot);
-                     }
-                  }
-               }
-            },
-            None => {}
+// The first two dimensions of every sample place the ray around the pixel
+// center, the film keeps the filter weighted sums
+pub fn render_pixel(px: u32, py: u32, scene: &Scene, integrator: &Integrator,
+                    sampler: &mut Sampler, samples_per_pixel: u32, filter: &FilterSampler,
+                    film: &mut Film) {
+    let needs_first_hit = film.needs_first_hit();
+
+    for i in 0..samples_per_pixel {
+        sampler.start_pixel_sample(px, py, i);
+        let (u, v) = sampler.next_2d();
+        let ((dx, dy), weight) = filter.sample(u, v);
+        if weight == 0.0 {
+            continue;
         }
-    }
 
-    return avg_col;
+        let ray = create_ray(px, py, scene, (0.5 + dx, 0.5 + dy));
+        let mut sample = FilmSample::new(Color::new_black());
+        let radiance = integrator.li(&ray, scene, sampler,
+                                     if needs_first_hit { Some(&mut sample) } else { None });
+        sample.radiance = radiance;
+        film.add_sample(px, py, &sample, weight);
+    }
 }
 
-pub fn render(scene: Scene) {
+// Threads take the tiles one after the other and merge them into the film
+pub fn render(scene: Scene, integrator: Arc<Integrator>, settings: &RenderSettings) -> Film {
 
     let w = scene.width;
     let h = scene.height;
 
-    let img = Arc::new(Mutex::new(DynamicImage::new_rgb8(w, h)));
+    let film = Arc::new(Mutex::new(Film::new(w, h, &settings.layers)));
     let scene_ptr = Arc::new(scene);
 
-    let (tx, rx) = mpsc::channel();
-
-    let mut pixels: Vec<(u32, u32)> = Vec::with_capacity((w * h) as usize);
-    let mut random_samples: Vec<(f32, f32)> = Vec::with_capacity(NB_RAND_SAMPLE  as usize);
-    for px in 0..w {
-        for py in 0..h {
-            pixels.push((px, py));
+    let mut tiles: Vec<(u32, u32, u32, u32)> = Vec::new();
+    for y0 in (0..(h + TILE_SIZE - 1) / TILE_SIZE).map(|t| t * TILE_SIZE) {
+        for x0 in (0..(w + TILE_SIZE - 1) / TILE_SIZE).map(|t| t * TILE_SIZE) {
+            tiles.push((x0, y0, min(TILE_SIZE, w - x0), min(TILE_SIZE, h - y0)));
         }
     }
 
-    let mut rng = rand::thread_rng();
-    let between = Range::new(0.0, 1.0);
-    for _ in 0..NB_RAND_SAMPLE {
-        random_samples.push((between.ind_sample(&mut rng),
-                             between.ind_sample(&mut rng)));
-    }
-
-    let mut rng = thread_rng();
-    rng.shuffle(&mut pixels);
-    let num_cpus = num_cpus::get()- 1;
-    let pixels_ptr = Arc::new(pixels);
-    let random_samples_ptr = Arc::new(random_samples);
+    let nb_threads = max(num_cpus::get(), 1);
+    let tiles_ptr = Arc::new(tiles);
+    let next_tile = Arc::new(AtomicUsize::new(0));
+    let filter_ptr = Arc::new(FilterSampler::new(settings.filter.clone()));
+    let sampler_kind = settings.sampler;
+    let samples_per_pixel = settings.samples_per_pixel;
 
     let time_start = time::get_time().sec;
 
-    for i in 0..num_cpus {
+    let mut threads = Vec::with_capacity(nb_threads);
+    for _ in 0..nb_threads {
         let cur_scene = scene_ptr.clone();
-        let cur_pixels = pixels_ptr.clone();
-        let cur_random_samples = random_samples_ptr.clone();
-        let cur_img = img.clone();
-        let tx = tx.clone();
-
-        thread::spawn(move || {
-            let sliced_pixels = &cur_pixels[i * (cur_pixels.len() / num_cpus)..
-                                            (i + 1) * (cur_pixels.len() / num_cpus)];
-            let mut cols = Vec::with_capacity(sliced_pixels.len());
-            for pixel in sliced_pixels {
-                let color = render_pixel(pixel.0, pixel.1, &cur_scene, &cur_random_samples);
-                cols.push((pixel.0, pixel.1, color));
-            }
-
-            let mut cur_img = cur_img.lock().unwrap();
-            for c in cols {
-                cur_img.put_pixel(c.0, c.1,
-                                  Rgba::to_rgba(&c.2.to_rgba()));
+        let cur_integrator = integrator.clone();
+        let cur_filter = filter_ptr.clone();
+        let cur_tiles = tiles_ptr.clone();
+        let cur_next_tile = next_tile.clone();
+        let cur_film = film.clone();
+
+        threads.push(thread::spawn(move || {
+            let mut sampler = create_sampler(sampler_kind, samples_per_pixel);
+            loop {
+                let t = cur_next_tile.fetch_add(1, Ordering::SeqCst);
+                if t >= cur_tiles.len() {
+                    break;
+                }
+
+                let (x0, y0, tile_w, tile_h) = cur_tiles[t];
+                let mut tile = cur_film.lock().unwrap().tile(x0, y0, tile_w, tile_h);
+                for py in y0..y0 + tile_h {
+                    for px in x0..x0 + tile_w {
+                        render_pixel(px, py, &cur_scene, &*cur_integrator, &mut *sampler,
+                                     samples_per_pixel, &*cur_filter, &mut tile);
+                    }
+                }
+                cur_film.lock().unwrap().merge_tile(&tile);
             }
-
-            tx.send(()).unwrap();
-        });
+        }));
     }
-    
-    for _ in 0..num_cpus {
-        rx.recv().unwrap();
+
+    for thread in threads {
+        thread.join().unwrap();
     }
 
     let time_elapsed = time::get_time().sec - time_start;
     if time_elapsed > 0 {
         println!("Rendered in {} seconds", time_elapsed);
         println!("Throughput {}M ray/s", 
-            (((h * w) as u64 * (NB_RAY) as u64) / 1000000) / time_elapsed as u64);
+            (((h * w) as u64 * samples_per_pixel as u64) / 1000000) / time_elapsed as u64);
     }
- 
-    println!("Writting image to disk");
-    let ref mut fout = File::create(&Path::new("output.png")).unwrap();
-    let img = img.lock().unwrap();
-    img.save(fout, image::PNG).unwrap();
 
+    let film = match Arc::try_unwrap(film) {
+        Ok(film) => film,
+        Err(_) => panic!("render threads still hold the film")
+    };
+    return film.into_inner().unwrap();
 }
 
 fn main() {
@@ -321,44 +250,187 @@ fn main() {
     let args: Vec<String> = env::args().collect();
     println!("Building scene");
 
+    // A .toml scene file describes the whole scene and how to render it
+    match args.iter().skip(1).find(|a| a.ends_with(".toml")) {
+        Some(path) => {
+            let scene_file = match load_scene(Path::new(path)) {
+                Ok(scene_file) => scene_file,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
+
+            println!("Rendering...");
+            let film = render(scene_file.scene, scene_file.integrator, &scene_file.settings);
+            println!("Writting image to disk");
+            match save_film(&film, &scene_file.output, scene_file.exr_pixel_type) {
+                Ok(_) => {},
+                Err(e) => println!("{}", e)
+            }
+            return;
+        },
+        None => {}
+    }
+
+    // --integrator=direct|path, --max-depth=N,
+    // --sampler=independent|stratified|halton|sobol, --spp=N,
+    // --filter=box|tent|gaussian|mitchell|lanczos,
+    // --layers=depth,normal,albedo,variance and --output=<path> (.png, .pfm,
+    // .hdr or .exr, output.png by default), everything else is an OBJ file
+    let mut use_path_tracing = false;
+    let mut sampler_kind = SamplerKind::Independent;
+    let mut samples_per_pixel = DEFAULT_SAMPLES_PER_PIXEL;
+    let mut filter: Arc<Filter> = create_filter("box", None).unwrap();
+    let mut layers: Vec<Layer> = Vec::new();
+    let mut max_depth = DEFAULT_MAX_DEPTH;
+    let mut output = Path::new("output.png");
+    let mut obj_paths: Vec<&String> = Vec::new();
+    for argument in &args[1..] {
+        if argument == "--integrator=path" {
+            use_path_tracing = true;
+        }
+        else if argument == "--integrator=direct" {
+            use_path_tracing = false;
+        }
+        else if argument.starts_with("--max-depth=") {
+            max_depth = match argument["--max-depth=".len()..].parse() {
+                Ok(depth) => depth,
+                Err(_) => {
+                    println!("Not a valid depth: {}", argument);
+                    return;
+                }
+            };
+        }
+        else if argument.starts_with("--sampler=") {
+            sampler_kind = match &argument["--sampler=".len()..] {
+                "independent" => SamplerKind::Independent,
+                "stratified" => SamplerKind::Stratified,
+                "halton" => SamplerKind::Halton,
+                "sobol" => SamplerKind::Sobol,
+                _ => {
+                    println!("Unknown sampler: {}", argument);
+                    return;
+                }
+            };
+        }
+        else if argument.starts_with("--spp=") {
+            samples_per_pixel = match argument["--spp=".len()..].parse() {
+                Ok(spp) if spp > 0 => spp,
+                _ => {
+                    println!("Not a valid number of samples: {}", argument);
+                    return;
+                }
+            };
+        }
+        else if argument.starts_with("--filter=") {
+            filter = match create_filter(&argument["--filter=".len()..], None) {
+                Some(filter) => filter,
+                None => {
+                    println!("Unknown filter: {}", argument);
+                    return;
+                }
+            };
+        }
+        else if argument.starts_with("--layers=") {
+            for name in argument["--layers=".len()..].split(',') {
+                match Layer::from_name(name) {
+                    Some(layer) => layers.push(layer),
+                    None => {
+                        println!("Unknown layer: {}", name);
+                        return;
+                    }
+                }
+            }
+        }
+        else if argument.starts_with("--output=") {
+            output = Path::new(&argument["--output=".len()..]);
+            if OutputFormat::from_path(output).is_none() {
+                println!("Not a .png, .pfm, .hdr or .exr file: {}", argument);
+                return;
+            }
+        }
+        else {
+            obj_paths.push(argument);
+        }
+    }
+
+    // One object per OBJ file
+    let mut bvh = TopLevelBVH::new(&BVHBuildOptions::default());
     let mut primitives: Vec<Primitive> = Vec::new();
     // let spheres = gen_random_spheres();
     // let triangles = gen_random_triangles();
     let ground = create_ground();
-    for argument in &args[1..] {
-        let obj = import_obj(argument);
-        primitives.extend(obj);
+    for path in obj_paths {
+        match import_obj(Path::new(path), None) {
+            Ok(mesh) => {
+                let mesh = Arc::new(mesh);
+                let mesh_primitives = TriangleMesh::get_primitives(&mesh).into_iter().map(Primitive::MeshTriangle);
+                bvh.add_object(mesh_primitives.collect(), Transform::identity());
+            },
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        }
     }
 
     // primitives.extend(spheres);
     // primitives.extend(triangles);
     primitives.extend(ground);
 
+    let light_radiance = Color::new(LIGHT_RADIANCE, LIGHT_RADIANCE, LIGHT_RADIANCE);
     let light_primitives = vec![
          Primitive::Triangle(
-            Triangle::new(
+            Box::new(Triangle::new(
                Point3::new(-10.0, 300.0, -10.0),
                Point3::new(10.0, 300.0, -10.0),
                Point3::new(0.0, 300.0, 0.0),
-               Color::new(1.0, 1.0, 1.0)))];
+               Arc::new(Emissive::new(light_radiance)))))];
 
-    let area_light = Light {
-        primitives: light_primitives
-    };
+    // The light is also part of the geometry so rays can hit it
+    let light_start = primitives.len();
+    primitives.extend(light_primitives.iter().cloned());
+
+    let object_index = bvh.add_object(primitives, Transform::identity());
+    bvh.update();
+
+    let nb_light_primitives = light_primitives.len();
+    let area_light: Box<Light> = Box::new(AreaLight::new(light_primitives, light_radiance).unwrap());
+    let mut lights = LightSet::new(vec![area_light], LightSelection::Power, bvh.get_radius());
+    for i in 0..nb_light_primitives {
+        lights.add_emitter(object_index, light_start + i, 0, i);
+    }
 
     let scene = Scene {
         width: 1920,
         height: 1080,
-        light: area_light,
+        lights: lights,
         camera: Camera::new(Point3::new(0.0, 100.0, 200.0), 
                             Point3::new(0.0, 0.0, -100000.0), 
                             Vector3::new(0.0, 1.0, 0.0), 
                             288.0), //60 deg FOV
-        bvh: BoundingVolumeHierarchy::new(primitives)
+        bvh: bvh
+    };
+
+    let integrator: Arc<Integrator> = if use_path_tracing {
+        Arc::new(PathTracer::new(max_depth))
+    }
+    else {
+        Arc::new(DirectLighting::new(NB_LIGHT_SAMPLE))
     };
 
     println!("Rendering...");
-    render(scene);
+    let settings = RenderSettings {
+        sampler: sampler_kind,
+        samples_per_pixel: samples_per_pixel,
+        filter: filter,
+        layers: layers
+    };
+    let film = render(scene, integrator, &settings);
+    println!("Writting image to disk");
+    match save_film(&film, output, ExrPixelType::Float) {
+        Ok(_) => {},
+        Err(e) => println!("{}", e)
+    }
 }
-
-
@@ -0,0 +1,69 @@
+use tracer::integrators::{Integrator, sample_light, escaped_radiance};
+use tracer::utils::color::Color;
+use tracer::utils::ray::Ray;
+use tracer::utils::scene::Scene;
+use tracer::utils::sampler::Sampler;
+use tracer::utils::film::FilmSample;
+
+// Direct lighting from the scene lights only. Specular surfaces are followed
+// up to max_specular_depth bounces so mirrors and glass still show something
+pub struct DirectLighting {
+    pub nb_light_samples: u32,
+    pub max_specular_depth: u32
+}
+
+impl DirectLighting {
+    pub fn new(nb_light_samples: u32) -> DirectLighting {
+        return DirectLighting {
+            nb_light_samples: nb_light_samples,
+            max_specular_depth: 5
+        };
+    }
+
+    fn trace(&self, ray: &Ray, scene: &Scene, sampler: &mut Sampler, depth: u32,
+             first_hit: Option<&mut FilmSample>) -> Color {
+        let hit_info = match scene.bvh.intersect(ray) {
+            Some(hit_info) => hit_info,
+            None => return escaped_radiance(scene, ray, None)
+        };
+        match first_hit {
+            Some(sample) => sample.set_first_hit(&hit_info),
+            None => {}
+        }
+
+        let inter = &hit_info.intersection;
+        let material = &hit_info.material;
+        let wo = -ray.direction;
+        let mut col = material.emitted(&wo, inter);
+
+        for _ in 0..self.nb_light_samples {
+            let random_u_v = sampler.next_2d();
+            match sample_light(scene, inter, &**material, &wo, random_u_v.0, random_u_v.1) {
+                Some(c) => col += c.radiance / self.nb_light_samples as f32,
+                None => {}
+            }
+        }
+
+        if depth < self.max_specular_depth {
+            let random_u_v = sampler.next_2d();
+            match material.sample(&wo, inter, random_u_v.0, random_u_v.1) {
+                Some(ref s) if s.is_specular => {
+                    let bounce = inter.spawn_ray(s.wi.as_ref());
+                    let cos_theta: f32 = inter.shading_normal.dot(&s.wi).abs();
+                    let incoming: Color = self.trace(&bounce, scene, sampler, depth + 1, None);
+                    col += s.value * incoming * (cos_theta / s.pdf);
+                },
+                _ => {}
+            }
+        }
+
+        return col;
+    }
+}
+
+impl Integrator for DirectLighting {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut Sampler,
+          first_hit: Option<&mut FilmSample>) -> Color {
+        return self.trace(ray, scene, sampler, 0, first_hit);
+    }
+}
@@ -0,0 +1,115 @@
+pub mod direct_lighting;
+pub mod path_tracer;
+
+pub use tracer::integrators::direct_lighting::DirectLighting;
+pub use tracer::integrators::path_tracer::PathTracer;
+
+use tracer::lights::Light;
+use tracer::materials::Material;
+use tracer::utils::color::Color;
+use tracer::utils::intersection::Intersection;
+use tracer::utils::scene::Scene;
+use tracer::utils::ray::Ray;
+use tracer::utils::sampler::Sampler;
+use tracer::utils::film::FilmSample;
+
+use nalgebra::{Vector3, Point3};
+use nalgebra::core::Unit;
+
+// A rendering algorithm, computes the radiance arriving along camera rays
+pub trait Integrator: Send + Sync {
+    // The first surface hit by the ray is given to first_hit, if any, so
+    // the film layers do not trace the camera ray again
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut Sampler,
+          first_hit: Option<&mut FilmSample>) -> Color;
+}
+
+// Shadow rays stop a little before the light so they do not hit it
+const SHADOW_RAY_SHORTENING: f32 = 0.999;
+
+// Unoccluded light sample, radiance is f * Li * cos / light_pdf
+pub struct LightContribution {
+    pub radiance: Color,
+    // Solid angle densities of the sampled direction, for the light
+    // and for the material. bsdf_pdf is 0 for delta lights, the material
+    // cannot sample them
+    pub light_pdf: f32,
+    pub bsdf_pdf: f32
+}
+
+// Picks a light, then a point on it, and computes what it brings to inter.
+// u selects the light before being reused for the point
+pub fn sample_light(scene: &Scene, inter: &Intersection, material: &Material,
+                    wo: &Unit<Vector3<f32>>, u: f32, v: f32) -> Option<LightContribution> {
+    if scene.lights.lights.is_empty() {
+        return None;
+    }
+    let (light_index, pmf, u) = scene.lights.select(u);
+    let light: &Light = &*scene.lights.lights[light_index];
+
+    let sample = match light.sample_li(inter, u, v) {
+        Some(sample) => sample,
+        None => return None
+    };
+    let wi = sample.wi;
+
+    let f: Color = material.eval(wo, &wi, inter);
+    if f.is_black() || sample.radiance.is_black() {
+        return None;
+    }
+
+    // Lights at infinity are blocked by anything along the ray
+    let shadow_ray = inter.spawn_ray(wi.as_ref());
+    if scene.bvh.occluded(&shadow_ray, sample.distance * SHADOW_RAY_SHORTENING) {
+        return None;
+    }
+
+    let light_pdf = pmf * sample.pdf;
+    if light_pdf <= 0.0 {
+        return None;
+    }
+
+    let bsdf_pdf = if light.is_delta() { 0.0 } else { material.pdf(wo, &wi, inter) };
+    let cos_theta: f32 = inter.shading_normal.dot(&wi).abs();
+    return Some(LightContribution {
+        radiance: f * sample.radiance * (cos_theta / light_pdf),
+        light_pdf: light_pdf,
+        bsdf_pdf: bsdf_pdf
+    });
+}
+
+// Solid angle density of sampling light_inter, on the light at light_index,
+// from p through sample_light
+pub fn light_pdf(scene: &Scene, p: &Point3<f32>, light_index: usize, light_inter: &Intersection) -> f32 {
+    return scene.lights.pmf(light_index) * scene.lights.lights[light_index].pdf_li(p, light_inter);
+}
+
+// Radiance of the lights at infinity along a ray that left the scene. When
+// the ray was sampled from a material with density bsdf_pdf it is weighted
+// against light sampling, None keeps all of it
+pub fn escaped_radiance(scene: &Scene, ray: &Ray, bsdf_pdf: Option<f32>) -> Color {
+    let mut radiance = Color::new_black();
+    for (i, light) in scene.lights.lights.iter().enumerate() {
+        let le: Color = light.le(ray);
+        if le.is_black() {
+            continue;
+        }
+        let weight = match bsdf_pdf {
+            Some(pdf) => power_heuristic(pdf, scene.lights.pmf(i) * light.pdf_le(&ray.direction)),
+            None => 1.0
+        };
+        radiance += le * weight;
+    }
+    return radiance;
+}
+
+// Multiple importance sampling weight of a strategy with density pdf_a
+// against one with density pdf_b, one sample each
+pub fn power_heuristic(pdf_a: f32, pdf_b: f32) -> f32 {
+    let a2 = pdf_a * pdf_a;
+    let b2 = pdf_b * pdf_b;
+    if a2 + b2 <= 0.0 {
+        return 0.0;
+    }
+    return a2 / (a2 + b2);
+}
@@ -0,0 +1,121 @@
+use tracer::integrators::{Integrator, sample_light, light_pdf, escaped_radiance, power_heuristic};
+use tracer::utils::color::Color;
+use tracer::utils::ray::Ray;
+use tracer::utils::scene::Scene;
+use tracer::utils::sampler::Sampler;
+use tracer::utils::film::FilmSample;
+
+use nalgebra::Point3;
+
+// Unidirectional path tracer. Every bounce samples the light (next event
+// estimation) and the material, the two are combined with multiple
+// importance sampling
+pub struct PathTracer {
+    pub max_depth: u32,
+    // Paths longer than this are randomly terminated based on their throughput
+    pub russian_roulette_depth: u32
+}
+
+impl PathTracer {
+    pub fn new(max_depth: u32) -> PathTracer {
+        return PathTracer {
+            max_depth: max_depth,
+            russian_roulette_depth: 3
+        };
+    }
+}
+
+impl Integrator for PathTracer {
+    fn li(&self, camera_ray: &Ray, scene: &Scene, sampler: &mut Sampler,
+          first_hit: Option<&mut FilmSample>) -> Color {
+        // Taken at the first hit, later hits leave it alone
+        let mut first_hit = first_hit;
+        let mut radiance = Color::new_black();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray: Ray = *camera_ray;
+
+        // Emission found by BSDF sampling is weighted against light sampling,
+        // unless the last bounce was specular (or the camera)
+        let mut specular_bounce = true;
+        let mut last_bsdf_pdf: f32 = 0.0;
+        let mut last_point: Point3<f32> = camera_ray.origin;
+
+        let mut depth = 0;
+        loop {
+            let hit_info = match scene.bvh.intersect(&ray) {
+                Some(hit_info) => hit_info,
+                None => {
+                    let bsdf_pdf = if specular_bounce { None } else { Some(last_bsdf_pdf) };
+                    radiance += throughput * escaped_radiance(scene, &ray, bsdf_pdf);
+                    break;
+                }
+            };
+            match first_hit.take() {
+                Some(sample) => sample.set_first_hit(&hit_info),
+                None => {}
+            }
+            let inter = &hit_info.intersection;
+            let material = &hit_info.material;
+            let wo = -ray.direction;
+
+            let le: Color = material.emitted(&wo, inter);
+            if !le.is_black() {
+                if specular_bounce {
+                    radiance += throughput * le;
+                }
+                else {
+                    // Emitters that are not in the scene lights can only be
+                    // found by BSDF sampling
+                    let weight = match scene.lights.find_emitter(inter) {
+                        Some((light_index, ref light_inter)) => {
+                            power_heuristic(last_bsdf_pdf,
+                                            light_pdf(scene, &last_point, light_index, light_inter))
+                        },
+                        None => 1.0
+                    };
+                    radiance += throughput * le * weight;
+                }
+            }
+
+            if depth >= self.max_depth {
+                break;
+            }
+
+            let light_u_v = sampler.next_2d();
+            match sample_light(scene, inter, &**material, &wo, light_u_v.0, light_u_v.1) {
+                Some(c) => {
+                    let weight = power_heuristic(c.light_pdf, c.bsdf_pdf);
+                    radiance += throughput * c.radiance * weight;
+                },
+                None => {}
+            }
+
+            let bsdf_u_v = sampler.next_2d();
+            let sample = match material.sample(&wo, inter, bsdf_u_v.0, bsdf_u_v.1) {
+                Some(sample) => sample,
+                None => break
+            };
+            if sample.pdf <= 0.0 || sample.value.is_black() {
+                break;
+            }
+
+            let cos_theta: f32 = inter.shading_normal.dot(&sample.wi).abs();
+            throughput = throughput * sample.value * (cos_theta / sample.pdf);
+            specular_bounce = sample.is_specular;
+            last_bsdf_pdf = sample.pdf;
+            last_point = inter.point;
+            ray = inter.spawn_ray(sample.wi.as_ref());
+
+            depth = depth + 1;
+            if depth >= self.russian_roulette_depth {
+                let q: f32 = (1.0 - throughput.max_component()).max(0.05);
+                if sampler.next_1d() < q {
+                    break;
+                }
+                throughput = throughput / (1.0 - q);
+            }
+        }
+
+        return radiance;
+    }
+}
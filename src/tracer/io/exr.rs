@@ -0,0 +1,266 @@
+// OpenEXR writer: single part scanline files without compression, any number
+// of named channels of 16 or 32 bit floats
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+const MAGIC: u32 = 20000630;
+const HALF: i32 = 1;
+const FLOAT: i32 = 2;
+
+#[derive(Clone, Copy)]
+pub enum ExrPixelType {
+    // 16 bit floats, about 3 significant digits, values up to 65504
+    Half,
+    Float
+}
+
+// A channel of the image, values row by row from the top
+pub struct ExrChannel {
+    // "R", "G", "B" for the color, layers are usually written "layer.X"
+    pub name: String,
+    pub values: Vec<f32>
+}
+
+// Rounds to the nearest half, overflows to infinity
+fn f32_to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007fffff;
+
+    // Infinity and NaN, which keeps a mantissa bit
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x0200 } else { 0 };
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if half_exponent <= 0 {
+        // Denormal half or 0
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x00800000;
+        let shift = (14 - half_exponent) as u32;
+        let half_mantissa = mantissa >> shift;
+        let remainder = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round = remainder > halfway || (remainder == halfway && half_mantissa & 1 != 0);
+        return sign | (half_mantissa as u16 + round as u16);
+    }
+
+    let half = sign as u32 | (half_exponent as u32) << 10 | mantissa >> 13;
+    let remainder = mantissa & 0x1fff;
+    // A carry into the exponent is still the right rounding
+    let round = remainder > 0x1000 || (remainder == 0x1000 && half & 1 != 0);
+    return (half + round as u32) as u16;
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend(name.as_bytes());
+    header.push(0);
+    header.extend(kind.as_bytes());
+    header.push(0);
+    header.extend(&i32_bytes(value.len() as i32));
+    header.extend(value);
+}
+
+fn i32_bytes(x: i32) -> [u8; 4] {
+    let x = x as u32;
+    return [x as u8, (x >> 8) as u8, (x >> 16) as u8, (x >> 24) as u8];
+}
+
+fn f32_bytes(x: f32) -> [u8; 4] {
+    return i32_bytes(x.to_bits() as i32);
+}
+
+fn u64_bytes(x: u64) -> [u8; 8] {
+    let mut bytes = [0u8; 8];
+    for i in 0..8 {
+        bytes[i] = (x >> (8 * i)) as u8;
+    }
+    return bytes;
+}
+
+pub fn write_exr(path: &Path, width: u32, height: u32, channels: &[ExrChannel],
+                 pixel_type: ExrPixelType) -> Result<(), String> {
+    // Readers expect the channels sorted by name
+    let mut order: Vec<&ExrChannel> = channels.iter().collect();
+    order.sort_by(|a, b| a.name.cmp(&b.name));
+    let (type_id, bytes_per_value) = match pixel_type {
+        ExrPixelType::Half => (HALF, 2),
+        ExrPixelType::Float => (FLOAT, 4)
+    };
+
+    let mut data: Vec<u8> = Vec::new();
+    data.extend(&i32_bytes(MAGIC as i32));
+    // Version 2, single part scanline file
+    data.extend(&[2, 0, 0, 0]);
+
+    let mut channel_list: Vec<u8> = Vec::new();
+    for channel in &order {
+        channel_list.extend(channel.name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend(&i32_bytes(type_id));
+        // Not perceptually linear, then 3 reserved bytes
+        channel_list.extend(&[0, 0, 0, 0]);
+        // No subsampling
+        channel_list.extend(&i32_bytes(1));
+        channel_list.extend(&i32_bytes(1));
+    }
+    channel_list.push(0);
+
+    let mut window: Vec<u8> = Vec::new();
+    for x in &[0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend(&i32_bytes(*x));
+    }
+
+    attribute(&mut data, "channels", "chlist", &channel_list);
+    attribute(&mut data, "compression", "compression", &[0]);
+    attribute(&mut data, "dataWindow", "box2i", &window);
+    attribute(&mut data, "displayWindow", "box2i", &window);
+    attribute(&mut data, "lineOrder", "lineOrder", &[0]);
+    attribute(&mut data, "pixelAspectRatio", "float", &f32_bytes(1.0));
+    let mut center: Vec<u8> = Vec::new();
+    center.extend(&f32_bytes(0.0));
+    center.extend(&f32_bytes(0.0));
+    attribute(&mut data, "screenWindowCenter", "v2f", &center);
+    attribute(&mut data, "screenWindowWidth", "float", &f32_bytes(1.0));
+    data.push(0);
+
+    // Uncompressed chunks hold a single scanline: its y, the size of the
+    // pixel data, then every channel of the line one after the other
+    let line_size = width as usize * order.len() * bytes_per_value;
+    let first_chunk = data.len() + 8 * height as usize;
+    for y in 0..height as usize {
+        data.extend(&u64_bytes((first_chunk + y * (8 + line_size)) as u64));
+    }
+    for y in 0..height {
+        data.extend(&i32_bytes(y as i32));
+        data.extend(&i32_bytes(line_size as i32));
+        for channel in &order {
+            let line = &channel.values[(y * width) as usize..((y + 1) * width) as usize];
+            for value in line {
+                match pixel_type {
+                    ExrPixelType::Half => {
+                        let half = f32_to_half(*value);
+                        data.extend(&[half as u8, (half >> 8) as u8]);
+                    },
+                    ExrPixelType::Float => data.extend(&f32_bytes(*value))
+                }
+            }
+        }
+    }
+
+    match File::create(path).and_then(|mut f| f.write_all(&data)) {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(format!("cannot write '{}': {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::f32;
+
+    // Exact value of a half, to check the conversion against
+    fn half_to_f32(half: u16) -> f32 {
+        let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+        let exponent = ((half >> 10) & 0x1f) as i32;
+        let mantissa = (half & 0x03ff) as f32;
+        if exponent == 0x1f {
+            return if mantissa != 0.0 { f32::NAN } else { sign * f32::INFINITY };
+        }
+        if exponent == 0 {
+            return sign * mantissa * 2.0f32.powi(-24);
+        }
+        return sign * (1.0 + mantissa / 1024.0) * 2.0f32.powi(exponent - 15);
+    }
+
+    #[test]
+    fn known_values() {
+        let cases: Vec<(f32, u16)> = vec![
+            (0.0, 0x0000),
+            (-0.0, 0x8000),
+            (1.0, 0x3c00),
+            (-2.0, 0xc000),
+            (0.5, 0x3800),
+            (0.1, 0x2e66),
+            (65504.0, 0x7bff),
+            // Smallest normal, smallest and largest subnormals
+            (2.0f32.powi(-14), 0x0400),
+            (2.0f32.powi(-24), 0x0001),
+            (-2.0f32.powi(-24), 0x8001),
+            (1023.0 * 2.0f32.powi(-24), 0x03ff),
+            (f32::INFINITY, 0x7c00),
+            (f32::NEG_INFINITY, 0xfc00)
+        ];
+        for (value, half) in cases {
+            assert_eq!(f32_to_half(value), half, "{}", value);
+        }
+    }
+
+    #[test]
+    fn overflow_to_infinity() {
+        // 65520 is halfway to the next exponent, ties go to the even infinity
+        assert_eq!(f32_to_half(65519.0), 0x7bff);
+        assert_eq!(f32_to_half(65520.0), 0x7c00);
+        assert_eq!(f32_to_half(1e5), 0x7c00);
+        assert_eq!(f32_to_half(-1e30), 0xfc00);
+        assert_eq!(f32_to_half(f32::MAX), 0x7c00);
+    }
+
+    #[test]
+    fn underflow_and_subnormals() {
+        // Half of the smallest subnormal ties to 0, anything above rounds up
+        assert_eq!(f32_to_half(2.0f32.powi(-25)), 0x0000);
+        assert_eq!(f32_to_half(2.0f32.powi(-25) * 1.01), 0x0001);
+        assert_eq!(f32_to_half(-2.0f32.powi(-26)), 0x8000);
+        assert_eq!(f32_to_half(1e-10), 0x0000);
+        assert_eq!(f32_to_half(f32::MIN_POSITIVE), 0x0000);
+        // 1.5 and 2.5 times the smallest subnormal tie to the even neighbour
+        assert_eq!(f32_to_half(1.5 * 2.0f32.powi(-24)), 0x0002);
+        assert_eq!(f32_to_half(2.5 * 2.0f32.powi(-24)), 0x0002);
+        // Just below the smallest normal rounds up into it
+        assert_eq!(f32_to_half(1023.75 * 2.0f32.powi(-24)), 0x0400);
+    }
+
+    #[test]
+    fn nan_stays_nan() {
+        for &value in &[f32::NAN, -f32::NAN, f32::from_bits(0x7f800001), f32::from_bits(0x7fc00000)] {
+            let half = f32_to_half(value);
+            assert_eq!(half & 0x7c00, 0x7c00);
+            assert!(half & 0x03ff != 0, "{:x} is not a NaN", half);
+        }
+    }
+
+    #[test]
+    fn rounding_to_nearest_even() {
+        let ulp = 2.0f32.powi(-10);
+        // Halfway between 1 and the next half goes down to the even 1, the
+        // next halfway point goes up to the even 0x3c02
+        assert_eq!(f32_to_half(1.0 + 0.5 * ulp), 0x3c00);
+        assert_eq!(f32_to_half(1.0 + 1.5 * ulp), 0x3c02);
+        assert_eq!(f32_to_half(1.0 + 0.5 * ulp + 2.0f32.powi(-20)), 0x3c01);
+        assert_eq!(f32_to_half(1.0 + 0.49 * ulp), 0x3c00);
+        // A carry out of the mantissa moves to the next exponent
+        assert_eq!(f32_to_half(2.0 - 0.25 * ulp), 0x4000);
+    }
+
+    #[test]
+    fn every_half_round_trips() {
+        for half in 0..0x10000u32 {
+            let half = half as u16;
+            let value = half_to_f32(half);
+            if value.is_nan() {
+                continue;
+            }
+            assert_eq!(f32_to_half(value), half, "{}", value);
+        }
+    }
+}
@@ -0,0 +1,11 @@
+pub mod toml;
+pub mod obj;
+pub mod mtl;
+pub mod pfm;
+pub mod exr;
+pub mod output;
+pub mod scene_file;
+
+pub use tracer::io::obj::import_obj;
+pub use tracer::io::scene_file::{load_scene, SceneFile, SceneError};
+pub use tracer::io::output::save_film;
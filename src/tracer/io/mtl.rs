@@ -0,0 +1,350 @@
+// Wavefront MTL reader. Kd, Ks, Ns, Ni, d (or Tr), Ke, illum and map_Kd
+// are read, other statements are ignored. Glass needs both a dissolve below 1
+// and a transparent illumination model (4, 6, 7 or 9). Other dissolves are
+// cutouts and fading (leaves, decals), which the tracer does not support:
+// those surfaces stay opaque and the importer warns about them
+
+use tracer::io::obj::ObjError;
+use tracer::materials::{Material, Lambertian, Mirror, Dielectric, Emissive, Phong, Texture, Image};
+use tracer::utils::color::Color;
+
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::io::BufRead;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Exporters often leave Ni at 1 on glass, which would make it invisible
+const DEFAULT_GLASS_IOR: f32 = 1.5;
+
+pub struct MtlMaterial {
+    pub name: String,
+    pub diffuse: Color,
+    pub specular: Color,
+    pub emission: Color,
+    pub exponent: f32,
+    pub ior: f32,
+    // 1 is opaque
+    pub dissolve: f32,
+    pub illum: u32,
+    pub diffuse_map: Option<Arc<Image>>
+}
+
+impl MtlMaterial {
+    fn new(name: String) -> MtlMaterial {
+        return MtlMaterial {
+            name: name,
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            specular: Color::new_black(),
+            emission: Color::new_black(),
+            exponent: 0.0,
+            ior: 1.0,
+            dissolve: 1.0,
+            illum: 2,
+            diffuse_map: None
+        };
+    }
+
+    fn is_glass(&self) -> bool {
+        return self.dissolve < 1.0 &&
+               (self.illum == 4 || self.illum == 6 || self.illum == 7 || self.illum == 9);
+    }
+
+    // Transparency to_material cannot render, the surface is made opaque
+    pub fn ignores_dissolve(&self) -> bool {
+        return self.dissolve < 1.0 && self.emission.is_black() && !self.is_glass();
+    }
+
+    // Closest tracer material, in order: emitters, glass, mirrors (illum 3
+    // and 5 without a diffuse color), glossy surfaces and diffuse ones
+    pub fn to_material(&self) -> Arc<Material> {
+        if !self.emission.is_black() {
+            return Arc::new(Emissive::new(self.emission));
+        }
+
+        if self.is_glass() {
+            let ior = if self.ior > 1.0 { self.ior } else { DEFAULT_GLASS_IOR };
+            return Arc::new(Dielectric::new(ior));
+        }
+
+        if (self.illum == 3 || self.illum == 5) && self.diffuse.is_black() &&
+           self.diffuse_map.is_none() && !self.specular.is_black() {
+            return Arc::new(Mirror::new(self.specular));
+        }
+
+        let diffuse = match self.diffuse_map {
+            Some(ref image) => Texture::new_image(self.diffuse, image.clone()),
+            None => Texture::new(self.diffuse)
+        };
+
+        // Ns 0 has no highlight, illum 0 and 1 have no specular term
+        if self.illum >= 2 && self.exponent > 0.0 && !self.specular.is_black() {
+            // Keep diffuse + specular <= 1
+            let d = self.diffuse.max_component();
+            let s = self.specular.max_component();
+            let specular = if d + s > 1.0 {
+                self.specular * ((1.0 - d).max(0.0) / s)
+            }
+            else {
+                self.specular
+            };
+            return Arc::new(Phong::new(diffuse, specular, self.exponent));
+        }
+
+        return Arc::new(Lambertian::new_textured(diffuse));
+    }
+}
+
+struct Parser {
+    path: String,
+    line: usize
+}
+
+impl Parser {
+    fn error(&self, message: String) -> ObjError {
+        return ObjError {
+            path: self.path.clone(),
+            line: self.line,
+            message: message
+        };
+    }
+
+    fn float(&self, statement: &str, tokens: &[&str]) -> Result<f32, ObjError> {
+        if tokens.len() != 1 {
+            return Err(self.error(format!("'{}' expects 1 number, found {}", statement, tokens.len())));
+        }
+        match tokens[0].parse::<f32>() {
+            Ok(x) => return Ok(x),
+            Err(_) => return Err(self.error(format!("'{}' is not a valid number", tokens[0])))
+        }
+    }
+
+    // A single value is a grey, "spectral" and "xyz" colors are not supported
+    fn color(&self, statement: &str, tokens: &[&str]) -> Result<Color, ObjError> {
+        if tokens.len() != 1 && tokens.len() != 3 {
+            return Err(self.error(format!("'{}' expects 1 or 3 numbers, found {}",
+                                          statement, tokens.len())));
+        }
+
+        let mut rgb = [0.0; 3];
+        for i in 0..3 {
+            let token = tokens[if tokens.len() == 1 { 0 } else { i }];
+            rgb[i] = match token.parse::<f32>() {
+                Ok(x) => x,
+                Err(_) => return Err(self.error(format!("'{}' is not a valid number", token)))
+            };
+        }
+        return Ok(Color::new(rgb[0], rgb[1], rgb[2]));
+    }
+}
+
+// Images are shared between the materials, and files, using them
+pub fn parse_mtl(path: &Path, images: &mut HashMap<PathBuf, Arc<Image>>) -> Result<Vec<MtlMaterial>, ObjError> {
+    let f = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => return Err(ObjError {
+            path: path.display().to_string(),
+            line: 0,
+            message: format!("cannot open the file: {}", e)
+        })
+    };
+    return read_mtl(BufReader::new(&f), path, images);
+}
+
+// Parses MTL statements from any reader, images are relative to path
+fn read_mtl<R: BufRead>(reader: R, path: &Path, images: &mut HashMap<PathBuf, Arc<Image>>)
+    -> Result<Vec<MtlMaterial>, ObjError> {
+    let mut parser = Parser {
+        path: path.display().to_string(),
+        line: 0
+    };
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    let mut materials: Vec<MtlMaterial> = Vec::new();
+    for line in reader.lines() {
+        parser.line = parser.line + 1;
+        let l = match line {
+            Ok(l) => l,
+            Err(e) => return Err(parser.error(format!("cannot read the line: {}", e)))
+        };
+        let statement = match l.find('#') {
+            Some(i) => &l[..i],
+            None => &l[..]
+        };
+
+        let tokens: Vec<&str> = statement.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+
+        if tokens[0] == "newmtl" {
+            if tokens.len() < 2 {
+                return Err(parser.error("'newmtl' needs a material name".to_string()));
+            }
+            materials.push(MtlMaterial::new(tokens[1..].join(" ")));
+            continue;
+        }
+
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None => return Err(parser.error(format!("'{}' before any 'newmtl'", tokens[0])))
+        };
+        let args = &tokens[1..];
+        match tokens[0] {
+            "Kd" => material.diffuse = try!(parser.color("Kd", args)),
+            "Ks" => material.specular = try!(parser.color("Ks", args)),
+            "Ke" => material.emission = try!(parser.color("Ke", args)),
+            "Ns" => material.exponent = try!(parser.float("Ns", args)),
+            "Ni" => material.ior = try!(parser.float("Ni", args)),
+            "d" => material.dissolve = try!(parser.float("d", args)),
+            "Tr" => material.dissolve = 1.0 - try!(parser.float("Tr", args)),
+            "illum" => {
+                let illum = try!(parser.float("illum", args));
+                if illum < 0.0 || illum.fract() != 0.0 {
+                    return Err(parser.error(format!("'{}' is not a valid illumination model", args[0])));
+                }
+                material.illum = illum as u32;
+            },
+            "map_Kd" => {
+                // Options (-s, -o, -bm...) come first, the file name is last
+                let file = match args.last() {
+                    Some(file) => Path::new(file),
+                    None => return Err(parser.error("'map_Kd' needs a file name".to_string()))
+                };
+                let image_path = directory.join(file);
+                if !images.contains_key(&image_path) {
+                    let image = match Image::open(&image_path) {
+                        Ok(image) => image,
+                        Err(message) => return Err(parser.error(message))
+                    };
+                    images.insert(image_path.clone(), Arc::new(image));
+                }
+                material.diffuse_map = Some(images[&image_path].clone());
+            },
+            _ => {}
+        }
+    }
+
+    return Ok(materials);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tracer::utils::intersection::Intersection;
+    use tracer::utils::ray::Ray;
+
+    use nalgebra::{Point2, Point3, Vector3};
+    use nalgebra::core::Unit;
+
+    fn parse(text: &str) -> Vec<MtlMaterial> {
+        let mut images: HashMap<PathBuf, Arc<Image>> = HashMap::new();
+        match read_mtl(text.as_bytes(), Path::new("test.mtl"), &mut images) {
+            Ok(materials) => return materials,
+            Err(e) => panic!("unexpected error: {}", e)
+        }
+    }
+
+    fn parse_err(text: &str) -> (usize, String) {
+        let mut images: HashMap<PathBuf, Arc<Image>> = HashMap::new();
+        match read_mtl(text.as_bytes(), Path::new("test.mtl"), &mut images) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => return (e.line, e.message)
+        }
+    }
+
+    // Whether light hitting the material head on can go through it
+    fn transmits(material: &Material) -> bool {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let inter = Intersection::new(&ray, 1.0, Unit::new_normalize(Vector3::new(0.0, 0.0, 1.0)),
+                                      Point2::new(0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let wo = Unit::new_normalize(Vector3::new(0.0, 0.0, 1.0));
+        return (0..10).any(|i| {
+            match material.sample(&wo, &inter, (i as f32 + 0.5) / 10.0, 0.5) {
+                Some(sample) => sample.wi.z < 0.0,
+                None => false
+            }
+        });
+    }
+
+    #[test]
+    fn parses_fields() {
+        let materials = parse("# comment\n\
+                               newmtl plain\n\
+                               newmtl shiny red\n\
+                               Kd 0.5 0 0\n\
+                               Ks 0.2\n\
+                               Ns 50\n\
+                               Ni 1.33\n\
+                               Ke 0 0 0\n\
+                               illum 3\n\
+                               d 0.25\n\
+                               newmtl tr\n\
+                               Tr 0.25\n");
+        assert_eq!(materials.len(), 3);
+
+        let plain = &materials[0];
+        assert_eq!(plain.name, "plain");
+        assert_eq!(plain.dissolve, 1.0);
+        assert_eq!(plain.illum, 2);
+
+        let shiny = &materials[1];
+        assert_eq!(shiny.name, "shiny red");
+        assert_eq!((shiny.diffuse.red, shiny.diffuse.green), (0.5, 0.0));
+        assert_eq!(shiny.specular.blue, 0.2);
+        assert_eq!((shiny.exponent, shiny.ior, shiny.illum, shiny.dissolve), (50.0, 1.33, 3, 0.25));
+
+        // Tr is the complement of d
+        assert_eq!(materials[2].dissolve, 0.75);
+    }
+
+    #[test]
+    fn dissolve_with_transparent_illum_is_glass() {
+        for illum in &[4, 6, 7, 9] {
+            let materials = parse(&format!("newmtl glass\nNi 1.5\nd 0.1\nillum {}\n", illum));
+            assert!(materials[0].is_glass());
+            assert!(!materials[0].ignores_dissolve());
+            assert!(transmits(&*materials[0].to_material()));
+        }
+    }
+
+    #[test]
+    fn other_dissolves_stay_opaque() {
+        // A cutout on a diffuse surface
+        let materials = parse("newmtl leaf\nKd 0 0.5 0\nd 0.5\nillum 2\n\
+                               newmtl opaque_glass\nillum 4\nd 1\n\
+                               newmtl lamp\nKe 1 1 1\nd 0.5\n");
+        let leaf = &materials[0];
+        assert!(!leaf.is_glass());
+        assert!(leaf.ignores_dissolve());
+        assert_eq!(leaf.dissolve, 0.5);
+        assert!(!transmits(&*leaf.to_material()));
+
+        // Transparent illum without dissolve, nothing to warn about
+        assert!(!materials[1].is_glass());
+        assert!(!materials[1].ignores_dissolve());
+        assert!(!transmits(&*materials[1].to_material()));
+
+        // Emitters do not use the dissolve
+        assert!(!materials[2].ignores_dissolve());
+        assert!(!materials[2].to_material().emission().is_black());
+    }
+
+    #[test]
+    fn malformed_input() {
+        let cases: Vec<(&str, usize, &str)> = vec![
+            ("d 0.5", 1, "'d' before any 'newmtl'"),
+            ("newmtl a\nd", 2, "'d' expects 1 number, found 0"),
+            ("newmtl a\nTr x", 2, "'x' is not a valid number"),
+            ("newmtl a\nKd 1 2", 2, "'Kd' expects 1 or 3 numbers, found 2"),
+            ("newmtl a\nillum 1.5", 2, "'1.5' is not a valid illumination model"),
+            ("newmtl", 1, "'newmtl' needs a material name")
+        ];
+        for (text, line, message) in cases {
+            let (error_line, error_message) = parse_err(text);
+            assert_eq!((error_line, error_message.as_str()), (line, message), "parsing {:?}", text);
+        }
+    }
+}
@@ -0,0 +1,604 @@
+// Wavefront OBJ reader. Supports v, vt, vn, f (any polygon, fan
+// triangulated, with v, v/vt, v//vn or v/vt/vn vertices and negative
+// relative indices), o, g, usemtl and mtllib. Other statements (s, l, p,
+// curves...) are ignored
+
+use tracer::io::mtl::parse_mtl;
+use tracer::primitives::triangle_mesh::TriangleMesh;
+use tracer::materials::{Material, Lambertian, Image};
+use tracer::utils::color::Color;
+
+use nalgebra::{Point2, Point3, Vector3};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::fmt;
+use std::io::BufReader;
+use std::io::BufRead;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+// Faces meeting at a sharper angle than this keep a hard edge when vertex
+// normals are generated, cos(60°)
+const CREASE_COS: f32 = 0.5;
+
+pub struct ObjError {
+    pub path: String,
+    // 0 when the error is not tied to a line
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            return write!(f, "{}: {}", self.path, self.message);
+        }
+        write!(f, "{}:{}: {}", self.path, self.line, self.message)
+    }
+}
+
+// Indices of the attributes of a face corner in the ObjModel arrays, 32
+// bits keep large models small while they are converted
+#[derive(Clone, Copy)]
+pub struct ObjVertex {
+    pub position: u32,
+    pub uv: Option<u32>,
+    pub normal: Option<u32>
+}
+
+pub struct ObjTriangle {
+    pub vertices: [ObjVertex; 3],
+    // Index in ObjModel::groups
+    pub group: usize
+}
+
+// Faces sharing the same object/group name and material, a new group starts
+// at every o, g or usemtl statement
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Option<String>,
+    // Line of the statement that started the group
+    pub line: usize
+}
+
+pub struct ObjModel {
+    pub positions: Vec<Point3<f32>>,
+    pub uvs: Vec<Point2<f32>>,
+    pub normals: Vec<Vector3<f32>>,
+    pub triangles: Vec<ObjTriangle>,
+    pub groups: Vec<ObjGroup>,
+    // Files named by mtllib, as written in the OBJ
+    pub material_libraries: Vec<String>
+}
+
+struct Parser {
+    path: String,
+    line: usize
+}
+
+impl Parser {
+    fn error(&self, message: String) -> ObjError {
+        return ObjError {
+            path: self.path.clone(),
+            line: self.line,
+            message: message
+        };
+    }
+
+    fn floats(&self, statement: &str, tokens: &[&str], min: usize, max: usize) -> Result<Vec<f32>, ObjError> {
+        if tokens.len() < min || tokens.len() > max {
+            let expected = if min == max { format!("{}", min) } else { format!("{} to {}", min, max) };
+            return Err(self.error(format!("'{}' expects {} numbers, found {}",
+                                          statement, expected, tokens.len())));
+        }
+
+        let mut values = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            match token.parse::<f32>() {
+                Ok(x) => values.push(x),
+                Err(_) => return Err(self.error(format!("'{}' is not a valid number", token)))
+            }
+        }
+        return Ok(values);
+    }
+
+    // OBJ indices start at 1, negative ones count back from the last element
+    fn index(&self, token: &str, count: usize, kind: &str) -> Result<u32, ObjError> {
+        let i: i64 = match token.parse() {
+            Ok(i) => i,
+            Err(_) => return Err(self.error(format!("'{}' is not a valid {} index", token, kind)))
+        };
+
+        let resolved = if i > 0 { i - 1 } else { count as i64 + i };
+        if i == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(self.error(format!("{} index {} is out of range, {} defined",
+                                          kind, i, count)));
+        }
+        return Ok(resolved as u32);
+    }
+
+    fn vertex(&self, token: &str, model: &ObjModel) -> Result<ObjVertex, ObjError> {
+        let parts: Vec<&str> = token.split('/').collect();
+        if parts.len() > 3 || parts[0].is_empty() {
+            return Err(self.error(format!("'{}' is not a valid face vertex", token)));
+        }
+
+        let position = try!(self.index(parts[0], model.positions.len(), "vertex"));
+        let uv = if parts.len() > 1 && !parts[1].is_empty() {
+            Some(try!(self.index(parts[1], model.uvs.len(), "texture coordinate")))
+        }
+        else {
+            None
+        };
+        let normal = if parts.len() > 2 && !parts[2].is_empty() {
+            Some(try!(self.index(parts[2], model.normals.len(), "normal")))
+        }
+        else {
+            None
+        };
+
+        return Ok(ObjVertex {
+            position: position,
+            uv: uv,
+            normal: normal
+        });
+    }
+}
+
+pub fn parse_obj(path: &Path) -> Result<ObjModel, ObjError> {
+    let f = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => return Err(ObjError {
+            path: path.display().to_string(),
+            line: 0,
+            message: format!("cannot open the file: {}", e)
+        })
+    };
+    return read_obj(BufReader::new(&f), &path.display().to_string());
+}
+
+// Parses OBJ statements from any reader, path only names it in the errors
+fn read_obj<R: BufRead>(reader: R, path: &str) -> Result<ObjModel, ObjError> {
+    let mut parser = Parser {
+        path: path.to_string(),
+        line: 0
+    };
+
+    let mut model = ObjModel {
+        positions: Vec::new(),
+        uvs: Vec::new(),
+        normals: Vec::new(),
+        triangles: Vec::new(),
+        groups: vec![ObjGroup { name: String::new(), material: None, line: 0 }],
+        material_libraries: Vec::new()
+    };
+
+    let mut pending = String::new();
+    let mut lines = reader.lines();
+    loop {
+        let l = match lines.next() {
+            Some(Ok(l)) => {
+                parser.line = parser.line + 1;
+                l
+            },
+            Some(Err(e)) => return Err(parser.error(format!("cannot read the line: {}", e))),
+            // A backslash on the last line, the end of the file ends the
+            // statement
+            None if !pending.is_empty() => String::new(),
+            None => break
+        };
+
+        // A trailing backslash joins the next line, unless it is commented out
+        let l = match l.find('#') {
+            Some(i) => &l[..i],
+            None => &l[..]
+        };
+        if l.ends_with('\\') {
+            pending.push_str(&l[..l.len() - 1]);
+            pending.push(' ');
+            continue;
+        }
+        pending.push_str(l);
+        let statement = pending.clone();
+        pending.clear();
+
+        let tokens: Vec<&str> = statement.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+
+        match tokens[0] {
+            "v" => {
+                // The optional w is ignored
+                let xyz = try!(parser.floats("v", &tokens[1..], 3, 4));
+                model.positions.push(Point3::new(xyz[0], xyz[1], xyz[2]));
+            },
+            "vt" => {
+                let uv = try!(parser.floats("vt", &tokens[1..], 1, 3));
+                model.uvs.push(Point2::new(uv[0], if uv.len() > 1 { uv[1] } else { 0.0 }));
+            },
+            "vn" => {
+                let xyz = try!(parser.floats("vn", &tokens[1..], 3, 3));
+                model.normals.push(Vector3::new(xyz[0], xyz[1], xyz[2]));
+            },
+            "f" => {
+                if tokens.len() < 4 {
+                    return Err(parser.error(format!("a face needs at least 3 vertices, found {}",
+                                                    tokens.len() - 1)));
+                }
+
+                let mut corners: Vec<ObjVertex> = Vec::with_capacity(tokens.len() - 1);
+                for token in &tokens[1..] {
+                    corners.push(try!(parser.vertex(token, &model)));
+                }
+
+                // Fan triangulation, exact for the convex polygons OBJ exporters write
+                let group = model.groups.len() - 1;
+                for i in 1..corners.len() - 1 {
+                    model.triangles.push(ObjTriangle {
+                        vertices: [corners[0], corners[i], corners[i + 1]],
+                        group: group
+                    });
+                }
+            },
+            "o" | "g" => {
+                let material = model.groups.last().unwrap().material.clone();
+                model.groups.push(ObjGroup {
+                    name: tokens[1..].join(" "),
+                    material: material,
+                    line: parser.line
+                });
+            },
+            "usemtl" => {
+                if tokens.len() < 2 {
+                    return Err(parser.error("'usemtl' needs a material name".to_string()));
+                }
+                let name = model.groups.last().unwrap().name.clone();
+                model.groups.push(ObjGroup {
+                    name: name,
+                    material: Some(tokens[1..].join(" ")),
+                    line: parser.line
+                });
+            },
+            "mtllib" => {
+                if tokens.len() < 2 {
+                    return Err(parser.error("'mtllib' needs a file name".to_string()));
+                }
+                model.material_libraries.extend(tokens[1..].iter().map(|t| t.to_string()));
+            },
+            _ => {}
+        }
+    }
+
+    return Ok(model);
+}
+
+// Smooth normals for the corners of every triangle, the average of the
+// normals of the faces around the vertex weighted by their angle at the
+// vertex. Faces beyond the crease angle are left out of the average
+fn generate_normals(model: &ObjModel) -> Vec<[Vector3<f32>; 3]> {
+    let mut face_normals: Vec<Vector3<f32>> = Vec::with_capacity(model.triangles.len());
+    let mut corner_angles: Vec<[f32; 3]> = Vec::with_capacity(model.triangles.len());
+    let mut corners_of: Vec<Vec<(usize, usize)>> = vec![Vec::new(); model.positions.len()];
+    for (i, triangle) in model.triangles.iter().enumerate() {
+        let p: Vec<Point3<f32>> = triangle.vertices.iter().map(|v| model.positions[v.position as usize]).collect();
+        let n = (p[1] - p[0]).cross(&(p[2] - p[0]));
+        let norm = n.norm();
+        face_normals.push(if norm > 0.0 { n / norm } else { n });
+
+        let mut angles = [0.0; 3];
+        for c in 0..3 {
+            let a = p[(c + 1) % 3] - p[c];
+            let b = p[(c + 2) % 3] - p[c];
+            let denominator = a.norm() * b.norm();
+            if denominator > 0.0 {
+                angles[c] = (a.dot(&b) / denominator).max(-1.0).min(1.0).acos();
+            }
+            corners_of[triangle.vertices[c].position as usize].push((i, c));
+        }
+        corner_angles.push(angles);
+    }
+
+    let mut normals: Vec<[Vector3<f32>; 3]> = Vec::with_capacity(model.triangles.len());
+    for (i, triangle) in model.triangles.iter().enumerate() {
+        let mut corner_normals = [face_normals[i]; 3];
+        for c in 0..3 {
+            let mut sum = Vector3::new(0.0, 0.0, 0.0);
+            for &(j, k) in &corners_of[triangle.vertices[c].position as usize] {
+                if face_normals[i].dot(&face_normals[j]) >= CREASE_COS {
+                    sum = sum + corner_angles[j][k] * face_normals[j];
+                }
+            }
+            if sum.norm() > 0.0 {
+                corner_normals[c] = sum.normalize();
+            }
+        }
+        normals.push(corner_normals);
+    }
+    return normals;
+}
+
+// Normalized vn of a corner, None without one or when it is degenerate
+// (exporters write "vn 0 0 0" for vertices they could not compute a normal for)
+fn corner_normal(model: &ObjModel, vertex: &ObjVertex) -> Option<Vector3<f32>> {
+    match vertex.normal {
+        Some(n) => {
+            let normal = model.normals[n as usize];
+            let norm_squared = normal.norm_squared();
+            if norm_squared > 0.0 && norm_squared.is_finite() {
+                return Some(normal / norm_squared.sqrt());
+            }
+            return None;
+        },
+        None => return None
+    }
+}
+
+// Materials of every group, looked up in the mtllib files. Groups without
+// usemtl are white and diffuse
+fn group_materials(path: &Path, model: &ObjModel) -> Result<Vec<Arc<Material>>, ObjError> {
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let mut images: HashMap<PathBuf, Arc<Image>> = HashMap::new();
+    let mut library: HashMap<String, Arc<Material>> = HashMap::new();
+    for file in &model.material_libraries {
+        let mtl_path = directory.join(file);
+        let mtls = try!(parse_mtl(&mtl_path, &mut images));
+        let opaque: Vec<String> = mtls.iter().filter(|m| m.ignores_dissolve()).map(|m| m.name.clone()).collect();
+        if !opaque.is_empty() {
+            println!("{}: dissolve is only supported on glass (illum 4, 6, 7 or 9), rendering {} opaque",
+                     mtl_path.display(), opaque.join(", "));
+        }
+        for mtl in mtls {
+            let material = mtl.to_material();
+            library.insert(mtl.name, material);
+        }
+    }
+
+    let default: Arc<Material> = Arc::new(Lambertian::new(Color::new(1.0, 1.0, 1.0)));
+    let mut materials: Vec<Arc<Material>> = Vec::with_capacity(model.groups.len());
+    for group in &model.groups {
+        match group.material {
+            Some(ref name) => match library.get(name) {
+                Some(material) => materials.push(material.clone()),
+                None => return Err(ObjError {
+                    path: path.display().to_string(),
+                    line: group.line,
+                    message: format!("material '{}' is not defined in the material libraries", name)
+                })
+            },
+            None => materials.push(default.clone())
+        }
+    }
+    return Ok(materials);
+}
+
+// All the triangles of the file in one mesh, with the given material or,
+// without one, with the materials of the MTL files. Corners without vn get
+// generated normals, corners without vt get (0, 0) when other corners have
+// texture coordinates. Degenerate vn are replaced the same way. Degenerate
+// triangles are dropped since they have no normal
+pub fn import_obj(path: &Path, material: Option<Arc<Material>>) -> Result<TriangleMesh, ObjError> {
+    let model = try!(parse_obj(path));
+    let materials = match material {
+        Some(material) => vec![material; model.groups.len()],
+        None => try!(group_materials(path, &model))
+    };
+    return Ok(build_mesh(&model, materials));
+}
+
+// Mesh of the triangles of a model, materials has one entry per group
+fn build_mesh(model: &ObjModel, materials: Vec<Arc<Material>>) -> TriangleMesh {
+    let missing_normals = model.triangles.iter().any(|t| {
+        t.vertices.iter().any(|v| corner_normal(model, v).is_none())
+    });
+    let generated_normals = if missing_normals { generate_normals(model) } else { Vec::new() };
+    let has_uvs = model.triangles.iter().any(|t| t.vertices.iter().any(|v| v.uv.is_some()));
+
+    let mut mesh = TriangleMesh {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        uvs: Vec::new(),
+        indices: Vec::with_capacity(model.triangles.len()),
+        materials: materials,
+        material_indices: Vec::with_capacity(model.triangles.len())
+    };
+
+    // OBJ corners index positions, normals and uvs separately, the mesh
+    // needs one index per distinct combination
+    let mut vertex_of: HashMap<(u32, Option<u32>, [u32; 3]), u32> = HashMap::new();
+    for (i, triangle) in model.triangles.iter().enumerate() {
+        let v = &triangle.vertices;
+        let p0 = model.positions[v[0].position as usize];
+        let p1 = model.positions[v[1].position as usize];
+        let p2 = model.positions[v[2].position as usize];
+        if (p1 - p0).cross(&(p2 - p0)).norm() <= 0.0 {
+            continue;
+        }
+
+        let mut indices = [0; 3];
+        for c in 0..3 {
+            let normal = match corner_normal(model, &v[c]) {
+                Some(n) => n,
+                None => generated_normals[i][c]
+            };
+            let key = (v[c].position, v[c].uv, [normal.x.to_bits(), normal.y.to_bits(), normal.z.to_bits()]);
+            let corner = &v[c];
+            let mesh = &mut mesh;
+            indices[c] = *vertex_of.entry(key).or_insert_with(|| {
+                mesh.positions.push(model.positions[corner.position as usize]);
+                mesh.normals.push(normal);
+                if has_uvs {
+                    mesh.uvs.push(match corner.uv {
+                        Some(uv) => model.uvs[uv as usize],
+                        None => Point2::new(0.0, 0.0)
+                    });
+                }
+                return (mesh.positions.len() - 1) as u32;
+            });
+        }
+
+        mesh.indices.push(indices);
+        mesh.material_indices.push(triangle.group as u32);
+    }
+
+    return mesh;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> ObjModel {
+        match read_obj(text.as_bytes(), "test.obj") {
+            Ok(model) => return model,
+            Err(e) => panic!("unexpected error: {}", e)
+        }
+    }
+
+    // Line and message of the error the text must fail with
+    fn parse_err(text: &str) -> (usize, String) {
+        match read_obj(text.as_bytes(), "test.obj") {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => return (e.line, e.message)
+        }
+    }
+
+    fn mesh(text: &str) -> TriangleMesh {
+        let model = parse(text);
+        let material: Arc<Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let materials = vec![material; model.groups.len()];
+        return build_mesh(&model, materials);
+    }
+
+    fn positions(model: &ObjModel) -> Vec<[u32; 3]> {
+        return model.triangles.iter().map(|t| {
+            [t.vertices[0].position, t.vertices[1].position, t.vertices[2].position]
+        }).collect();
+    }
+
+    const SQUARE: &'static str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    #[test]
+    fn vertex_forms() {
+        let model = parse(&format!("{}vt 0 0\nvt 1 0\nvt 1 1\nvn 0 0 1\n\
+                                    f 1 2 3\n\
+                                    f 1/1 2/2 3/3\n\
+                                    f 1//1 2//1 3//1\n\
+                                    f 1/1/1 2/2/1 3/3/1\n", SQUARE));
+        assert_eq!(model.triangles.len(), 4);
+        let corners: Vec<(u32, Option<u32>, Option<u32>)> = model.triangles.iter().map(|t| {
+            let v = t.vertices[1];
+            (v.position, v.uv, v.normal)
+        }).collect();
+        assert_eq!(corners, vec![(1, None, None), (1, Some(1), None), (1, None, Some(0)), (1, Some(1), Some(0))]);
+    }
+
+    #[test]
+    fn negative_indices() {
+        let model = parse(&format!("{}vn 0 0 1\nf -4 -3 -2\nv 2 2 0\nf -1//-1 -4//1 -3//-1\n", SQUARE));
+        assert_eq!(positions(&model), vec![[0, 1, 2], [4, 1, 2]]);
+        assert_eq!(model.triangles[1].vertices[0].normal, Some(0));
+        assert_eq!(model.positions[4], Point3::new(2.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn fan_triangulation() {
+        let model = parse(&format!("{}v 0.5 1.5 0\nf 1 2 3 4\nf 1 2 3 5 4\n", SQUARE));
+        assert_eq!(positions(&model), vec![[0, 1, 2], [0, 2, 3],
+                                           [0, 1, 2], [0, 2, 4], [0, 4, 3]]);
+    }
+
+    #[test]
+    fn line_continuation() {
+        let model = parse(&format!("{}f 1 2 \\\n3 \\\n4 # comment \\\nf 1 2 3\n", SQUARE));
+        assert_eq!(positions(&model), vec![[0, 1, 2], [0, 2, 3], [0, 1, 2]]);
+
+        // Errors give the line the statement ends on
+        let (line, message) = parse_err(&format!("{}f 1 2 \\\n9\n", SQUARE));
+        assert_eq!((line, message.as_str()), (6, "vertex index 9 is out of range, 4 defined"));
+    }
+
+    #[test]
+    fn continuation_on_last_line() {
+        let model = parse(&format!("{}f 1 2 3 \\", SQUARE));
+        assert_eq!(positions(&model), vec![[0, 1, 2]]);
+
+        let model = parse("v 0 0 0\nv 1 0 0\nv 0 1 \\\n0 \\");
+        assert_eq!(model.positions.len(), 3);
+
+        let (line, message) = parse_err(&format!("{}f 1 2 \\", SQUARE));
+        assert_eq!((line, message.as_str()), (5, "a face needs at least 3 vertices, found 2"));
+    }
+
+    #[test]
+    fn groups_and_materials() {
+        let model = parse(&format!("mtllib a.mtl b.mtl\n{}o box\nusemtl red\nf 1 2 3\ng lid\nf 1 3 4\n", SQUARE));
+        assert_eq!(model.material_libraries, vec!["a.mtl".to_string(), "b.mtl".to_string()]);
+        let groups: Vec<(&str, Option<&str>, usize)> = model.groups.iter().map(|g| {
+            (g.name.as_str(), g.material.as_ref().map(|m| m.as_str()), g.line)
+        }).collect();
+        assert_eq!(groups, vec![("", None, 0), ("box", None, 6), ("box", Some("red"), 7), ("lid", Some("red"), 9)]);
+        assert_eq!(model.triangles[0].group, 2);
+        assert_eq!(model.triangles[1].group, 3);
+    }
+
+    #[test]
+    fn malformed_input() {
+        let cases: Vec<(String, usize, &str)> = vec![
+            ("v 1 2".to_string(), 1, "'v' expects 3 to 4 numbers, found 2"),
+            ("vn 0 0 x".to_string(), 1, "'x' is not a valid number"),
+            (format!("{}f 1 2", SQUARE), 5, "a face needs at least 3 vertices, found 2"),
+            (format!("{}f 1 2 0", SQUARE), 5, "vertex index 0 is out of range, 4 defined"),
+            (format!("{}f 1 2 -5", SQUARE), 5, "vertex index -5 is out of range, 4 defined"),
+            (format!("{}f 1/1 2/1 3/1", SQUARE), 5, "texture coordinate index 1 is out of range, 0 defined"),
+            (format!("{}f 1 2 3/1/1/1", SQUARE), 5, "'3/1/1/1' is not a valid face vertex"),
+            (format!("{}f 1 2 /1", SQUARE), 5, "'/1' is not a valid face vertex"),
+            (format!("{}f 1 2 a", SQUARE), 5, "'a' is not a valid vertex index"),
+            ("usemtl".to_string(), 1, "'usemtl' needs a material name")
+        ];
+        for (text, line, message) in cases {
+            let (error_line, error_message) = parse_err(&text);
+            assert_eq!((error_line, error_message.as_str()), (line, message), "parsing {:?}", text);
+        }
+    }
+
+    #[test]
+    fn shared_corners_are_merged() {
+        // Flat square, the generated normals of both triangles are the same
+        let square = mesh(&format!("{}f 1 2 3 4\n", SQUARE));
+        assert_eq!(square.positions.len(), 4);
+        assert_eq!(square.indices, vec![[0, 1, 2], [0, 2, 3]]);
+        assert!(square.uvs.is_empty());
+        for n in &square.normals {
+            assert!((n - Vector3::new(0.0, 0.0, 1.0)).norm() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn corners_with_different_attributes_are_split() {
+        // Same positions, but the uv or the normal differ at the shared edge
+        let uvs = mesh(&format!("{}vt 0 0\nvt 1 0\nf 1/1 2/1 3/1\nf 1/1 3/2 4/1\n", SQUARE));
+        assert_eq!(uvs.positions.len(), 5);
+        assert_eq!(uvs.uvs.len(), 5);
+
+        let normals = mesh(&format!("{}vn 0 0 1\nvn 0 0.6 0.8\nf 1//1 2//1 3//1\nf 1//2 3//1 4//1\n", SQUARE));
+        assert_eq!(normals.positions.len(), 5);
+        assert_eq!(normals.indices, vec![[0, 1, 2], [3, 2, 4]]);
+
+        // Faces beyond the crease angle keep their own normals
+        let fold = mesh("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 2 3\nf 1 4 2\n");
+        assert_eq!(fold.positions.len(), 6);
+    }
+
+    #[test]
+    fn degenerate_normals_and_triangles() {
+        // A zero vn gets the generated normal, the degenerate face is dropped
+        let m = mesh(&format!("{}vn 0 0 0\nf 1//1 2//1 3//1\nf 1 2 2\n", SQUARE));
+        assert_eq!(m.indices.len(), 1);
+        assert_eq!(m.material_indices, vec![0]);
+        for n in &m.normals {
+            assert!((n - Vector3::new(0.0, 0.0, 1.0)).norm() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// Writes rendered films, the format is chosen from the file extension:
+//
+//    .png                      8 bit, gamma encoded, values clamped to [0, 1]
+//    .pfm                      linear 32 bit floats
+//    .hdr                      linear Radiance RGBE, negative values clamped
+//                              to 0
+//    .exr                      linear OpenEXR with 16 or 32 bit floats
+//
+// OpenEXR files hold the layers of the film as extra channels (Z for the
+// depth, normal.X, albedo.R, variance.R, ...). The other formats write every
+// layer next to the image, with the name of the layer appended to the file
+// name
+
+use tracer::io::exr::{write_exr, ExrChannel, ExrPixelType};
+use tracer::io::pfm::write_pfm;
+use tracer::utils::color::Color;
+use tracer::utils::film::{Film, Layer};
+
+use image;
+use image::{DynamicImage, GenericImage, Rgb};
+use image::hdr::HDREncoder;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Png,
+    Pfm,
+    Hdr,
+    Exr
+}
+
+impl OutputFormat {
+    // None if the extension is not supported
+    pub fn from_path(path: &Path) -> Option<OutputFormat> {
+        let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase());
+        match extension {
+            Some(ref e) if e == "png" => return Some(OutputFormat::Png),
+            Some(ref e) if e == "pfm" => return Some(OutputFormat::Pfm),
+            Some(ref e) if e == "hdr" => return Some(OutputFormat::Hdr),
+            Some(ref e) if e == "exr" => return Some(OutputFormat::Exr),
+            _ => return None
+        }
+    }
+}
+
+fn write_png(path: &Path, width: u32, height: u32, colors: &[Color]) -> Result<(), String> {
+    let mut img = DynamicImage::new_rgb8(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            img.put_pixel(x, y, colors[(y * width + x) as usize].to_rgba());
+        }
+    }
+    let result = File::create(path).map_err(|e| e.to_string()).and_then(|mut f| {
+        img.save(&mut f, image::PNG).map_err(|e| e.to_string())
+    });
+    return result.map_err(|e| format!("cannot write '{}': {}", path.display(), e));
+}
+
+fn write_hdr(path: &Path, width: u32, height: u32, colors: &[Color]) -> Result<(), String> {
+    let pixels: Vec<Rgb<f32>> = colors.iter().map(|c| {
+        Rgb { data: [c.red.max(0.0), c.green.max(0.0), c.blue.max(0.0)] }
+    }).collect();
+    let result = File::create(path).and_then(|f| {
+        HDREncoder::new(BufWriter::new(f)).encode(&pixels, width as usize, height as usize)
+    });
+    return result.map_err(|e| format!("cannot write '{}': {}", path.display(), e));
+}
+
+// PNG layers are remapped to be visible: normals from [-1, 1] to [0, 1] and
+// depths divided by the largest one
+fn displayable(layer: Layer, colors: &mut Vec<Color>) {
+    match layer {
+        Layer::Normal => {
+            for c in colors.iter_mut() {
+                *c = *c * 0.5 + Color::new(0.5, 0.5, 0.5);
+            }
+        },
+        Layer::Depth => {
+            let max_depth = colors.iter().fold(0.0, |m: f32, c| m.max(c.red));
+            if max_depth > 0.0 {
+                for c in colors.iter_mut() {
+                    *c = *c / max_depth;
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+fn exr_channels(film: &Film) -> Vec<ExrChannel> {
+    let mut channels: Vec<ExrChannel> = Vec::new();
+    {
+        let mut add = |name: String, colors: &[Color], component: usize| {
+            channels.push(ExrChannel {
+                name: name,
+                values: colors.iter().map(|c| [c.red, c.green, c.blue][component]).collect()
+            });
+        };
+
+        let radiance = film.radiance();
+        for (i, name) in ["R", "G", "B"].iter().enumerate() {
+            add(name.to_string(), &radiance, i);
+        }
+        for layer in &film.layers {
+            let colors = film.layer(*layer).unwrap();
+            match *layer {
+                // All the components hold the depth
+                Layer::Depth => add("Z".to_string(), &colors, 0),
+                Layer::Normal => {
+                    for (i, axis) in ["X", "Y", "Z"].iter().enumerate() {
+                        add(format!("normal.{}", axis), &colors, i);
+                    }
+                },
+                _ => {
+                    for (i, name) in ["R", "G", "B"].iter().enumerate() {
+                        add(format!("{}.{}", layer.name(), name), &colors, i);
+                    }
+                }
+            }
+        }
+    }
+    return channels;
+}
+
+pub fn save_film(film: &Film, path: &Path, exr_pixel_type: ExrPixelType) -> Result<(), String> {
+    let format = match OutputFormat::from_path(path) {
+        Some(format) => format,
+        None => return Err(format!("cannot write '{}': unsupported image format", path.display()))
+    };
+    let (width, height) = (film.width, film.height);
+
+    if format == OutputFormat::Exr {
+        return write_exr(path, width, height, &exr_channels(film), exr_pixel_type);
+    }
+
+    let write = |path: &Path, colors: &[Color]| -> Result<(), String> {
+        match format {
+            OutputFormat::Pfm => return write_pfm(path, width, height, colors),
+            OutputFormat::Hdr => return write_hdr(path, width, height, colors),
+            _ => return write_png(path, width, height, colors)
+        }
+    };
+    try!(write(path, &film.radiance()));
+
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    for layer in &film.layers {
+        let mut colors = film.layer(*layer).unwrap();
+        if format == OutputFormat::Png {
+            displayable(*layer, &mut colors);
+        }
+        let layer_path = path.with_file_name(format!("{}_{}.{}", stem, layer.name(), extension));
+        try!(write(&layer_path, &colors));
+    }
+    return Ok(());
+}
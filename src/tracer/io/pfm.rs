@@ -0,0 +1,199 @@
+// Portable float maps: a text header ("PF" for RGB or "Pf" for grey, the
+// size, then a scale whose sign gives the byte order) followed by 32 bit
+// floats, rows from the bottom of the image to the top. Written little endian
+
+use tracer::utils::color::Color;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+fn header_token(data: &[u8], position: &mut usize) -> Option<String> {
+    while *position < data.len() && (data[*position] as char).is_whitespace() {
+        *position = *position + 1;
+    }
+    let start = *position;
+    while *position < data.len() && !(data[*position] as char).is_whitespace() {
+        *position = *position + 1;
+    }
+    if start == *position {
+        return None;
+    }
+    return String::from_utf8(data[start..*position].to_vec()).ok();
+}
+
+// Width, height and colors, row 0 at the top of the image
+pub fn read_pfm(path: &Path) -> Result<(u32, u32, Vec<Color>), String> {
+    let mut data: Vec<u8> = Vec::new();
+    match File::open(path).and_then(|mut f| f.read_to_end(&mut data)) {
+        Ok(_) => {},
+        Err(e) => return Err(format!("cannot read '{}': {}", path.display(), e))
+    }
+    let bad = |message: &str| format!("'{}' is not a valid PFM file: {}", path.display(), message);
+
+    let mut position = 0;
+    let channels = match header_token(&data, &mut position) {
+        Some(ref magic) if magic == "PF" => 3,
+        Some(ref magic) if magic == "Pf" => 1,
+        _ => return Err(bad("missing 'PF' or 'Pf'"))
+    };
+    let mut numbers = [0.0f64; 3];
+    for i in 0..3 {
+        numbers[i] = match header_token(&data, &mut position).and_then(|t| t.parse::<f64>().ok()) {
+            Some(x) => x,
+            None => return Err(bad("incomplete header"))
+        };
+    }
+    let (width, height, scale) = (numbers[0], numbers[1], numbers[2]);
+    if width < 1.0 || height < 1.0 || width.fract() != 0.0 || height.fract() != 0.0 || scale == 0.0 {
+        return Err(bad("invalid size or scale"));
+    }
+    let (width, height) = (width as u32, height as u32);
+    let little_endian = scale < 0.0;
+
+    // A single whitespace separates the header from the data
+    position = position + 1;
+    let nb_floats = (width * height) as usize * channels;
+    if data.len() < position + 4 * nb_floats {
+        return Err(bad("truncated data"));
+    }
+
+    let float_at = |i: usize| -> f32 {
+        let b = &data[position + 4 * i..position + 4 * i + 4];
+        let bits = if little_endian {
+            (b[0] as u32) | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+        }
+        else {
+            (b[3] as u32) | (b[2] as u32) << 8 | (b[1] as u32) << 16 | (b[0] as u32) << 24
+        };
+        return f32::from_bits(bits);
+    };
+
+    let mut colors = Vec::with_capacity((width * height) as usize);
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let i = (y * width + x) as usize * channels;
+            if channels == 3 {
+                colors.push(Color::new(float_at(i), float_at(i + 1), float_at(i + 2)));
+            }
+            else {
+                let grey = float_at(i);
+                colors.push(Color::new(grey, grey, grey));
+            }
+        }
+    }
+
+    return Ok((width, height, colors));
+}
+
+// Colors are row by row from the top of the image
+pub fn write_pfm(path: &Path, width: u32, height: u32, colors: &[Color]) -> Result<(), String> {
+    let mut data: Vec<u8> = Vec::with_capacity(32 + 12 * colors.len());
+    data.extend(format!("PF\n{} {}\n-1.0\n", width, height).as_bytes());
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let c = colors[(y * width + x) as usize];
+            for value in &[c.red, c.green, c.blue] {
+                let bits = value.to_bits();
+                data.extend(&[bits as u8, (bits >> 8) as u8, (bits >> 16) as u8, (bits >> 24) as u8]);
+            }
+        }
+    }
+
+    match File::create(path).and_then(|mut f| f.write_all(&data)) {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(format!("cannot write '{}': {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::f32;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_file(name: &str) -> PathBuf {
+        return env::temp_dir().join(format!("ray_tracer_rust_test_{}.pfm", name));
+    }
+
+    fn read_ok(path: &Path) -> (u32, u32, Vec<Color>) {
+        match read_pfm(path) {
+            Ok(image) => return image,
+            Err(e) => panic!("unexpected error: {}", e)
+        }
+    }
+
+    fn bits(c: &Color) -> [u32; 3] {
+        return [c.red.to_bits(), c.green.to_bits(), c.blue.to_bits()];
+    }
+
+    #[test]
+    fn write_read_round_trip() {
+        let colors = vec![Color::new(0.0, 1.0, -2.5), Color::new(1e-30, 3.4e38, 0.1),
+                          Color::new(f32::INFINITY, 0.5, 7.0), Color::new(-0.0, 1e-42, 123.456),
+                          Color::new(0.25, 0.75, 1.0), Color::new(9.0, 8.0, 7.0)];
+        let path = temp_file("round_trip");
+        write_pfm(&path, 3, 2, &colors).unwrap();
+        let (width, height, read) = read_ok(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((width, height), (3, 2));
+        assert_eq!(read.len(), colors.len());
+        for (a, b) in colors.iter().zip(read.iter()) {
+            assert_eq!(bits(a), bits(b));
+        }
+    }
+
+    #[test]
+    fn bottom_row_written_first() {
+        let colors = vec![Color::new(1.0, 1.0, 1.0), Color::new(2.0, 2.0, 2.0)];
+        let path = temp_file("row_order");
+        write_pfm(&path, 1, 2, &colors).unwrap();
+        let mut data: Vec<u8> = Vec::new();
+        File::open(&path).and_then(|mut f| f.read_to_end(&mut data)).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(&data[..header.len()], &header[..]);
+        // 2.0 then 1.0, little endian
+        assert_eq!(&data[header.len()..header.len() + 4], &[0, 0, 0, 0x40]);
+        assert_eq!(&data[header.len() + 12..header.len() + 16], &[0, 0, 0x80, 0x3f]);
+    }
+
+    #[test]
+    fn read_big_endian_grey() {
+        let mut data: Vec<u8> = b"Pf 2 1 1.0\n".to_vec();
+        data.extend(&[0x3f, 0x80, 0, 0, 0xc0, 0x20, 0, 0]);
+        let path = temp_file("big_endian");
+        File::create(&path).and_then(|mut f| f.write_all(&data)).unwrap();
+        let (width, height, colors) = read_ok(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(bits(&colors[0]), bits(&Color::new(1.0, 1.0, 1.0)));
+        assert_eq!(bits(&colors[1]), bits(&Color::new(-2.5, -2.5, -2.5)));
+    }
+
+    #[test]
+    fn invalid_files() {
+        let cases: Vec<(&[u8], &str)> = vec![
+            (b"P6 1 1 255\n", "missing 'PF' or 'Pf'"),
+            (b"PF 1 1", "incomplete header"),
+            (b"PF 0 1 -1.0\n", "invalid size or scale"),
+            (b"PF 1 1 0\n", "invalid size or scale"),
+            (b"PF 1 1 -1.0\n\x00\x00\x80\x3f", "truncated data")
+        ];
+        let path = temp_file("invalid");
+        for (data, message) in cases {
+            File::create(&path).and_then(|mut f| f.write_all(data)).unwrap();
+            match read_pfm(&path) {
+                Ok(_) => panic!("expected an error for {:?}", data),
+                Err(e) => assert!(e.ends_with(message), "{}", e)
+            }
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
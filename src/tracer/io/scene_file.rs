@@ -0,0 +1,1129 @@
+// Scene description files, written in the TOML subset of tracer::io::toml:
+//
+//    [render]                  width, height, integrator ("direct" or "path"),
+//                              max_depth, light_samples, bvh ("sah" or
+//                              "nearest"), light_selection ("power" or
+//                              "area"), sampler ("independent",
+//                              "stratified", "halton" or "sobol"), samples
+//                              (per pixel), filter ("box", "tent",
+//                              "gaussian", "mitchell" or "lanczos"),
+//                              filter_radius (in pixels), layers (any of
+//                              "depth", "normal", "albedo" and "variance"),
+//                              output (.png, .pfm, .hdr or .exr, see
+//                              tracer::io::output), exr_type ("half" or
+//                              "float")
+//    [camera]                  eye, look_at, up, fov (vertical, in degrees)
+//    [[material]]              name, type ("lambertian", "mirror", "dielectric"
+//                              or "emissive"), color, ior, radiance
+//    [[light]]                 type, color, intensity and for each type:
+//                                 "area" (default): a triangle (v0, v1, v2), a
+//                                 sphere (center, radius) or a mesh (file,
+//                                 scale, rotate, translate)
+//                                 "point": position
+//                                 "spot": position, direction, angle (half
+//                                 angle of the cone) and falloff (width of
+//                                 the fading edge), in degrees
+//                                 "directional": direction the light travels
+//    [environment]             file (equirectangular .hdr or .pfm image, its
+//                              top row straight up), intensity, rotate
+//                              (degrees around x, y then z)
+//    [sky]                     daylight sky with a sun, lighting the scene
+//                              like [environment]: elevation and azimuth of
+//                              the sun (degrees, azimuth 0 is toward -z and 90
+//                              toward +x), turbidity (2 is clear, 10 hazy),
+//                              intensity, resolution (width of the image the
+//                              sky is sampled from)
+//    [[sphere]]                center, radius, material
+//    [[triangle]]              v0, v1, v2, material
+//    [[plane]]                 point, normal, material (not emissive)
+//    [[mesh]]                  file, material (overrides the MTL materials of
+//                              the file), scale, rotate (degrees around x, y
+//                              then z), translate
+//    [[instance]]              same fields as [[mesh]], the instances of a file
+//                              with the same material share its geometry
+//
+// Every mesh and instance is an object of the top level BVH, the other
+// primitives are grouped in a single object
+//
+// Spheres, triangles and meshes with an emissive material are lights, like
+// the area [[light]] tables
+//
+// Relative paths are relative to the scene file
+
+use tracer::io::toml;
+use tracer::io::toml::{Table, Value};
+use tracer::io::obj::import_obj;
+use tracer::io::exr::ExrPixelType;
+use tracer::io::output::OutputFormat;
+use tracer::primitives::{Primitive, HasMaterial};
+use tracer::primitives::sphere::Sphere;
+use tracer::primitives::triangle::Triangle;
+use tracer::primitives::plane::Plane;
+use tracer::primitives::triangle_mesh::TriangleMesh;
+use tracer::lights::{Light, AreaLight, PointLight, SpotLight, DirectionalLight, EnvironmentLight, PreethamSky,
+                     LightSet, LightSelection};
+use tracer::materials::{Material, Lambertian, Mirror, Dielectric, Emissive};
+use tracer::materials::texture::Image;
+use tracer::integrators::{Integrator, DirectLighting, PathTracer};
+use tracer::utils::color::Color;
+use tracer::utils::camera::Camera;
+use tracer::utils::scene::{Scene, RenderSettings};
+use tracer::utils::film::Layer;
+use tracer::utils::sampler::SamplerKind;
+use tracer::utils::filter::{Filter, create_filter};
+use tracer::utils::transform::Transform;
+use tracer::utils::top_level_bvh::TopLevelBVH;
+use tracer::utils::bounding_volume_hierarchy::{BoundingVolumeHierarchy, BVHBuildOptions, BVHBuildMethod};
+
+use nalgebra::{Point3, Vector3};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::f32;
+
+const DEFAULT_WIDTH: u32 = 1920;
+const DEFAULT_HEIGHT: u32 = 1080;
+const DEFAULT_FOV: f32 = 60.0;
+const DEFAULT_MAX_DEPTH: u32 = 8;
+const DEFAULT_LIGHT_SAMPLES: u32 = 100;
+const DEFAULT_SAMPLES_PER_PIXEL: u32 = 1;
+const DEFAULT_FILTER: &'static str = "box";
+const DEFAULT_OUTPUT: &'static str = "output.png";
+const DEFAULT_SPOT_ANGLE: f32 = 30.0;
+const DEFAULT_SPOT_FALLOFF: f32 = 5.0;
+const DEFAULT_SUN_ELEVATION: f32 = 45.0;
+const DEFAULT_TURBIDITY: f32 = 3.0;
+const DEFAULT_SKY_RESOLUTION: u32 = 1024;
+
+// Everything needed to render a scene file
+pub struct SceneFile {
+    pub scene: Scene,
+    pub integrator: Arc<Integrator>,
+    pub settings: RenderSettings,
+    pub output: PathBuf,
+    pub exr_pixel_type: ExrPixelType
+}
+
+pub struct SceneError {
+    pub path: String,
+    // 0 when the error is not tied to a line
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            return write!(f, "{}: {}", self.path, self.message);
+        }
+        write!(f, "{}:{}: {}", self.path, self.line, self.message)
+    }
+}
+
+struct Loader {
+    path: String,
+    directory: PathBuf
+}
+
+impl Loader {
+    fn error(&self, line: usize, message: String) -> SceneError {
+        return SceneError {
+            path: self.path.clone(),
+            line: line,
+            message: message
+        };
+    }
+
+    fn field_error(&self, table: &Table, key: &str, line: usize, message: &str) -> SceneError {
+        return self.error(line, format!("{}, field '{}': {}", table.header(), key, message));
+    }
+
+    fn check_fields(&self, table: &Table, allowed: &[&str]) -> Result<(), SceneError> {
+        for entry in &table.entries {
+            if !allowed.contains(&entry.key.as_str()) {
+                return Err(self.field_error(table, &entry.key, entry.line, "unknown field"));
+            }
+        }
+        return Ok(());
+    }
+
+    fn number(&self, table: &Table, key: &str) -> Result<Option<f32>, SceneError> {
+        match table.get(key) {
+            Some(entry) => match entry.value {
+                Value::Number(x) => Ok(Some(x as f32)),
+                ref other => Err(self.field_error(table, key, entry.line,
+                                                  &format!("expected a number, found {}",
+                                                           other.type_name())))
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn required_number(&self, table: &Table, key: &str) -> Result<f32, SceneError> {
+        match try!(self.number(table, key)) {
+            Some(x) => Ok(x),
+            None => Err(self.field_error(table, key, table.line, "missing"))
+        }
+    }
+
+    fn count(&self, table: &Table, key: &str, default: u32) -> Result<u32, SceneError> {
+        match try!(self.number(table, key)) {
+            Some(x) => {
+                if x < 0.0 || x.fract() != 0.0 {
+                    let line = table.get(key).unwrap().line;
+                    return Err(self.field_error(table, key, line,
+                                                "expected a positive integer"));
+                }
+                Ok(x as u32)
+            },
+            None => Ok(default)
+        }
+    }
+
+    fn string(&self, table: &Table, key: &str) -> Result<Option<String>, SceneError> {
+        match table.get(key) {
+            Some(entry) => match entry.value {
+                Value::Str(ref s) => Ok(Some(s.clone())),
+                ref other => Err(self.field_error(table, key, entry.line,
+                                                  &format!("expected a string, found {}",
+                                                           other.type_name())))
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn required_string(&self, table: &Table, key: &str) -> Result<String, SceneError> {
+        match try!(self.string(table, key)) {
+            Some(s) => Ok(s),
+            None => Err(self.field_error(table, key, table.line, "missing"))
+        }
+    }
+
+    fn triple(&self, table: &Table, key: &str) -> Result<Option<[f32; 3]>, SceneError> {
+        let entry = match table.get(key) {
+            Some(entry) => entry,
+            None => return Ok(None)
+        };
+
+        let bad = || self.field_error(table, key, entry.line, "expected an array of 3 numbers");
+        match entry.value {
+            Value::Array(ref values) => {
+                if values.len() != 3 {
+                    return Err(bad());
+                }
+                let mut xyz = [0.0; 3];
+                for i in 0..3 {
+                    match values[i] {
+                        Value::Number(x) => xyz[i] = x as f32,
+                        _ => return Err(bad())
+                    }
+                }
+                Ok(Some(xyz))
+            },
+            _ => Err(bad())
+        }
+    }
+
+    fn point(&self, table: &Table, key: &str) -> Result<Option<Point3<f32>>, SceneError> {
+        return Ok(try!(self.triple(table, key)).map(|p| Point3::new(p[0], p[1], p[2])));
+    }
+
+    fn required_point(&self, table: &Table, key: &str) -> Result<Point3<f32>, SceneError> {
+        match try!(self.point(table, key)) {
+            Some(p) => Ok(p),
+            None => Err(self.field_error(table, key, table.line, "missing"))
+        }
+    }
+
+    fn vector(&self, table: &Table, key: &str) -> Result<Option<Vector3<f32>>, SceneError> {
+        return Ok(try!(self.triple(table, key)).map(|v| Vector3::new(v[0], v[1], v[2])));
+    }
+
+    fn required_vector(&self, table: &Table, key: &str) -> Result<Vector3<f32>, SceneError> {
+        match try!(self.vector(table, key)) {
+            Some(v) => {
+                if v.norm() <= 0.0 {
+                    let line = table.get(key).unwrap().line;
+                    return Err(self.field_error(table, key, line, "cannot be 0"));
+                }
+                Ok(v)
+            },
+            None => Err(self.field_error(table, key, table.line, "missing"))
+        }
+    }
+
+    fn radius(&self, table: &Table) -> Result<f32, SceneError> {
+        let radius = try!(self.required_number(table, "radius"));
+        if radius <= 0.0 {
+            let line = table.get("radius").unwrap().line;
+            return Err(self.field_error(table, "radius", line, "must be positive"));
+        }
+        return Ok(radius);
+    }
+
+    fn color(&self, table: &Table, key: &str) -> Result<Option<Color>, SceneError> {
+        return Ok(try!(self.triple(table, key)).map(|c| Color::new(c[0], c[1], c[2])));
+    }
+
+    fn required_color(&self, table: &Table, key: &str) -> Result<Color, SceneError> {
+        match try!(self.color(table, key)) {
+            Some(c) => Ok(c),
+            None => Err(self.field_error(table, key, table.line, "missing"))
+        }
+    }
+
+    fn material(&self, table: &Table, materials: &HashMap<String, Arc<Material>>)
+        -> Result<Arc<Material>, SceneError> {
+        let name = try!(self.required_string(table, "material"));
+        match materials.get(&name) {
+            Some(material) => Ok(material.clone()),
+            None => {
+                let line = table.get("material").unwrap().line;
+                Err(self.field_error(table, "material", line,
+                                     &format!("no [[material]] is named '{}'", name)))
+            }
+        }
+    }
+
+    fn resolve(&self, file: &str) -> PathBuf {
+        let path = Path::new(file);
+        if path.is_absolute() {
+            return path.to_path_buf();
+        }
+        return self.directory.join(path);
+    }
+
+    fn load_material(&self, table: &Table) -> Result<(String, Arc<Material>), SceneError> {
+        try!(self.check_fields(table, &["name", "type", "color", "ior", "radiance"]));
+        let name = try!(self.required_string(table, "name"));
+        let kind = try!(self.required_string(table, "type"));
+        let material: Arc<Material> = match kind.as_str() {
+            "lambertian" => Arc::new(Lambertian::new(try!(self.required_color(table, "color")))),
+            "mirror" => Arc::new(Mirror::new(try!(self.required_color(table, "color")))),
+            "dielectric" => Arc::new(Dielectric::new(try!(self.required_number(table, "ior")))),
+            "emissive" => Arc::new(Emissive::new(try!(self.required_color(table, "radiance")))),
+            _ => {
+                let line = table.get("type").unwrap().line;
+                return Err(self.field_error(table, "type", line,
+                                            &format!("unknown material type '{}'", kind)));
+            }
+        };
+        return Ok((name, material));
+    }
+
+    // Scale, then rotation, then translation
+    fn transform(&self, table: &Table) -> Result<Transform, SceneError> {
+        // A single number scales uniformly
+        let scale: Vector3<f32> = match table.get("scale") {
+            Some(&toml::Entry { value: Value::Number(s), .. }) => {
+                Vector3::new(s as f32, s as f32, s as f32)
+            },
+            Some(_) => try!(self.vector(table, "scale")).unwrap(),
+            None => Vector3::new(1.0, 1.0, 1.0)
+        };
+        if scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0 {
+            let line = table.get("scale").unwrap().line;
+            return Err(self.field_error(table, "scale", line, "cannot be 0"));
+        }
+        let degrees = try!(self.vector(table, "rotate")).unwrap_or(Vector3::new(0.0, 0.0, 0.0));
+        let radians = Vector3::new(degrees.x.to_radians(), degrees.y.to_radians(), degrees.z.to_radians());
+        let translation = try!(self.vector(table, "translate")).unwrap_or(Vector3::new(0.0, 0.0, 0.0));
+
+        return Ok(Transform::translation(&translation) *
+                  Transform::rotation(&radians) *
+                  Transform::scaling(&scale));
+    }
+
+    fn optional_material(&self, table: &Table, materials: &HashMap<String, Arc<Material>>)
+        -> Result<Option<Arc<Material>>, SceneError> {
+        match table.get("material") {
+            Some(_) => return Ok(Some(try!(self.material(table, materials)))),
+            None => return Ok(None)
+        }
+    }
+
+    // The material, if any, replaces the MTL materials of the file
+    fn import_mesh(&self, table: &Table, material: Option<Arc<Material>>)
+        -> Result<TriangleMesh, SceneError> {
+        let file = try!(self.required_string(table, "file"));
+        match import_obj(&self.resolve(&file), material) {
+            Ok(mesh) => return Ok(mesh),
+            Err(e) => {
+                let line = table.get("file").unwrap().line;
+                return Err(self.field_error(table, "file", line, &e.to_string()));
+            }
+        }
+    }
+
+    fn area_light(&self, table: &Table, primitives: Vec<Primitive>, emission: Color)
+        -> Result<Box<Light>, SceneError> {
+        match AreaLight::new(primitives, emission) {
+            Ok(light) => return Ok(Box::new(light)),
+            Err(message) => return Err(self.error(table.line, format!("{}: {}", table.header(), message)))
+        }
+    }
+
+    // Area light of a [[sphere]] or [[triangle]] with an emitting material
+    fn primitive_light(&self, table: &Table, primitive: &Primitive) -> Result<Option<Box<Light>>, SceneError> {
+        let emission = primitive.get_material().emission();
+        if emission.is_black() {
+            return Ok(None);
+        }
+        return Ok(Some(try!(self.area_light(table, vec![primitive.clone()], emission))));
+    }
+
+    // Area lights of the emitting triangles of a mesh placed by transform,
+    // one per emitting material, with the index in the mesh of each of their
+    // triangles. Lights are sampled in scene space, they get their own moved
+    // copy of these triangles
+    fn mesh_lights(&self, table: &Table, mesh: &TriangleMesh, transform: &Transform)
+        -> Result<Vec<(Box<Light>, Vec<usize>)>, SceneError> {
+        let emitting: Vec<usize> = (0..mesh.materials.len()).filter(|m| {
+            !mesh.materials[*m].emission().is_black()
+        }).collect();
+        if emitting.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut lights: Vec<(Box<Light>, Vec<usize>)> = Vec::new();
+        for m in emitting {
+            let indices: Vec<usize> = (0..mesh.nb_triangles())
+                .filter(|t| mesh.material_indices[*t] as usize == m)
+                .collect();
+            // Groups without faces can still name an emitting material
+            if indices.is_empty() {
+                continue;
+            }
+            let mut moved = mesh.extract(&indices);
+            moved.transform(transform);
+            let primitives: Vec<Primitive> = TriangleMesh::get_primitives(&Arc::new(moved)).into_iter()
+                .map(Primitive::MeshTriangle)
+                .collect();
+            lights.push((try!(self.area_light(table, primitives, mesh.materials[m].emission())), indices));
+        }
+        return Ok(lights);
+    }
+
+    // Area lights also give their primitives, which rays must be able to hit
+    fn load_light(&self, table: &Table, light_primitives: &mut Vec<Primitive>)
+        -> Result<Box<Light>, SceneError> {
+        let kind = try!(self.string(table, "type")).unwrap_or("area".to_string());
+        let mut fields = vec!["type", "color", "intensity"];
+        match kind.as_str() {
+            "area" => fields.extend(&["v0", "v1", "v2", "center", "radius", "file", "scale", "rotate",
+                                      "translate"]),
+            "point" => fields.push("position"),
+            "spot" => fields.extend(&["position", "direction", "angle", "falloff"]),
+            "directional" => fields.push("direction"),
+            _ => {
+                let line = table.get("type").unwrap().line;
+                return Err(self.field_error(table, "type", line,
+                                            &format!("unknown light type '{}'", kind)));
+            }
+        }
+        try!(self.check_fields(table, &fields));
+
+        let color = try!(self.color(table, "color")).unwrap_or(Color::new(1.0, 1.0, 1.0));
+        let intensity = try!(self.number(table, "intensity")).unwrap_or(1.0);
+        let emission = color * intensity;
+
+        let light: Box<Light> = match kind.as_str() {
+            "area" => {
+                let material: Arc<Material> = Arc::new(Emissive::new(emission));
+                let primitives: Vec<Primitive> = if table.get("center").is_some() {
+                    vec![Primitive::Sphere(
+                        Box::new(Sphere::new(try!(self.radius(table)),
+                                             try!(self.required_point(table, "center")),
+                                             material)))]
+                }
+                else if table.get("file").is_some() {
+                    // Lights are sampled in scene space, the mesh is moved
+                    // for good
+                    let transform = try!(self.transform(table));
+                    let mut mesh = try!(self.import_mesh(table, Some(material)));
+                    mesh.transform(&transform);
+                    let mesh = Arc::new(mesh);
+                    TriangleMesh::get_primitives(&mesh).into_iter().map(Primitive::MeshTriangle).collect()
+                }
+                else {
+                    vec![Primitive::Triangle(
+                        Box::new(Triangle::new(try!(self.required_point(table, "v0")),
+                                               try!(self.required_point(table, "v1")),
+                                               try!(self.required_point(table, "v2")),
+                                               material)))]
+                };
+                light_primitives.extend(primitives.iter().cloned());
+                try!(self.area_light(table, primitives, emission))
+            },
+            "point" => Box::new(PointLight::new(try!(self.required_point(table, "position")), emission)),
+            "spot" => {
+                let angle = try!(self.number(table, "angle")).unwrap_or(DEFAULT_SPOT_ANGLE);
+                if angle <= 0.0 || angle > 180.0 {
+                    let line = table.get("angle").unwrap().line;
+                    return Err(self.field_error(table, "angle", line,
+                                                "must be between 0 and 180 degrees"));
+                }
+                let falloff = try!(self.number(table, "falloff")).unwrap_or(DEFAULT_SPOT_FALLOFF);
+                Box::new(SpotLight::new(try!(self.required_point(table, "position")),
+                                        try!(self.required_vector(table, "direction")),
+                                        emission,
+                                        angle.to_radians(),
+                                        falloff.max(0.0).to_radians()))
+            },
+            _ => Box::new(DirectionalLight::new(try!(self.required_vector(table, "direction")), emission))
+        };
+        return Ok(light);
+    }
+
+    fn load_environment(&self, table: &Table) -> Result<Box<Light>, SceneError> {
+        try!(self.check_fields(table, &["file", "intensity", "rotate"]));
+        let file = try!(self.required_string(table, "file"));
+        let image = match Image::open(&self.resolve(&file)) {
+            Ok(image) => image,
+            Err(e) => {
+                let line = table.get("file").unwrap().line;
+                return Err(self.field_error(table, "file", line, &e));
+            }
+        };
+        let intensity = try!(self.number(table, "intensity")).unwrap_or(1.0);
+        let degrees = try!(self.vector(table, "rotate")).unwrap_or(Vector3::new(0.0, 0.0, 0.0));
+        let radians = Vector3::new(degrees.x.to_radians(), degrees.y.to_radians(), degrees.z.to_radians());
+        return Ok(Box::new(EnvironmentLight::new(image, intensity, Transform::rotation(&radians))));
+    }
+
+    fn load_sky(&self, table: &Table) -> Result<Box<Light>, SceneError> {
+        try!(self.check_fields(table, &["elevation", "azimuth", "turbidity", "intensity", "resolution"]));
+        let elevation = try!(self.number(table, "elevation")).unwrap_or(DEFAULT_SUN_ELEVATION);
+        if elevation < 0.0 || elevation > 90.0 {
+            let line = table.get("elevation").unwrap().line;
+            return Err(self.field_error(table, "elevation", line, "must be between 0 and 90 degrees"));
+        }
+        let azimuth = try!(self.number(table, "azimuth")).unwrap_or(0.0);
+        let turbidity = try!(self.number(table, "turbidity")).unwrap_or(DEFAULT_TURBIDITY);
+        if turbidity < 1.0 {
+            let line = table.get("turbidity").unwrap().line;
+            return Err(self.field_error(table, "turbidity", line, "must be at least 1"));
+        }
+        let intensity = try!(self.number(table, "intensity")).unwrap_or(1.0);
+        let resolution = try!(self.count(table, "resolution", DEFAULT_SKY_RESOLUTION));
+        if resolution < 2 {
+            let line = table.get("resolution").unwrap().line;
+            return Err(self.field_error(table, "resolution", line, "must be at least 2"));
+        }
+
+        let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+        let sun_direction = Vector3::new(elevation.cos() * azimuth.sin(),
+                                         elevation.sin(),
+                                         -elevation.cos() * azimuth.cos());
+        let sky = PreethamSky::new(sun_direction, turbidity);
+        return Ok(Box::new(EnvironmentLight::new(sky.to_image(resolution), intensity, Transform::identity())));
+    }
+
+    fn load(&self, text: &str) -> Result<SceneFile, SceneError> {
+        let tables = match toml::parse(text) {
+            Ok(tables) => tables,
+            Err(e) => return Err(self.error(e.line, e.message))
+        };
+
+        // Materials first, objects may refer to materials declared after them
+        let mut materials: HashMap<String, Arc<Material>> = HashMap::new();
+        for table in tables.iter().filter(|t| t.name == "material") {
+            let (name, material) = try!(self.load_material(table));
+            if materials.contains_key(&name) {
+                let line = table.get("name").unwrap().line;
+                return Err(self.field_error(table, "name", line,
+                                            &format!("material '{}' is declared twice", name)));
+            }
+            materials.insert(name, material);
+        }
+
+        let mut render: Option<&Table> = None;
+        let mut camera: Option<&Table> = None;
+        let mut primitives: Vec<Primitive> = Vec::new();
+        let mut lights: Vec<Box<Light>> = Vec::new();
+        let mut light_primitives: Vec<Primitive> = Vec::new();
+        // Where the BVH finds the light primitives: object, primitive in the
+        // object, light and primitive in the light. Primitives of the loose
+        // object are numbered from the end of primitives for the ones of the
+        // [[light]] tables and the object index is only known at the end
+        let mut object_emitters: Vec<(usize, usize, usize, usize)> = Vec::new();
+        let mut loose_emitters: Vec<(usize, usize, usize)> = Vec::new();
+        let mut light_emitters: Vec<(usize, usize, usize)> = Vec::new();
+
+        // Instanced meshes are only loaded once per file and material. The
+        // BVHs are built once the [render] options are known
+        let mut meshes: Vec<TriangleMesh> = Vec::new();
+        let mut instance_keys: HashMap<(PathBuf, Option<String>), usize> = HashMap::new();
+        let mut objects: Vec<(usize, Transform)> = Vec::new();
+
+        for table in &tables {
+            match (table.name.as_str(), table.is_array) {
+                ("", _) => {
+                    if !table.entries.is_empty() {
+                        let entry = &table.entries[0];
+                        return Err(self.error(entry.line,
+                                              format!("field '{}' must be inside a table",
+                                                      entry.key)));
+                    }
+                },
+                ("render", false) => render = Some(table),
+                ("camera", false) => camera = Some(table),
+                ("material", true) => {},
+                ("light", true) => {
+                    let start = light_primitives.len();
+                    lights.push(try!(self.load_light(table, &mut light_primitives)));
+                    for i in start..light_primitives.len() {
+                        light_emitters.push((i, lights.len() - 1, i - start));
+                    }
+                },
+                ("environment", false) => lights.push(try!(self.load_environment(table))),
+                ("sky", false) => lights.push(try!(self.load_sky(table))),
+                ("sphere", true) => {
+                    try!(self.check_fields(table, &["center", "radius", "material"]));
+                    let sphere = Primitive::Sphere(
+                        Box::new(Sphere::new(try!(self.radius(table)),
+                                             try!(self.required_point(table, "center")),
+                                             try!(self.material(table, &materials)))));
+                    match try!(self.primitive_light(table, &sphere)) {
+                        Some(light) => {
+                            lights.push(light);
+                            loose_emitters.push((primitives.len(), lights.len() - 1, 0));
+                        },
+                        None => {}
+                    }
+                    primitives.push(sphere);
+                },
+                ("triangle", true) => {
+                    try!(self.check_fields(table, &["v0", "v1", "v2", "material"]));
+                    let triangle = Primitive::Triangle(
+                        Box::new(Triangle::new(try!(self.required_point(table, "v0")),
+                                               try!(self.required_point(table, "v1")),
+                                               try!(self.required_point(table, "v2")),
+                                               try!(self.material(table, &materials)))));
+                    match try!(self.primitive_light(table, &triangle)) {
+                        Some(light) => {
+                            lights.push(light);
+                            loose_emitters.push((primitives.len(), lights.len() - 1, 0));
+                        },
+                        None => {}
+                    }
+                    primitives.push(triangle);
+                },
+                ("plane", true) => {
+                    try!(self.check_fields(table, &["point", "normal", "material"]));
+                    let normal = try!(self.required_vector(table, "normal"));
+                    let material = try!(self.material(table, &materials));
+                    // Lights are sampled by area, a plane has an infinite one
+                    if !material.emission().is_black() {
+                        let line = table.get("material").unwrap().line;
+                        return Err(self.field_error(table, "material", line,
+                                                    "a plane cannot be emissive"));
+                    }
+                    primitives.push(Primitive::Plane(
+                        Box::new(Plane::new(try!(self.required_point(table, "point")),
+                                            normal,
+                                            material))));
+                },
+                ("mesh", true) => {
+                    try!(self.check_fields(table, &["file", "material", "scale", "rotate", "translate"]));
+                    let transform = try!(self.transform(table));
+                    meshes.push(try!(self.import_mesh(table, try!(self.optional_material(table, &materials)))));
+                    for (light, indices) in try!(self.mesh_lights(table, &meshes[meshes.len() - 1], &transform)) {
+                        lights.push(light);
+                        for (i, triangle) in indices.into_iter().enumerate() {
+                            object_emitters.push((objects.len(), triangle, lights.len() - 1, i));
+                        }
+                    }
+                    objects.push((meshes.len() - 1, transform));
+                },
+                ("instance", true) => {
+                    try!(self.check_fields(table, &["file", "material", "scale", "rotate", "translate"]));
+                    let transform = try!(self.transform(table));
+                    let key = (self.resolve(&try!(self.required_string(table, "file"))),
+                               try!(self.string(table, "material")));
+                    let existing = instance_keys.get(&key).cloned();
+                    let index = match existing {
+                        Some(index) => index,
+                        None => {
+                            meshes.push(try!(self.import_mesh(table, try!(self.optional_material(table, &materials)))));
+                            instance_keys.insert(key, meshes.len() - 1);
+                            meshes.len() - 1
+                        }
+                    };
+                    for (light, indices) in try!(self.mesh_lights(table, &meshes[index], &transform)) {
+                        lights.push(light);
+                        for (i, triangle) in indices.into_iter().enumerate() {
+                            object_emitters.push((objects.len(), triangle, lights.len() - 1, i));
+                        }
+                    }
+                    objects.push((index, transform));
+                },
+                _ => {
+                    return Err(self.error(table.line,
+                                          format!("unknown table {}", table.header())));
+                }
+            }
+        }
+
+        if lights.is_empty() {
+            return Err(self.error(0, "the scene needs at least one [[light]], emissive primitive, [environment] or [sky]".to_string()));
+        }
+
+        let mut width = DEFAULT_WIDTH;
+        let mut height = DEFAULT_HEIGHT;
+        let mut integrator: Arc<Integrator> = Arc::new(DirectLighting::new(DEFAULT_LIGHT_SAMPLES));
+        let mut bvh_options = BVHBuildOptions::default();
+        let mut light_selection = LightSelection::Power;
+        let mut sampler = SamplerKind::Independent;
+        let mut samples_per_pixel = DEFAULT_SAMPLES_PER_PIXEL;
+        let mut filter: Arc<Filter> = create_filter(DEFAULT_FILTER, None).unwrap();
+        let mut layers: Vec<Layer> = Vec::new();
+        let mut output = self.resolve(DEFAULT_OUTPUT);
+        let mut exr_pixel_type = ExrPixelType::Float;
+        match render {
+            Some(table) => {
+                try!(self.check_fields(table, &["width", "height", "integrator", "max_depth",
+                                                "light_samples", "bvh", "light_selection", "sampler",
+                                                "samples", "filter", "filter_radius", "layers",
+                                                "output", "exr_type"]));
+                width = try!(self.count(table, "width", DEFAULT_WIDTH));
+                height = try!(self.count(table, "height", DEFAULT_HEIGHT));
+                if width == 0 || height == 0 {
+                    return Err(self.error(table.line,
+                                          "[render] width and height cannot be 0".to_string()));
+                }
+
+                let max_depth = try!(self.count(table, "max_depth", DEFAULT_MAX_DEPTH));
+                let light_samples = try!(self.count(table, "light_samples", DEFAULT_LIGHT_SAMPLES));
+                integrator = match try!(self.string(table, "integrator")) {
+                    None => Arc::new(DirectLighting::new(light_samples)),
+                    Some(ref name) if name == "direct" => Arc::new(DirectLighting::new(light_samples)),
+                    Some(ref name) if name == "path" => Arc::new(PathTracer::new(max_depth)),
+                    Some(name) => {
+                        let line = table.get("integrator").unwrap().line;
+                        return Err(self.field_error(table, "integrator", line,
+                                                    &format!("unknown integrator '{}'", name)));
+                    }
+                };
+
+                match try!(self.string(table, "bvh")) {
+                    None => {},
+                    Some(ref name) if name == "sah" => {
+                        bvh_options.method = BVHBuildMethod::SurfaceAreaHeuristic;
+                    },
+                    Some(ref name) if name == "nearest" => {
+                        bvh_options.method = BVHBuildMethod::NearestNeighbour;
+                    },
+                    Some(name) => {
+                        let line = table.get("bvh").unwrap().line;
+                        return Err(self.field_error(table, "bvh", line,
+                                                    &format!("unknown BVH builder '{}'", name)));
+                    }
+                }
+
+                match try!(self.string(table, "light_selection")) {
+                    None => {},
+                    Some(ref name) if name == "power" => light_selection = LightSelection::Power,
+                    Some(ref name) if name == "area" => light_selection = LightSelection::Area,
+                    Some(name) => {
+                        let line = table.get("light_selection").unwrap().line;
+                        return Err(self.field_error(table, "light_selection", line,
+                                                    &format!("unknown light selection '{}'", name)));
+                    }
+                }
+
+                match try!(self.string(table, "sampler")) {
+                    None => {},
+                    Some(ref name) if name == "independent" => sampler = SamplerKind::Independent,
+                    Some(ref name) if name == "stratified" => sampler = SamplerKind::Stratified,
+                    Some(ref name) if name == "halton" => sampler = SamplerKind::Halton,
+                    Some(ref name) if name == "sobol" => sampler = SamplerKind::Sobol,
+                    Some(name) => {
+                        let line = table.get("sampler").unwrap().line;
+                        return Err(self.field_error(table, "sampler", line,
+                                                    &format!("unknown sampler '{}'", name)));
+                    }
+                }
+
+                samples_per_pixel = try!(self.count(table, "samples", DEFAULT_SAMPLES_PER_PIXEL));
+                if samples_per_pixel == 0 {
+                    let line = table.get("samples").unwrap().line;
+                    return Err(self.field_error(table, "samples", line, "cannot be 0"));
+                }
+
+                let filter_radius = try!(self.number(table, "filter_radius"));
+                if filter_radius.map_or(false, |r| r <= 0.0) {
+                    let line = table.get("filter_radius").unwrap().line;
+                    return Err(self.field_error(table, "filter_radius", line, "must be positive"));
+                }
+                let filter_name = try!(self.string(table, "filter")).unwrap_or(DEFAULT_FILTER.to_string());
+                filter = match create_filter(&filter_name, filter_radius) {
+                    Some(filter) => filter,
+                    None => {
+                        let line = table.get("filter").unwrap().line;
+                        return Err(self.field_error(table, "filter", line,
+                                                    &format!("unknown filter '{}'", filter_name)));
+                    }
+                };
+
+                match table.get("layers") {
+                    Some(entry) => {
+                        let names: Vec<&Value> = match entry.value {
+                            Value::Array(ref values) => values.iter().collect(),
+                            _ => vec![&entry.value]
+                        };
+                        for name in names {
+                            let layer = match *name {
+                                Value::Str(ref name) => Layer::from_name(name),
+                                _ => None
+                            };
+                            match layer {
+                                Some(layer) if !layers.contains(&layer) => layers.push(layer),
+                                Some(_) => {},
+                                None => {
+                                    return Err(self.field_error(table, "layers", entry.line,
+                                                                "expected 'depth', 'normal', 'albedo' or 'variance'"));
+                                }
+                            }
+                        }
+                    },
+                    None => {}
+                }
+
+                match try!(self.string(table, "output")) {
+                    Some(file) => {
+                        output = self.resolve(&file);
+                        if OutputFormat::from_path(&output).is_none() {
+                            let line = table.get("output").unwrap().line;
+                            return Err(self.field_error(table, "output", line,
+                                                        "expected a .png, .pfm, .hdr or .exr file"));
+                        }
+                    },
+                    None => {}
+                }
+
+                match try!(self.string(table, "exr_type")) {
+                    None => {},
+                    Some(ref name) if name == "half" => exr_pixel_type = ExrPixelType::Half,
+                    Some(ref name) if name == "float" => exr_pixel_type = ExrPixelType::Float,
+                    Some(name) => {
+                        let line = table.get("exr_type").unwrap().line;
+                        return Err(self.field_error(table, "exr_type", line,
+                                                    &format!("unknown EXR pixel type '{}'", name)));
+                    }
+                }
+            },
+            None => {}
+        }
+
+        let camera = match camera {
+            Some(table) => {
+                try!(self.check_fields(table, &["eye", "look_at", "up", "fov"]));
+                let fov = try!(self.number(table, "fov")).unwrap_or(DEFAULT_FOV);
+                if fov <= 0.0 || fov >= 180.0 {
+                    let line = table.get("fov").unwrap().line;
+                    return Err(self.field_error(table, "fov", line,
+                                                "must be between 0 and 180 degrees"));
+                }
+                let distance = 0.5 * height as f32 / (0.5 * fov.to_radians()).tan();
+                Camera::new(try!(self.required_point(table, "eye")),
+                            try!(self.required_point(table, "look_at")),
+                            try!(self.vector(table, "up")).unwrap_or(Vector3::new(0.0, 1.0, 0.0)),
+                            distance)
+            },
+            None => return Err(self.error(0, "the scene needs a [camera]".to_string()))
+        };
+
+        let mesh_bvhs: Vec<Arc<BoundingVolumeHierarchy>> = meshes.into_iter().map(|mesh| {
+            let mesh_primitives = TriangleMesh::get_primitives(&Arc::new(mesh));
+            Arc::new(BoundingVolumeHierarchy::new_with_options(
+                mesh_primitives.into_iter().map(Primitive::MeshTriangle).collect(), &bvh_options))
+        }).collect();
+
+        let mut bvh = TopLevelBVH::new(&bvh_options);
+        for (index, transform) in objects {
+            bvh.add_instance(mesh_bvhs[index].clone(), transform);
+        }
+
+        // The lights are also part of the geometry so rays can hit them
+        let light_start = primitives.len();
+        primitives.extend(light_primitives);
+        let loose_object = bvh.add_object(primitives, Transform::identity());
+        bvh.update();
+        let scene_radius = bvh.get_radius();
+
+        let mut lights = LightSet::new(lights, light_selection, scene_radius);
+        for (object, primitive, light, light_primitive) in object_emitters {
+            lights.add_emitter(object, primitive, light, light_primitive);
+        }
+        for (primitive, light, light_primitive) in loose_emitters {
+            lights.add_emitter(loose_object, primitive, light, light_primitive);
+        }
+        for (primitive, light, light_primitive) in light_emitters {
+            lights.add_emitter(loose_object, light_start + primitive, light, light_primitive);
+        }
+
+        return Ok(SceneFile {
+            scene: Scene {
+                width: width,
+                height: height,
+                lights: lights,
+                camera: camera,
+                bvh: bvh
+            },
+            integrator: integrator,
+            settings: RenderSettings {
+                sampler: sampler,
+                samples_per_pixel: samples_per_pixel,
+                filter: filter,
+                layers: layers
+            },
+            output: output,
+            exr_pixel_type: exr_pixel_type
+        });
+    }
+}
+
+pub fn load_scene(path: &Path) -> Result<SceneFile, SceneError> {
+    let loader = Loader {
+        path: path.display().to_string(),
+        directory: path.parent().unwrap_or(Path::new("")).to_path_buf()
+    };
+
+    let mut text = String::new();
+    match File::open(path).and_then(|mut f| f.read_to_string(&mut text)) {
+        Ok(_) => {},
+        Err(e) => return Err(loader.error(0, format!("cannot read the file: {}", e)))
+    }
+
+    return loader.load(&text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tracer::utils::ray::Ray;
+
+    use std::env;
+    use std::io::Write;
+
+    const CAMERA: &'static str = "[camera]\neye = [0, 0, 5]\nlook_at = [0, 0, 0]\n";
+    const LIGHT: &'static str = "[[light]]\ntype = \"point\"\nposition = [0, 5, 0]\n";
+
+    fn loader() -> Loader {
+        return Loader {
+            path: "test.toml".to_string(),
+            directory: PathBuf::from("scenes")
+        };
+    }
+
+    fn load_ok(text: &str) -> SceneFile {
+        match loader().load(text) {
+            Ok(scene_file) => return scene_file,
+            Err(e) => panic!("unexpected error: {}", e)
+        }
+    }
+
+    // Line and message of the error the scene must fail with
+    fn load_err(text: &str) -> (usize, String) {
+        match loader().load(text) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => return (e.line, e.message)
+        }
+    }
+
+    #[test]
+    fn minimal_scene() {
+        let scene_file = load_ok(&format!("{}{}", CAMERA, LIGHT));
+        assert_eq!(scene_file.scene.width, DEFAULT_WIDTH);
+        assert_eq!(scene_file.scene.height, DEFAULT_HEIGHT);
+        assert_eq!(scene_file.scene.lights.lights.len(), 1);
+        assert_eq!(scene_file.settings.samples_per_pixel, DEFAULT_SAMPLES_PER_PIXEL);
+        assert!(scene_file.settings.layers.is_empty());
+        assert_eq!(scene_file.output, Path::new("scenes").join(DEFAULT_OUTPUT));
+    }
+
+    #[test]
+    fn render_settings() {
+        let scene_file = load_ok(&format!("[render]\n\
+                                           width = 64\n\
+                                           height = 32\n\
+                                           samples = 16\n\
+                                           layers = [\"depth\", \"albedo\", \"depth\"]\n\
+                                           output = \"/tmp/out.exr\"\n\
+                                           {}{}", CAMERA, LIGHT));
+        assert_eq!(scene_file.scene.width, 64);
+        assert_eq!(scene_file.scene.height, 32);
+        assert_eq!(scene_file.settings.samples_per_pixel, 16);
+        assert!(scene_file.settings.layers == vec![Layer::Depth, Layer::Albedo]);
+        assert_eq!(scene_file.output, Path::new("/tmp/out.exr"));
+    }
+
+    #[test]
+    fn emissive_primitives_are_lights() {
+        let scene_file = load_ok(&format!("[[material]]\n\
+                                           name = \"lamp\"\n\
+                                           type = \"emissive\"\n\
+                                           radiance = [4, 4, 4]\n\
+                                           [[material]]\n\
+                                           name = \"grey\"\n\
+                                           type = \"lambertian\"\n\
+                                           color = [0.5, 0.5, 0.5]\n\
+                                           [[sphere]]\n\
+                                           center = [0, 1, 0]\n\
+                                           radius = 0.5\n\
+                                           material = \"lamp\"\n\
+                                           [[sphere]]\n\
+                                           center = [0, -1, 0]\n\
+                                           radius = 0.5\n\
+                                           material = \"grey\"\n\
+                                           {}", CAMERA));
+        assert_eq!(scene_file.scene.lights.lights.len(), 1);
+    }
+
+    #[test]
+    fn emitter_hits_find_their_light() {
+        // Unit square in z = 0 split along its diagonal
+        let path = env::temp_dir().join("scene_file_emitters.obj");
+        File::create(&path).unwrap()
+            .write_all(b"v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3\nf 1 3 4\n").unwrap();
+        let scene_file = load_ok(&format!("[[material]]\n\
+                                           name = \"lamp\"\n\
+                                           type = \"emissive\"\n\
+                                           radiance = [4, 4, 4]\n\
+                                           [[material]]\n\
+                                           name = \"grey\"\n\
+                                           type = \"lambertian\"\n\
+                                           color = [0.5, 0.5, 0.5]\n\
+                                           [[mesh]]\n\
+                                           file = {:?}\n\
+                                           material = \"grey\"\n\
+                                           [[sphere]]\n\
+                                           center = [0, -3, 0]\n\
+                                           radius = 0.5\n\
+                                           material = \"grey\"\n\
+                                           [[sphere]]\n\
+                                           center = [3, 0, 0]\n\
+                                           radius = 0.5\n\
+                                           material = \"lamp\"\n\
+                                           [[light]]\n\
+                                           v0 = [-3, 0, 0]\n\
+                                           v1 = [-2, 0, 0]\n\
+                                           v2 = [-3, 1, 0]\n\
+                                           [[instance]]\n\
+                                           file = {:?}\n\
+                                           material = \"lamp\"\n\
+                                           translate = [0, 3, 0]\n\
+                                           {}", path.to_str().unwrap(), path.to_str().unwrap(), CAMERA));
+        let scene = &scene_file.scene;
+        assert_eq!(scene.lights.lights.len(), 3);
+
+        // Light and primitive in the light found from the hit at (x, y)
+        let find = |x: f32, y: f32| -> Option<(usize, usize)> {
+            let ray = Ray::new(Point3::new(x, y, 5.0), Vector3::new(0.0, 0.0, -1.0));
+            let hit = scene.bvh.intersect(&ray).expect("the ray must hit the scene");
+            return scene.lights.find_emitter(&hit.intersection).map(|(light, inter)| (light, inter.primitive_index));
+        };
+        assert_eq!(find(0.7, 0.2), None);
+        assert_eq!(find(0.0, -3.0), None);
+        assert_eq!(find(3.0, 0.0), Some((0, 0)));
+        assert_eq!(find(-2.8, 0.2), Some((1, 0)));
+        assert_eq!(find(0.7, 3.2), Some((2, 0)));
+        assert_eq!(find(0.2, 3.7), Some((2, 1)));
+    }
+
+    #[test]
+    fn missing_fields() {
+        let cases: Vec<(String, usize, &str)> = vec![
+            (LIGHT.to_string(), 0, "the scene needs a [camera]"),
+            (CAMERA.to_string(), 0,
+             "the scene needs at least one [[light]], emissive primitive, [environment] or [sky]"),
+            (format!("{}[camera]\neye = [0, 0, 5]\n", LIGHT), 4, "[camera], field 'look_at': missing"),
+            (format!("{}{}[[sphere]]\ncenter = [0, 0, 0]\nmaterial = \"x\"\n", CAMERA, LIGHT), 7,
+             "[[sphere]], field 'radius': missing"),
+            (format!("{}[[light]]\ntype = \"point\"\n", CAMERA), 4,
+             "[[light]], field 'position': missing"),
+            (format!("{}{}[[material]]\ntype = \"mirror\"\ncolor = [1, 1, 1]\n", CAMERA, LIGHT), 7,
+             "[[material]], field 'name': missing"),
+            (format!("{}{}[[material]]\nname = \"m\"\ntype = \"lambertian\"\n", CAMERA, LIGHT), 7,
+             "[[material]], field 'color': missing"),
+            (format!("{}{}[[instance]]\nscale = 2\n", CAMERA, LIGHT), 7,
+             "[[instance]], field 'file': missing")
+        ];
+        for (text, line, message) in cases {
+            let (error_line, error_message) = load_err(&text);
+            assert_eq!((error_line, error_message.as_str()), (line, message), "loading {:?}", text);
+        }
+    }
+
+    #[test]
+    fn invalid_fields() {
+        let cases: Vec<(String, usize, &str)> = vec![
+            (format!("[render]\nwidth = -1\n{}{}", CAMERA, LIGHT), 2,
+             "[render], field 'width': expected a positive integer"),
+            (format!("[render]\nheight = 0\n{}{}", CAMERA, LIGHT), 1,
+             "[render] width and height cannot be 0"),
+            (format!("[render]\nsamples = \"many\"\n{}{}", CAMERA, LIGHT), 2,
+             "[render], field 'samples': expected a number, found a string"),
+            (format!("[render]\nsampler = \"random\"\n{}{}", CAMERA, LIGHT), 2,
+             "[render], field 'sampler': unknown sampler 'random'"),
+            (format!("[render]\nfilter_radius = 0\n{}{}", CAMERA, LIGHT), 2,
+             "[render], field 'filter_radius': must be positive"),
+            (format!("[render]\nlayers = [\"depth\", 1]\n{}{}", CAMERA, LIGHT), 2,
+             "[render], field 'layers': expected 'depth', 'normal', 'albedo' or 'variance'"),
+            (format!("[render]\noutput = \"image.jpg\"\n{}{}", CAMERA, LIGHT), 2,
+             "[render], field 'output': expected a .png, .pfm, .hdr or .exr file"),
+            (format!("[render]\nwidht = 10\n{}{}", CAMERA, LIGHT), 2,
+             "[render], field 'widht': unknown field"),
+            (format!("{}fov = 180\n{}", CAMERA, LIGHT), 4,
+             "[camera], field 'fov': must be between 0 and 180 degrees"),
+            (format!("[camera]\neye = [0, 0]\nlook_at = [0, 0, 0]\n{}", LIGHT), 2,
+             "[camera], field 'eye': expected an array of 3 numbers"),
+            (format!("{}{}[[sphere]]\ncenter = [0, 0, 0]\nradius = -1\nmaterial = \"x\"\n", CAMERA, LIGHT), 9,
+             "[[sphere]], field 'radius': must be positive"),
+            (format!("{}{}[[sphere]]\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"x\"\n", CAMERA, LIGHT), 10,
+             "[[sphere]], field 'material': no [[material]] is named 'x'"),
+            (format!("{}{}[[material]]\nname = \"m\"\ntype = \"metal\"\n", CAMERA, LIGHT), 9,
+             "[[material]], field 'type': unknown material type 'metal'"),
+            (format!("{}{}[[material]]\nname = \"m\"\ntype = \"dielectric\"\nior = 1.5\n\
+                      [[material]]\nname = \"m\"\ntype = \"dielectric\"\nior = 1.3\n", CAMERA, LIGHT), 12,
+             "[[material]], field 'name': material 'm' is declared twice"),
+            (format!("{}{}[[material]]\nname = \"lamp\"\ntype = \"emissive\"\nradiance = [1, 1, 1]\n\
+                      [[plane]]\npoint = [0, 0, 0]\nnormal = [0, 1, 0]\nmaterial = \"lamp\"\n", CAMERA, LIGHT), 14,
+             "[[plane]], field 'material': a plane cannot be emissive"),
+            (format!("{}[[plane]]\npoint = [0, 0, 0]\nnormal = [0, 0, 0]\nmaterial = \"x\"\n{}", CAMERA, LIGHT), 6,
+             "[[plane]], field 'normal': cannot be 0"),
+            (format!("{}[[light]]\ntype = \"laser\"\n", CAMERA), 5,
+             "[[light]], field 'type': unknown light type 'laser'"),
+            (format!("{}[[light]]\ntype = \"point\"\nposition = [0, 1, 0]\nradius = 1\n", CAMERA), 7,
+             "[[light]], field 'radius': unknown field"),
+            (format!("{}{}[[mesh]]\nfile = \"a.obj\"\nscale = [1, 0, 1]\n", CAMERA, LIGHT), 9,
+             "[[mesh]], field 'scale': cannot be 0"),
+            (format!("{}[[light]]\nv0 = [0, 0, 0]\nv1 = [1, 0, 0]\nv2 = [2, 0, 0]\n", CAMERA), 4,
+             "[[light]]: an area light needs a positive and finite area, not 0"),
+            (format!("{}[[material]]\nname = \"lamp\"\ntype = \"emissive\"\nradiance = [1, 1, 1]\n\
+                      [[triangle]]\nv0 = [0, 0, 0]\nv1 = [0, 1, 0]\nv2 = [0, 1, 0]\nmaterial = \"lamp\"\n", CAMERA), 8,
+             "[[triangle]]: an area light needs a positive and finite area, not 0"),
+            (format!("{}{}[[cube]]\n", CAMERA, LIGHT), 7, "unknown table [[cube]]"),
+            (format!("width = 10\n{}{}", CAMERA, LIGHT), 1, "field 'width' must be inside a table"),
+            (format!("{}{}[render\n", CAMERA, LIGHT), 7, "expected ']' at the end of the header")
+        ];
+        for (text, line, message) in cases {
+            let (error_line, error_message) = load_err(&text);
+            assert_eq!((error_line, error_message.as_str()), (line, message), "loading {:?}", text);
+        }
+    }
+
+    #[test]
+    fn error_display() {
+        let error = SceneError { path: "a.toml".to_string(), line: 3, message: "oops".to_string() };
+        assert_eq!(error.to_string(), "a.toml:3: oops");
+        let error = SceneError { path: "a.toml".to_string(), line: 0, message: "oops".to_string() };
+        assert_eq!(error.to_string(), "a.toml: oops");
+    }
+}
@@ -0,0 +1,508 @@
+// Minimal TOML subset used by scene files: [table], [[array of tables]] and
+// key = value, where values are numbers, strings, booleans or arrays of
+// values. Arrays may span several lines and # starts a comment outside of
+// strings. Inline tables, dates and dotted keys are not supported
+
+use std::fmt;
+
+pub enum Value {
+    Number(f64),
+    Str(String),
+    Bool(bool),
+    Array(Vec<Value>)
+}
+
+pub struct Entry {
+    pub key: String,
+    pub value: Value,
+    pub line: usize
+}
+
+pub struct Table {
+    // Empty for the keys found before the first header
+    pub name: String,
+    // Declared with [[name]], there can be many of them
+    pub is_array: bool,
+    pub line: usize,
+    pub entries: Vec<Entry>
+}
+
+pub struct ParseError {
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            &Value::Number(_) => "a number",
+            &Value::Str(_) => "a string",
+            &Value::Bool(_) => "a boolean",
+            &Value::Array(_) => "an array"
+        }
+    }
+}
+
+impl Table {
+    // How the table appears in the file, for error messages
+    pub fn header(&self) -> String {
+        if self.name.is_empty() {
+            return "top level".to_string();
+        }
+        if self.is_array {
+            return format!("[[{}]]", self.name);
+        }
+        return format!("[{}]", self.name);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        return self.entries.iter().find(|e| e.key == key);
+    }
+}
+
+// Drops the comment at the end of the line, if any
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            }
+            else if c == '\\' {
+                escaped = true;
+            }
+            else if c == '"' {
+                in_string = false;
+            }
+        }
+        else if c == '"' {
+            in_string = true;
+        }
+        else if c == '#' {
+            return &line[..i];
+        }
+    }
+    return line;
+}
+
+// Brackets opened and not closed yet, outside of strings
+fn open_brackets(text: &str) -> i32 {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in text.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            }
+            else if c == '\\' {
+                escaped = true;
+            }
+            else if c == '"' {
+                in_string = false;
+            }
+        }
+        else if c == '"' {
+            in_string = true;
+        }
+        else if c == '[' {
+            depth = depth + 1;
+        }
+        else if c == ']' {
+            depth = depth - 1;
+        }
+    }
+    return depth;
+}
+
+fn skip_whitespace(chars: &[char], pos: &mut usize) {
+    while *pos < chars.len() && chars[*pos].is_whitespace() {
+        *pos = *pos + 1;
+    }
+}
+
+fn parse_value(chars: &[char], pos: &mut usize) -> Result<Value, String> {
+    skip_whitespace(chars, pos);
+    if *pos >= chars.len() {
+        return Err("missing value".to_string());
+    }
+
+    let c = chars[*pos];
+    if c == '"' {
+        *pos = *pos + 1;
+        let mut s = String::new();
+        while *pos < chars.len() {
+            let c = chars[*pos];
+            *pos = *pos + 1;
+            if c == '"' {
+                return Ok(Value::Str(s));
+            }
+            if c == '\\' {
+                if *pos >= chars.len() {
+                    break;
+                }
+                let escaped = chars[*pos];
+                *pos = *pos + 1;
+                match escaped {
+                    'n' => s.push('\n'),
+                    't' => s.push('\t'),
+                    '"' => s.push('"'),
+                    '\\' => s.push('\\'),
+                    _ => return Err(format!("unknown escape sequence \\{}", escaped))
+                }
+            }
+            else {
+                s.push(c);
+            }
+        }
+        return Err("unterminated string".to_string());
+    }
+
+    if c == '[' {
+        *pos = *pos + 1;
+        let mut values: Vec<Value> = Vec::new();
+        loop {
+            skip_whitespace(chars, pos);
+            if *pos >= chars.len() {
+                return Err("unterminated array".to_string());
+            }
+            if chars[*pos] == ']' {
+                *pos = *pos + 1;
+                return Ok(Value::Array(values));
+            }
+
+            values.push(try!(parse_value(chars, pos)));
+
+            skip_whitespace(chars, pos);
+            if *pos < chars.len() && chars[*pos] == ',' {
+                *pos = *pos + 1;
+            }
+            else if *pos < chars.len() && chars[*pos] != ']' {
+                return Err(format!("expected ',' or ']' in array, found '{}'", chars[*pos]));
+            }
+        }
+    }
+
+    let start = *pos;
+    while *pos < chars.len() &&
+          (chars[*pos].is_alphanumeric() || chars[*pos] == '.' ||
+           chars[*pos] == '+' || chars[*pos] == '-' || chars[*pos] == '_') {
+        *pos = *pos + 1;
+    }
+    let word: String = chars[start..*pos].iter().filter(|c| **c != '_').cloned().collect();
+    if word == "true" {
+        return Ok(Value::Bool(true));
+    }
+    if word == "false" {
+        return Ok(Value::Bool(false));
+    }
+    match word.parse::<f64>() {
+        Ok(x) => return Ok(Value::Number(x)),
+        Err(_) => {
+            if word.is_empty() {
+                return Err(format!("unexpected '{}'", c));
+            }
+            return Err(format!("'{}' is not a valid value", word));
+        }
+    }
+}
+
+fn is_valid_key(key: &str) -> bool {
+    return !key.is_empty() &&
+           key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+}
+
+pub fn parse(text: &str) -> Result<Vec<Table>, ParseError> {
+    let mut tables: Vec<Table> = vec![Table {
+        name: String::new(),
+        is_array: false,
+        line: 0,
+        entries: Vec::new()
+    }];
+    // Plain [tables] can only be declared once
+    let mut declared: Vec<String> = Vec::new();
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut i = 0;
+    while i < lines.len() {
+        let line_number = i + 1;
+        let mut logical = strip_comment(lines[i]).trim().to_string();
+        i = i + 1;
+
+        if logical.is_empty() {
+            continue;
+        }
+
+        if logical.starts_with("[[") {
+            if !logical.ends_with("]]") {
+                return Err(ParseError { line: line_number,
+                                        message: "expected ']]' at the end of the header".to_string() });
+            }
+            let name = logical[2..logical.len() - 2].trim().to_string();
+            if !is_valid_key(&name) {
+                return Err(ParseError { line: line_number,
+                                        message: format!("invalid table name '{}'", name) });
+            }
+            tables.push(Table { name: name, is_array: true, line: line_number, entries: Vec::new() });
+            continue;
+        }
+
+        if logical.starts_with("[") {
+            if !logical.ends_with("]") {
+                return Err(ParseError { line: line_number,
+                                        message: "expected ']' at the end of the header".to_string() });
+            }
+            let name = logical[1..logical.len() - 1].trim().to_string();
+            if !is_valid_key(&name) {
+                return Err(ParseError { line: line_number,
+                                        message: format!("invalid table name '{}'", name) });
+            }
+            if declared.contains(&name) {
+                return Err(ParseError { line: line_number,
+                                        message: format!("table [{}] is declared twice", name) });
+            }
+            declared.push(name.clone());
+            tables.push(Table { name: name, is_array: false, line: line_number, entries: Vec::new() });
+            continue;
+        }
+
+        // Arrays may continue on the next lines
+        while open_brackets(&logical) > 0 && i < lines.len() {
+            logical.push(' ');
+            logical.push_str(strip_comment(lines[i]).trim());
+            i = i + 1;
+        }
+
+        let (key, value_text) = match logical.find('=') {
+            Some(idx) => (logical[..idx].trim().to_string(), logical[idx + 1..].to_string()),
+            None => return Err(ParseError { line: line_number,
+                                            message: "expected 'key = value'".to_string() })
+        };
+        if !is_valid_key(&key) {
+            return Err(ParseError { line: line_number,
+                                    message: format!("invalid key '{}'", key) });
+        }
+
+        let chars: Vec<char> = value_text.chars().collect();
+        let mut pos = 0;
+        let value = match parse_value(&chars, &mut pos) {
+            Ok(value) => value,
+            Err(message) => return Err(ParseError {
+                line: line_number,
+                message: format!("field '{}': {}", key, message)
+            })
+        };
+        skip_whitespace(&chars, &mut pos);
+        if pos < chars.len() {
+            return Err(ParseError { line: line_number,
+                                    message: format!("field '{}': unexpected text after the value", key) });
+        }
+
+        let table = tables.last_mut().unwrap();
+        if table.get(&key).is_some() {
+            return Err(ParseError { line: line_number,
+                                    message: format!("field '{}' is set twice", key) });
+        }
+        table.entries.push(Entry { key: key, value: value, line: line_number });
+    }
+
+    return Ok(tables);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_ok(text: &str) -> Vec<Table> {
+        match parse(text) {
+            Ok(tables) => return tables,
+            Err(e) => panic!("unexpected error: {}", e)
+        }
+    }
+
+    // Line and message of the error the text must fail with
+    fn parse_err(text: &str) -> (usize, String) {
+        match parse(text) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => return (e.line, e.message)
+        }
+    }
+
+    fn number(value: &Value) -> f64 {
+        match value {
+            &Value::Number(x) => return x,
+            other => panic!("expected a number, found {}", other.type_name())
+        }
+    }
+
+    fn string(value: &Value) -> &str {
+        match value {
+            &Value::Str(ref s) => return s,
+            other => panic!("expected a string, found {}", other.type_name())
+        }
+    }
+
+    fn array(value: &Value) -> &[Value] {
+        match value {
+            &Value::Array(ref values) => return values,
+            other => panic!("expected an array, found {}", other.type_name())
+        }
+    }
+
+    #[test]
+    fn scalars() {
+        let tables = parse_ok("a = 1\nb = -2.5e1\nc = 1_000\nd = true\ne = false");
+        let top = &tables[0];
+        assert_eq!(number(&top.get("a").unwrap().value), 1.0);
+        assert_eq!(number(&top.get("b").unwrap().value), -25.0);
+        assert_eq!(number(&top.get("c").unwrap().value), 1000.0);
+        match top.get("d").unwrap().value {
+            Value::Bool(true) => {},
+            ref other => panic!("expected true, found {}", other.type_name())
+        }
+        match top.get("e").unwrap().value {
+            Value::Bool(false) => {},
+            ref other => panic!("expected false, found {}", other.type_name())
+        }
+        assert_eq!(top.get("e").unwrap().line, 5);
+    }
+
+    #[test]
+    fn strings() {
+        let tables = parse_ok("a = \"plain\"\n\
+                               b = \"tab\\tquote\\\" backslash\\\\ newline\\n\"\n\
+                               c = \"# not a comment\" # a comment\n\
+                               d = \"\"");
+        let top = &tables[0];
+        assert_eq!(string(&top.get("a").unwrap().value), "plain");
+        assert_eq!(string(&top.get("b").unwrap().value), "tab\tquote\" backslash\\ newline\n");
+        assert_eq!(string(&top.get("c").unwrap().value), "# not a comment");
+        assert_eq!(string(&top.get("d").unwrap().value), "");
+    }
+
+    #[test]
+    fn arrays() {
+        let tables = parse_ok("a = [1, 2, 3]\n\
+                               b = []\n\
+                               c = [[1, 2], [\"x\", \"]\"]]\n\
+                               d = [1, 2,]\n\
+                               e = [\n\
+                                   1, # first\n\
+                                   \"[\",\n\
+                               ]\n\
+                               f = 0");
+        let top = &tables[0];
+
+        let a = array(&top.get("a").unwrap().value);
+        assert_eq!(a.len(), 3);
+        assert_eq!(number(&a[2]), 3.0);
+
+        assert_eq!(array(&top.get("b").unwrap().value).len(), 0);
+
+        let c = array(&top.get("c").unwrap().value);
+        assert_eq!(c.len(), 2);
+        assert_eq!(number(&array(&c[0])[1]), 2.0);
+        assert_eq!(string(&array(&c[1])[1]), "]");
+
+        assert_eq!(array(&top.get("d").unwrap().value).len(), 2);
+
+        // A multiline array keeps the line it starts on
+        let e = top.get("e").unwrap();
+        assert_eq!(e.line, 5);
+        let values = array(&e.value);
+        assert_eq!(values.len(), 2);
+        assert_eq!(number(&values[0]), 1.0);
+        assert_eq!(string(&values[1]), "[");
+
+        assert_eq!(top.get("f").unwrap().line, 9);
+    }
+
+    #[test]
+    fn tables() {
+        let tables = parse_ok("# comment\n\
+                               \n\
+                               [render]\n\
+                               width = 10\n\
+                               [ camera ]\n\
+                               fov = 45");
+        assert_eq!(tables.len(), 3);
+        assert_eq!(tables[0].header(), "top level");
+        assert!(tables[0].entries.is_empty());
+
+        assert_eq!(tables[1].name, "render");
+        assert!(!tables[1].is_array);
+        assert_eq!(tables[1].line, 3);
+        assert_eq!(tables[1].header(), "[render]");
+        assert_eq!(number(&tables[1].get("width").unwrap().value), 10.0);
+        assert!(tables[1].get("fov").is_none());
+
+        assert_eq!(tables[2].name, "camera");
+        assert_eq!(tables[2].line, 5);
+        assert_eq!(number(&tables[2].get("fov").unwrap().value), 45.0);
+    }
+
+    #[test]
+    fn arrays_of_tables() {
+        let tables = parse_ok("[[sphere]]\n\
+                               radius = 1\n\
+                               [[sphere]]\n\
+                               radius = 2\n\
+                               [[light]]");
+        assert_eq!(tables.len(), 4);
+        for (table, (name, line)) in tables[1..].iter().zip(vec![("sphere", 1), ("sphere", 3), ("light", 5)]) {
+            assert_eq!(table.name, name);
+            assert!(table.is_array);
+            assert_eq!(table.line, line);
+        }
+        assert_eq!(tables[1].header(), "[[sphere]]");
+        assert_eq!(number(&tables[1].get("radius").unwrap().value), 1.0);
+        assert_eq!(number(&tables[2].get("radius").unwrap().value), 2.0);
+        assert!(tables[3].entries.is_empty());
+    }
+
+    #[test]
+    fn malformed_input() {
+        let cases: Vec<(&str, usize, &str)> = vec![
+            ("a = 1\n\"b\" = 2", 2, "invalid key '\"b\"'"),
+            ("a = 1\nb", 2, "expected 'key = value'"),
+            ("a = ", 1, "field 'a': missing value"),
+            ("\na = \"open", 2, "field 'a': unterminated string"),
+            ("a = \"\\q\"", 1, "field 'a': unknown escape sequence \\q"),
+            ("a = 1\nb = [1,\n2\n\nc = 3", 2, "field 'b': expected ',' or ']' in array, found 'c'"),
+            ("a = [1,\n2", 1, "field 'a': unterminated array"),
+            ("a = [1 2]", 1, "field 'a': expected ',' or ']' in array, found '2'"),
+            ("a = 1 2", 1, "field 'a': unexpected text after the value"),
+            ("a = yes", 1, "field 'a': 'yes' is not a valid value"),
+            ("a = {}", 1, "field 'a': unexpected '{'"),
+            ("[a]\nb = 1\nb = 2", 3, "field 'b' is set twice"),
+            ("[a]\n[b]\n[a]", 3, "table [a] is declared twice"),
+            ("\n\n[a", 3, "expected ']' at the end of the header"),
+            ("[[a]", 1, "expected ']]' at the end of the header"),
+            ("[a b]", 1, "invalid table name 'a b'"),
+            ("[[]]", 1, "invalid table name ''")
+        ];
+        for (text, line, message) in cases {
+            let (error_line, error_message) = parse_err(text);
+            assert_eq!((error_line, error_message.as_str()), (line, message), "parsing {:?}", text);
+        }
+    }
+
+    #[test]
+    fn arrays_of_tables_can_repeat_keys() {
+        let tables = parse_ok("[[a]]\nx = 1\n[[a]]\nx = 2");
+        assert_eq!(tables.len(), 3);
+    }
+
+    #[test]
+    fn error_display() {
+        let error = ParseError { line: 4, message: "oops".to_string() };
+        assert_eq!(error.to_string(), "line 4: oops");
+    }
+}
@@ -0,0 +1,156 @@
+use tracer::lights::{Light, LightSample};
+use tracer::primitives::Primitive;
+use tracer::primitives::{CanSample, HasIntersection, HasMaterial};
+use tracer::utils::color::Color;
+use tracer::utils::intersection::Intersection;
+use tracer::utils::sampling::Distribution1D;
+
+use nalgebra::{Point3, Vector3};
+
+use std::f32::consts::PI;
+
+// Any number of primitives emitting the same radiance (the triangles of a
+// mesh for instance). A primitive is picked proportionally to its area, then
+// a point on it as seen from the lit point
+pub struct AreaLight {
+   pub primitives: Vec<Primitive>,
+   pub radiance: Color,
+   area: f32,
+   // Picks a primitive proportionally to its area
+   distribution: Distribution1D
+}
+
+impl AreaLight {
+    // Fails without primitives or when their total area is 0 or infinite,
+    // there would be nothing to sample
+    pub fn new(primitives: Vec<Primitive>, radiance: Color) -> Result<AreaLight, String> {
+        let areas: Vec<f32> = primitives.iter().map(|p| p.get_area()).collect();
+        let area: f32 = areas.iter().sum();
+        if primitives.is_empty() {
+            return Err("an area light needs at least one primitive".to_string());
+        }
+        if !(area > 0.0 && area.is_finite()) {
+            return Err(format!("an area light needs a positive and finite area, not {}", area));
+        }
+        return Ok(AreaLight {
+            primitives: primitives,
+            radiance: radiance,
+            area: area,
+            distribution: Distribution1D::new(&areas)
+        });
+    }
+}
+
+impl Light for AreaLight {
+    fn sample_li(&self, inter: &Intersection, u: f32, v: f32) -> Option<LightSample> {
+        let (index, pmf, u) = self.distribution.sample_discrete(u);
+        if pmf <= 0.0 {
+            return None;
+        }
+        let primitive = &self.primitives[index];
+        let p: Point3<f32> = primitive.get_sample_from(&inter.point, u, v);
+        let to_light: Vector3<f32> = p - inter.point;
+        let ray = inter.spawn_ray(&to_light);
+
+        // The sampled point itself, the density is the one of this primitive.
+        // Other primitives of the light in front of it are left to the
+        // shadow ray like any occluder
+        let mut light_inter = match primitive.get_intersection(&ray) {
+            Some(light_inter) => light_inter,
+            None => return None
+        };
+        light_inter.primitive_index = index;
+        let material = primitive.get_material();
+        let radiance: Color = material.emitted(&-ray.direction, &light_inter);
+        if radiance.is_black() {
+            return None;
+        }
+
+        let pdf = self.pdf_li(&inter.point, &light_inter);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        return Some(LightSample {
+            radiance: radiance,
+            wi: ray.direction,
+            distance: light_inter.distance,
+            pdf: pdf
+        });
+    }
+
+    fn pdf_li(&self, p: &Point3<f32>, light_inter: &Intersection) -> f32 {
+        let i = light_inter.primitive_index;
+        return self.distribution.pmf(i) * self.primitives[i].get_pdf_from(p, light_inter);
+    }
+
+    fn is_delta(&self) -> bool {
+        return false;
+    }
+
+    // Flux leaving the front side of the primitives
+    #[allow(unused_variables)]
+    fn power(&self, scene_radius: f32) -> f32 {
+        return self.radiance.luminance() * self.area * PI;
+    }
+
+    fn area(&self) -> f32 {
+        return self.area;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tracer::materials::{Material, Emissive};
+    use tracer::primitives::triangle::Triangle;
+    use tracer::primitives::plane::Plane;
+    use tracer::utils::ray::Ray;
+
+    use nalgebra::Point2;
+    use nalgebra::core::Unit;
+
+    use std::sync::Arc;
+
+    fn triangle(v2: Point3<f32>) -> Primitive {
+        let material: Arc<Material> = Arc::new(Emissive::new(Color::new(1.0, 1.0, 1.0)));
+        return Primitive::Triangle(Box::new(Triangle::new(Point3::new(0.0, 0.0, 0.0),
+                                                          Point3::new(1.0, 0.0, 0.0),
+                                                          v2,
+                                                          material)));
+    }
+
+    #[test]
+    fn lights_need_an_area() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        assert!(AreaLight::new(Vec::new(), white).is_err());
+        // Every point on one line
+        assert!(AreaLight::new(vec![triangle(Point3::new(2.0, 0.0, 0.0))], white).is_err());
+
+        let material: Arc<Material> = Arc::new(Emissive::new(white));
+        let plane = Primitive::Plane(Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0),
+                                                         Vector3::new(0.0, 1.0, 0.0),
+                                                         material)));
+        assert!(AreaLight::new(vec![plane], white).is_err());
+
+        let light = AreaLight::new(vec![triangle(Point3::new(0.0, 2.0, 0.0)),
+                                        triangle(Point3::new(2.0, 0.0, 0.0))], white).unwrap();
+        assert_eq!(light.area(), 1.0);
+    }
+
+    #[test]
+    fn degenerate_primitives_are_never_sampled() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let light = AreaLight::new(vec![triangle(Point3::new(2.0, 0.0, 0.0)),
+                                        triangle(Point3::new(0.0, 2.0, 0.0))], white).unwrap();
+        // A point above the light, facing it
+        let ray = Ray::new(Point3::new(0.2, 0.2, 2.0), Vector3::new(0.0, 0.0, -1.0));
+        let inter = Intersection::new(&ray, 1.0, Unit::new_normalize(Vector3::new(0.0, 0.0, -1.0)),
+                                      Point2::new(0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        for i in 0..20 {
+            let sample = light.sample_li(&inter, i as f32 / 20.0, 0.3).unwrap();
+            assert!(sample.pdf > 0.0 && sample.pdf.is_finite());
+        }
+    }
+}
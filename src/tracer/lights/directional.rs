@@ -0,0 +1,47 @@
+use tracer::lights::{Light, LightSample};
+use tracer::utils::color::Color;
+use tracer::utils::intersection::Intersection;
+
+use nalgebra::Vector3;
+use nalgebra::core::Unit;
+
+use std::f32;
+use std::f32::consts::PI;
+
+// Light from infinitely far away arriving along a single direction, like the
+// sun. irradiance is what a surface facing the light receives
+pub struct DirectionalLight {
+    // Direction the light travels in
+    pub direction: Unit<Vector3<f32>>,
+    pub irradiance: Color
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vector3<f32>, irradiance: Color) -> DirectionalLight {
+        return DirectionalLight {
+            direction: Unit::new_normalize(direction),
+            irradiance: irradiance
+        };
+    }
+}
+
+#[allow(unused_variables)]
+impl Light for DirectionalLight {
+    fn sample_li(&self, inter: &Intersection, u: f32, v: f32) -> Option<LightSample> {
+        return Some(LightSample {
+            radiance: self.irradiance,
+            wi: -self.direction,
+            distance: f32::INFINITY,
+            pdf: 1.0
+        });
+    }
+
+    fn is_delta(&self) -> bool {
+        return true;
+    }
+
+    // Flux through a disk covering the scene
+    fn power(&self, scene_radius: f32) -> f32 {
+        return self.irradiance.luminance() * PI * scene_radius * scene_radius;
+    }
+}
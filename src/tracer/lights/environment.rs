@@ -0,0 +1,126 @@
+use tracer::lights::{Light, LightSample};
+use tracer::materials::texture::Image;
+use tracer::utils::color::Color;
+use tracer::utils::intersection::Intersection;
+use tracer::utils::ray::Ray;
+use tracer::utils::sampling::Distribution2D;
+use tracer::utils::transform::Transform;
+
+use nalgebra::{Point2, Vector3};
+use nalgebra::core::Unit;
+
+use std::cmp::min;
+use std::f32;
+use std::f32::consts::PI;
+
+// Light arriving from infinitely far away in every direction, given by an
+// equirectangular image: u follows the angle around the y axis, the top row
+// of the image is straight up. Directions are importance sampled by the
+// luminance of the image
+pub struct EnvironmentLight {
+    pub image: Image,
+    pub intensity: f32,
+    // From the space of the image to the world
+    pub rotation: Transform,
+    // Over the image, row 0 at the top
+    distribution: Distribution2D,
+    mean_luminance: f32
+}
+
+impl EnvironmentLight {
+    pub fn new(image: Image, intensity: f32, rotation: Transform) -> EnvironmentLight {
+        let (width, height) = (image.width as usize, image.height as usize);
+        let luminances: Vec<f32> = image.texels.iter().map(|c| c.luminance().max(0.0)).collect();
+        // Bilinear lookups inside a texel also see its neighbours, a texel
+        // next to a bright one must be sampled as if it were as bright. Rows
+        // near the poles cover a smaller solid angle
+        let mut weights: Vec<f32> = Vec::with_capacity(width * height);
+        let mut total = 0.0;
+        for y in 0..height {
+            let sin_theta = (PI * (y as f32 + 0.5) / height as f32).sin();
+            for x in 0..width {
+                let mut brightest: f32 = 0.0;
+                for ny in y.saturating_sub(1)..min(y + 2, height) {
+                    for dx in 0..3 {
+                        let nx = (x + width + dx - 1) % width;
+                        brightest = brightest.max(luminances[ny * width + nx]);
+                    }
+                }
+                weights.push(brightest * sin_theta);
+                total += luminances[y * width + x] * sin_theta;
+            }
+        }
+        // Sum of the sin weights over the rows is 2 * height / PI
+        let mean_luminance = total * PI / (2.0 * (width * height) as f32);
+
+        return EnvironmentLight {
+            distribution: Distribution2D::new(&weights, width, height),
+            image: image,
+            intensity: intensity,
+            rotation: rotation,
+            mean_luminance: mean_luminance
+        };
+    }
+
+    // Position in [0, 1)² of the distribution, v going down from the top
+    fn direction_to_st(&self, w: &Vector3<f32>) -> Point2<f32> {
+        let local: Vector3<f32> = self.rotation.inverse().transform_vector(w).normalize();
+        let theta = local.y.max(-1.0).min(1.0).acos();
+        let mut phi = local.z.atan2(local.x);
+        if phi < 0.0 {
+            phi += 2.0 * PI;
+        }
+        return Point2::new(phi / (2.0 * PI), theta / PI);
+    }
+
+    fn radiance(&self, st: &Point2<f32>) -> Color {
+        return self.image.lookup(&Point2::new(st.x, 1.0 - st.y)) * self.intensity;
+    }
+}
+
+#[allow(unused_variables)]
+impl Light for EnvironmentLight {
+    fn sample_li(&self, inter: &Intersection, u: f32, v: f32) -> Option<LightSample> {
+        let (st, map_pdf) = self.distribution.sample(u, v);
+        if map_pdf <= 0.0 {
+            return None;
+        }
+        let theta = st.y * PI;
+        let phi = st.x * 2.0 * PI;
+        let sin_theta = theta.sin();
+        if sin_theta <= 0.0 {
+            return None;
+        }
+
+        let local = Vector3::new(sin_theta * phi.cos(), theta.cos(), sin_theta * phi.sin());
+        return Some(LightSample {
+            radiance: self.radiance(&st),
+            wi: Unit::new_normalize(self.rotation.transform_vector(&local)),
+            distance: f32::INFINITY,
+            pdf: map_pdf / (2.0 * PI * PI * sin_theta)
+        });
+    }
+
+    fn pdf_le(&self, wi: &Unit<Vector3<f32>>) -> f32 {
+        let st = self.direction_to_st(wi.as_ref());
+        let sin_theta = (st.y * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        return self.distribution.pdf(&st) / (2.0 * PI * PI * sin_theta);
+    }
+
+    fn le(&self, ray: &Ray) -> Color {
+        return self.radiance(&self.direction_to_st(ray.direction.as_ref()));
+    }
+
+    fn is_delta(&self) -> bool {
+        return false;
+    }
+
+    // Flux through a disk covering the scene, which receives an irradiance
+    // of PI times the mean radiance
+    fn power(&self, scene_radius: f32) -> f32 {
+        return PI * PI * scene_radius * scene_radius * self.mean_luminance * self.intensity;
+    }
+}
@@ -0,0 +1,150 @@
+pub mod area;
+pub mod point;
+pub mod spot;
+pub mod directional;
+pub mod environment;
+pub mod sky;
+
+pub use tracer::lights::area::AreaLight;
+pub use tracer::lights::point::PointLight;
+pub use tracer::lights::spot::SpotLight;
+pub use tracer::lights::directional::DirectionalLight;
+pub use tracer::lights::environment::EnvironmentLight;
+pub use tracer::lights::sky::PreethamSky;
+
+use tracer::utils::color::Color;
+use tracer::utils::intersection::Intersection;
+use tracer::utils::ray::Ray;
+use tracer::utils::sampling::Distribution1D;
+
+use nalgebra::{Point3, Vector3};
+use nalgebra::core::Unit;
+
+use std::collections::HashMap;
+
+// Light arriving at a point, chosen by Light::sample_li
+pub struct LightSample {
+    // Radiance arriving along wi, for delta lights what the point receives
+    pub radiance: Color,
+    // Direction toward the light
+    pub wi: Unit<Vector3<f32>>,
+    // Distance to the light, infinite for lights infinitely far away
+    pub distance: f32,
+    // Solid angle density, 1 for delta lights
+    pub pdf: f32
+}
+
+pub trait Light: Send + Sync {
+    fn sample_li(&self, inter: &Intersection, u: f32, v: f32) -> Option<LightSample>;
+
+    // Solid angle density of sample_li picking light_inter from p, 0 for
+    // delta lights since rays cannot hit them
+    #[allow(unused_variables)]
+    fn pdf_li(&self, p: &Point3<f32>, light_inter: &Intersection) -> f32 {
+        return 0.0;
+    }
+
+    // Radiance brought by a ray that leaves the scene, for lights at
+    // infinity that rays can reach
+    #[allow(unused_variables)]
+    fn le(&self, ray: &Ray) -> Color {
+        return Color::new_black();
+    }
+
+    // Solid angle density of sample_li picking the direction wi, for the
+    // lights returning something from le
+    #[allow(unused_variables)]
+    fn pdf_le(&self, wi: &Unit<Vector3<f32>>) -> f32 {
+        return 0.0;
+    }
+
+    // Lights with a single position or direction, only reachable through
+    // sample_li
+    fn is_delta(&self) -> bool;
+
+    // Total emitted flux, lights at infinity cover the scene bounding sphere
+    fn power(&self, scene_radius: f32) -> f32;
+
+    // Emitting area, 0 for delta lights and lights at infinity
+    fn area(&self) -> f32 {
+        return 0.0;
+    }
+}
+
+#[allow(dead_code)]
+pub enum LightSelection {
+    // Bright lights are sampled more often, usually the better choice
+    Power,
+    // Lights without an area (delta lights, lights at infinity) are picked as
+    // often as an average area light
+    Area
+}
+
+// All the lights of a scene, light sampling first picks one of them
+pub struct LightSet {
+    pub lights: Vec<Box<Light>>,
+    distribution: Distribution1D,
+    // Light and light primitive of the emitters also in the BVH, by object
+    // and primitive index of their hits
+    emitters: HashMap<(usize, usize), (usize, usize)>
+}
+
+impl LightSet {
+    pub fn new(lights: Vec<Box<Light>>, selection: LightSelection, scene_radius: f32) -> LightSet {
+        let weights: Vec<f32> = match selection {
+            LightSelection::Power => lights.iter().map(|l| l.power(scene_radius)).collect(),
+            LightSelection::Area => {
+                let areas: Vec<f32> = lights.iter().filter(|l| l.area() > 0.0).map(|l| l.area()).collect();
+                let mean_area = if areas.is_empty() {
+                    1.0
+                }
+                else {
+                    areas.iter().sum::<f32>() / areas.len() as f32
+                };
+                lights.iter().map(|l| if l.area() > 0.0 { l.area() } else { mean_area }).collect()
+            }
+        };
+        return LightSet {
+            lights: lights,
+            distribution: Distribution1D::new(&weights),
+            emitters: HashMap::new()
+        };
+    }
+
+    // Index of the light, its probability and u rescaled to [0, 1)
+    pub fn select(&self, u: f32) -> (usize, f32, f32) {
+        return self.distribution.sample_discrete(u);
+    }
+
+    // Probability of select returning index
+    pub fn pmf(&self, index: usize) -> f32 {
+        return self.distribution.pmf(index);
+    }
+
+    // Radiance of the lights at infinity along a ray leaving the scene
+    pub fn le(&self, ray: &Ray) -> Color {
+        let mut radiance = Color::new_black();
+        for light in self.lights.iter() {
+            radiance += light.le(ray);
+        }
+        return radiance;
+    }
+
+    // Tells that the primitive at primitive_index in the object at
+    // object_index of the top level BVH is the primitive at
+    // light_primitive_index of the light at light_index
+    pub fn add_emitter(&mut self, object_index: usize, primitive_index: usize,
+                       light_index: usize, light_primitive_index: usize) {
+        self.emitters.insert((object_index, primitive_index), (light_index, light_primitive_index));
+    }
+
+    // Light hit by the ray that found inter and the same hit as seen by that
+    // light, None for emitters that are not in the lights
+    pub fn find_emitter(&self, inter: &Intersection) -> Option<(usize, Intersection)> {
+        return self.emitters.get(&(inter.object_index, inter.primitive_index)).map(|&(light_index, index)| {
+            let mut light_inter = inter.clone();
+            light_inter.primitive_index = index;
+            (light_index, light_inter)
+        });
+    }
+}
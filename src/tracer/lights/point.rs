@@ -0,0 +1,49 @@
+use tracer::lights::{Light, LightSample};
+use tracer::utils::color::Color;
+use tracer::utils::intersection::Intersection;
+
+use nalgebra::{Point3, Vector3};
+use nalgebra::core::Unit;
+
+use std::f32::consts::PI;
+
+// Emits the same intensity in every direction from a single point
+pub struct PointLight {
+    pub position: Point3<f32>,
+    pub intensity: Color
+}
+
+impl PointLight {
+    pub fn new(position: Point3<f32>, intensity: Color) -> PointLight {
+        return PointLight {
+            position: position,
+            intensity: intensity
+        };
+    }
+}
+
+#[allow(unused_variables)]
+impl Light for PointLight {
+    fn sample_li(&self, inter: &Intersection, u: f32, v: f32) -> Option<LightSample> {
+        let to_light: Vector3<f32> = self.position - inter.point;
+        let distance_squared = to_light.norm_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+
+        return Some(LightSample {
+            radiance: self.intensity / distance_squared,
+            wi: Unit::new_normalize(to_light),
+            distance: distance_squared.sqrt(),
+            pdf: 1.0
+        });
+    }
+
+    fn is_delta(&self) -> bool {
+        return true;
+    }
+
+    fn power(&self, scene_radius: f32) -> f32 {
+        return 4.0 * PI * self.intensity.luminance();
+    }
+}
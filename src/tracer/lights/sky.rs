@@ -0,0 +1,178 @@
+use tracer::materials::texture::Image;
+use tracer::utils::color::Color;
+
+use nalgebra::Vector3;
+use nalgebra::core::Unit;
+
+use std::cmp::max;
+use std::f32::consts::PI;
+
+// The model gives luminances in kcd/m², a white surface lit by the sun at 45
+// degrees comes out close to 1 with this scale
+const LUMINANCE_SCALE: f32 = 0.04;
+// Angular radius of the sun, in radians
+const SUN_RADIUS: f32 = 0.00465;
+// Luminance of the sun before it goes through the atmosphere, in kcd/m²
+const SUN_LUMINANCE: f32 = 1.9e6;
+// Sub samples per side of the texels touching the sun disk
+const SUN_SUPERSAMPLING: u32 = 8;
+
+// Coefficients of the Perez distribution function
+struct Perez {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32
+}
+
+impl Perez {
+    // cos_theta is the cosine of the view direction with the zenith and gamma
+    // its angle with the sun
+    fn eval(&self, cos_theta: f32, gamma: f32) -> f32 {
+        let cos_gamma = gamma.cos();
+        return (1.0 + self.a * (self.b / cos_theta).exp()) *
+               (1.0 + self.c * (self.d * gamma).exp() + self.e * cos_gamma * cos_gamma);
+    }
+}
+
+// Clear daylight sky from "A Practical Analytic Model for Daylight" (Preetham
+// et al.), with a sun disk. The sky is y up and undefined under the horizon,
+// which repeats the color of the horizon instead
+pub struct PreethamSky {
+    pub sun_direction: Unit<Vector3<f32>>,
+    // From 2 (very clear) to about 10 (hazy)
+    pub turbidity: f32,
+    // Perez coefficients and zenith values of the luminance Y and of the
+    // chromaticity x and y
+    perez: [Perez; 3],
+    zenith: [f32; 3],
+    sun_radiance: Color
+}
+
+impl PreethamSky {
+    pub fn new(sun_direction: Vector3<f32>, turbidity: f32) -> PreethamSky {
+        let sun_direction = Unit::new_normalize(sun_direction);
+        let t = turbidity;
+        let theta_sun = sun_direction.y.max(0.0).min(1.0).acos();
+
+        let perez = [
+            Perez { a: 0.1787 * t - 1.4630, b: -0.3554 * t + 0.4275, c: -0.0227 * t + 5.3251,
+                    d: 0.1206 * t - 2.5771, e: -0.0670 * t + 0.3703 },
+            Perez { a: -0.0193 * t - 0.2592, b: -0.0665 * t + 0.0008, c: -0.0004 * t + 0.2125,
+                    d: -0.0641 * t - 0.8989, e: -0.0033 * t + 0.0452 },
+            Perez { a: -0.0167 * t - 0.2608, b: -0.0950 * t + 0.0092, c: -0.0079 * t + 0.2102,
+                    d: -0.0441 * t - 1.6537, e: -0.0109 * t + 0.0529 }
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_y = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let (t2, s, s2, s3) = (t * t, theta_sun, theta_sun * theta_sun, theta_sun * theta_sun * theta_sun);
+        let zenith_x = t2 * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s) +
+                       t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394) +
+                       (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886);
+        let zenith_chroma_y = t2 * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s) +
+                              t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516) +
+                              (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688);
+
+        return PreethamSky {
+            sun_direction: sun_direction,
+            turbidity: turbidity,
+            perez: perez,
+            zenith: [zenith_y, zenith_x, zenith_chroma_y],
+            sun_radiance: PreethamSky::attenuated_sun(theta_sun, turbidity)
+        };
+    }
+
+    // Sun light left after Rayleigh and aerosol scattering, for the
+    // wavelengths of red, green and blue
+    fn attenuated_sun(theta_sun: f32, turbidity: f32) -> Color {
+        let relative_air_mass = 1.0 / (theta_sun.cos() + 0.15 * (93.885 - theta_sun.to_degrees()).powf(-1.253));
+        let beta = 0.04608 * turbidity - 0.04586;
+        let transmittance = |lambda_um: f32| -> f32 {
+            let rayleigh = (-0.008735 * lambda_um.powf(-4.08) * relative_air_mass).exp();
+            let aerosol = (-beta * lambda_um.powf(-1.3) * relative_air_mass).exp();
+            return rayleigh * aerosol;
+        };
+        let t = Color::new(transmittance(0.68), transmittance(0.55), transmittance(0.44));
+        return t * (SUN_LUMINANCE * LUMINANCE_SCALE);
+    }
+
+    // Radiance of the sky alone along the direction w (away from the scene)
+    pub fn sky_radiance(&self, w: &Unit<Vector3<f32>>) -> Color {
+        // Under the horizon the model breaks down
+        let cos_theta = w.y.max(0.001);
+        let cos_gamma = w.dot(&self.sun_direction).max(-1.0).min(1.0);
+        let gamma = cos_gamma.acos();
+        let theta_sun = self.sun_direction.y.max(0.0).min(1.0).acos();
+
+        let mut values = [0.0; 3];
+        for i in 0..3 {
+            values[i] = self.zenith[i] * self.perez[i].eval(cos_theta, gamma) /
+                        self.perez[i].eval(1.0, theta_sun);
+        }
+        let (luminance, x, y) = (values[0] * LUMINANCE_SCALE, values[1], values[2]);
+        if luminance <= 0.0 || y <= 0.0 {
+            return Color::new_black();
+        }
+
+        // xyY to XYZ then to linear sRGB
+        let cx = x * luminance / y;
+        let cz = (1.0 - x - y) * luminance / y;
+        let cy = luminance;
+        return Color::new((3.2406 * cx - 1.5372 * cy - 0.4986 * cz).max(0.0),
+                          (-0.9689 * cx + 1.8758 * cy + 0.0415 * cz).max(0.0),
+                          (0.0557 * cx - 0.2040 * cy + 1.0570 * cz).max(0.0));
+    }
+
+    // Direction of the texel center for the equirectangular mapping of
+    // EnvironmentLight, s around the y axis and t going down from the zenith
+    fn direction(s: f32, t: f32) -> Unit<Vector3<f32>> {
+        let theta = t * PI;
+        let phi = s * 2.0 * PI;
+        return Unit::new_normalize(Vector3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin()));
+    }
+
+    // Equirectangular image of the sky and the sun. The sun covers a few
+    // texels, their coverage is estimated so the sun keeps its power
+    pub fn to_image(&self, width: u32) -> Image {
+        let height = max(width / 2, 1);
+        let texel_angle = PI / height as f32;
+        let cos_sun_radius = SUN_RADIUS.cos();
+        let sun_visible = self.sun_direction.y > -SUN_RADIUS;
+
+        let mut texels: Vec<Color> = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let s = (x as f32 + 0.5) / width as f32;
+                let t = (y as f32 + 0.5) / height as f32;
+                let w = PreethamSky::direction(s, t);
+                let mut radiance = self.sky_radiance(&w);
+
+                let gamma = w.dot(&self.sun_direction).max(-1.0).min(1.0).acos();
+                if sun_visible && gamma < SUN_RADIUS + 2.0 * texel_angle {
+                    let mut covered: u32 = 0;
+                    for j in 0..SUN_SUPERSAMPLING {
+                        for i in 0..SUN_SUPERSAMPLING {
+                            let sub_s = (x as f32 + (i as f32 + 0.5) / SUN_SUPERSAMPLING as f32) / width as f32;
+                            let sub_t = (y as f32 + (j as f32 + 0.5) / SUN_SUPERSAMPLING as f32) / height as f32;
+                            let sub_w = PreethamSky::direction(sub_s, sub_t);
+                            if sub_w.dot(&self.sun_direction) >= cos_sun_radius && sub_w.y > 0.0 {
+                                covered += 1;
+                            }
+                        }
+                    }
+                    let coverage = covered as f32 / (SUN_SUPERSAMPLING * SUN_SUPERSAMPLING) as f32;
+                    radiance += self.sun_radiance * coverage;
+                }
+                texels.push(radiance);
+            }
+        }
+
+        return Image {
+            width: width,
+            height: height,
+            texels: texels
+        };
+    }
+}
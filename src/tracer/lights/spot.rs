@@ -0,0 +1,81 @@
+use tracer::lights::{Light, LightSample};
+use tracer::utils::color::Color;
+use tracer::utils::intersection::Intersection;
+
+use nalgebra::{Point3, Vector3};
+use nalgebra::core::Unit;
+
+use std::f32::consts::PI;
+
+// Point light restricted to a cone. The intensity is full up to
+// cos_falloff_start and fades smoothly to 0 at the edge of the cone
+pub struct SpotLight {
+    pub position: Point3<f32>,
+    pub direction: Unit<Vector3<f32>>,
+    pub intensity: Color,
+    pub cos_total_width: f32,
+    pub cos_falloff_start: f32
+}
+
+impl SpotLight {
+    // angle is the half angle of the cone and falloff how much of it, at the
+    // edge, fades out, both in radians
+    pub fn new(position: Point3<f32>, direction: Vector3<f32>, intensity: Color,
+               angle: f32, falloff: f32) -> SpotLight {
+        let falloff_start = (angle - falloff).max(0.0);
+        return SpotLight {
+            position: position,
+            direction: Unit::new_normalize(direction),
+            intensity: intensity,
+            cos_total_width: angle.cos(),
+            cos_falloff_start: falloff_start.cos()
+        };
+    }
+
+    // Fraction of the intensity emitted along w (leaving the light)
+    fn falloff(&self, w: &Vector3<f32>) -> f32 {
+        let cos_theta = self.direction.dot(w);
+        if cos_theta < self.cos_total_width {
+            return 0.0;
+        }
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        let delta = (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+        return delta * delta * (3.0 - 2.0 * delta);
+    }
+}
+
+#[allow(unused_variables)]
+impl Light for SpotLight {
+    fn sample_li(&self, inter: &Intersection, u: f32, v: f32) -> Option<LightSample> {
+        let to_light: Vector3<f32> = self.position - inter.point;
+        let distance_squared = to_light.norm_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+
+        let wi = Unit::new_normalize(to_light);
+        let falloff = self.falloff(&-wi.as_ref());
+        if falloff <= 0.0 {
+            return None;
+        }
+
+        return Some(LightSample {
+            radiance: self.intensity * (falloff / distance_squared),
+            wi: wi,
+            distance: distance_squared.sqrt(),
+            pdf: 1.0
+        });
+    }
+
+    fn is_delta(&self) -> bool {
+        return true;
+    }
+
+    // The falloff is counted as half of its band
+    fn power(&self, scene_radius: f32) -> f32 {
+        return self.intensity.luminance() * 2.0 * PI *
+               (1.0 - 0.5 * (self.cos_falloff_start + self.cos_total_width));
+    }
+}
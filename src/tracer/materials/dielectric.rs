@@ -0,0 +1,189 @@
+use tracer::materials::{Material, BsdfSample};
+use tracer::utils::color::Color;
+use tracer::utils::intersection::Intersection;
+use tracer::utils::sampling::reflect;
+
+use nalgebra::Vector3;
+use nalgebra::core::Unit;
+
+// Smooth glass or water, reflects or refracts according to the Fresnel
+// equations. The outside of the object is assumed to be air (ior of 1)
+pub struct Dielectric {
+    pub ior: f32
+}
+
+impl Dielectric {
+    pub fn new(ior: f32) -> Dielectric {
+        return Dielectric {
+            ior: ior
+        };
+    }
+}
+
+// Unpolarized reflectance for cosines measured on each side of the interface,
+// eta being the ratio of the incident side ior over the transmitted side ior
+fn fresnel_dielectric(cos_i: f32, cos_t: f32, eta: f32) -> f32 {
+    let r_parallel = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let r_perpendicular = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    return 0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular);
+}
+
+#[allow(unused_variables)]
+impl Material for Dielectric {
+    fn eval(&self, wo: &Unit<Vector3<f32>>, wi: &Unit<Vector3<f32>>,
+            inter: &Intersection) -> Color {
+        return Color::new_black();
+    }
+
+    // u picks between reflection and refraction with the Fresnel reflectance
+    // as probability. Values are divided by the cosine like for a mirror
+    fn sample(&self, wo: &Unit<Vector3<f32>>, inter: &Intersection,
+              u: f32, v: f32) -> Option<BsdfSample> {
+        let n = inter.facing_normal(wo);
+        let eta = if inter.front_face { 1.0 / self.ior } else { self.ior };
+
+        let cos_i: f32 = n.dot(wo).min(1.0);
+        if cos_i <= 0.0 {
+            return None;
+        }
+        let sin2_t: f32 = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+
+        // Total internal reflection
+        if sin2_t >= 1.0 {
+            let wi = Unit::new_normalize(reflect(wo, &n));
+            if !inter.same_side(wo, &wi) {
+                return None;
+            }
+            return Some(BsdfSample {
+                wi: wi,
+                value: Color::new(1.0, 1.0, 1.0) / cos_i,
+                pdf: 1.0,
+                is_specular: true
+            });
+        }
+
+        let cos_t: f32 = (1.0 - sin2_t).sqrt();
+        let f = fresnel_dielectric(cos_i, cos_t, eta);
+        if u < f {
+            let wi = Unit::new_normalize(reflect(wo, &n));
+            if !inter.same_side(wo, &wi) {
+                return None;
+            }
+            return Some(BsdfSample {
+                wi: wi,
+                value: Color::new(f, f, f) / cos_i,
+                pdf: f,
+                is_specular: true
+            });
+        }
+
+        // Radiance is compressed into a smaller solid angle when entering a
+        // denser medium, hence the eta² factor
+        let wi = Unit::new_normalize(-eta * wo.as_ref() + (eta * cos_i - cos_t) * n.as_ref());
+        if inter.same_side(wo, &wi) {
+            return None;
+        }
+        let t = (1.0 - f) * eta * eta;
+        return Some(BsdfSample {
+            wi: wi,
+            value: Color::new(t, t, t) / cos_t,
+            pdf: 1.0 - f,
+            is_specular: true
+        });
+    }
+
+    fn pdf(&self, wo: &Unit<Vector3<f32>>, wi: &Unit<Vector3<f32>>,
+           inter: &Intersection) -> f32 {
+        return 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tracer::utils::ray::Ray;
+
+    use nalgebra::{Point2, Point3};
+
+    // Hit at the origin of the z = 0 surface facing +z by a ray going along
+    // direction, from outside when it goes down. Returns wo too
+    fn hit(direction: Vector3<f32>) -> (Intersection, Unit<Vector3<f32>>) {
+        let direction = Unit::new_normalize(direction);
+        let ray = Ray::new(Point3::origin() - direction.as_ref(), direction.unwrap());
+        let inter = Intersection::new(&ray, 1.0, Unit::new_normalize(Vector3::new(0.0, 0.0, 1.0)),
+                                      Point2::new(0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        return (inter, -direction);
+    }
+
+    // Throughput of a sample as the path tracer applies it
+    fn weight(sample: &BsdfSample) -> f32 {
+        return sample.value.red * sample.wi.z.abs() / sample.pdf;
+    }
+
+    #[test]
+    fn fresnel_reflectance() {
+        // Head on, ((n1 - n2) / (n1 + n2))² from either side
+        assert!((fresnel_dielectric(1.0, 1.0, 1.0 / 1.5) - 0.04).abs() < 1e-6);
+        assert!((fresnel_dielectric(1.0, 1.0, 1.5) - 0.04).abs() < 1e-6);
+
+        // The same for light going the other way
+        let cos_i = 30f32.to_radians().cos();
+        let sin_t = 30f32.to_radians().sin() / 1.5;
+        let cos_t = (1.0 - sin_t * sin_t).sqrt();
+        let outside = fresnel_dielectric(cos_i, cos_t, 1.0 / 1.5);
+        assert!((outside - fresnel_dielectric(cos_t, cos_i, 1.5)).abs() < 1e-6);
+        assert!(outside > 0.04 && outside < 0.1, "reflectance {}", outside);
+
+        // Everything is reflected at grazing angles
+        assert!((fresnel_dielectric(0.0, (1.0 - 1.0 / 2.25f32).sqrt(), 1.0 / 1.5) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn refracts_with_snell_law() {
+        let glass = Dielectric::new(1.5);
+        let (inter, wo) = hit(Vector3::new(30f32.to_radians().sin(), 0.0, -30f32.to_radians().cos()));
+        let sample = glass.sample(&wo, &inter, 0.99, 0.5).unwrap();
+        assert!(sample.is_specular);
+        assert!(sample.wi.z < 0.0);
+        assert!((sample.wi.x - 30f32.to_radians().sin() / 1.5).abs() < 1e-5, "refracted {:?}", sample.wi);
+        assert_eq!(sample.wi.y, 0.0);
+        // Radiance is compressed by eta² entering the glass
+        assert!((weight(&sample) - 1.0 / 2.25).abs() < 1e-5, "weight {}", weight(&sample));
+
+        let sample = glass.sample(&wo, &inter, 0.01, 0.5).unwrap();
+        assert!((sample.wi.x + wo.x).abs() < 1e-6 && (sample.wi.z - wo.z).abs() < 1e-6);
+        assert!((weight(&sample) - 1.0).abs() < 1e-5, "weight {}", weight(&sample));
+
+        // u picks reflection with the Fresnel reflectance as probability
+        let n = 1000;
+        let reflected = (0..n).filter(|i| {
+            glass.sample(&wo, &inter, (*i as f32 + 0.5) / n as f32, 0.5).unwrap().wi.z > 0.0
+        }).count();
+        let cos_t = (1.0 - 0.25 / 2.25f32).sqrt();
+        let expected = fresnel_dielectric(wo.z, cos_t, 1.0 / 1.5);
+        assert!((reflected as f32 / n as f32 - expected).abs() < 2e-3);
+    }
+
+    #[test]
+    fn total_internal_reflection() {
+        let glass = Dielectric::new(1.5);
+
+        // Leaving the glass past the critical angle of about 41.8 degrees
+        let (inter, wo) = hit(Vector3::new(45f32.to_radians().sin(), 0.0, 45f32.to_radians().cos()));
+        assert!(!inter.front_face);
+        for i in 0..10 {
+            let sample = glass.sample(&wo, &inter, (i as f32 + 0.5) / 10.0, 0.5).unwrap();
+            assert!(sample.wi.z < 0.0, "escaped along {:?}", sample.wi);
+            assert!((sample.wi.x + wo.x).abs() < 1e-6);
+            assert_eq!(sample.pdf, 1.0);
+            assert!((weight(&sample) - 1.0).abs() < 1e-5);
+        }
+
+        // Below it some light gets out, bent away from the normal
+        let (inter, wo) = hit(Vector3::new(40f32.to_radians().sin(), 0.0, 40f32.to_radians().cos()));
+        let sample = glass.sample(&wo, &inter, 0.99, 0.5).unwrap();
+        assert!(sample.wi.z > 0.0);
+        assert!((sample.wi.x - 1.5 * 40f32.to_radians().sin()).abs() < 1e-4, "refracted {:?}", sample.wi);
+    }
+}
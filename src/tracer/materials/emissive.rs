@@ -0,0 +1,52 @@
+use tracer::materials::{Material, BsdfSample};
+use tracer::utils::color::Color;
+use tracer::utils::intersection::Intersection;
+
+use nalgebra::Vector3;
+use nalgebra::core::Unit;
+
+// Emits radiance from the front side of the surface and reflects nothing
+pub struct Emissive {
+    pub radiance: Color
+}
+
+impl Emissive {
+    pub fn new(radiance: Color) -> Emissive {
+        return Emissive {
+            radiance: radiance
+        };
+    }
+}
+
+#[allow(unused_variables)]
+impl Material for Emissive {
+    fn eval(&self, wo: &Unit<Vector3<f32>>, wi: &Unit<Vector3<f32>>,
+            inter: &Intersection) -> Color {
+        return Color::new_black();
+    }
+
+    fn sample(&self, wo: &Unit<Vector3<f32>>, inter: &Intersection,
+              u: f32, v: f32) -> Option<BsdfSample> {
+        return None;
+    }
+
+    fn pdf(&self, wo: &Unit<Vector3<f32>>, wi: &Unit<Vector3<f32>>,
+           inter: &Intersection) -> f32 {
+        return 0.0;
+    }
+
+    fn emitted(&self, wo: &Unit<Vector3<f32>>, inter: &Intersection) -> Color {
+        if inter.normal.dot(wo) <= 0.0 {
+            return Color::new_black();
+        }
+        return self.radiance;
+    }
+
+    fn emission(&self) -> Color {
+        return self.radiance;
+    }
+
+    fn albedo(&self, inter: &Intersection) -> Color {
+        return Color::new_black();
+    }
+}
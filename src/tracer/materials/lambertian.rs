@@ -0,0 +1,144 @@
+use tracer::materials::{Material, BsdfSample, Texture};
+use tracer::utils::color::Color;
+use tracer::utils::intersection::Intersection;
+use tracer::utils::sampling::{cosine_sample_hemisphere, local_to_world};
+
+use nalgebra::Vector3;
+use nalgebra::core::Unit;
+
+use std::f32::consts::PI;
+
+// Ideal diffuse reflector, lit from both sides of the surface
+pub struct Lambertian {
+    pub albedo: Texture
+}
+
+impl Lambertian {
+    pub fn new(albedo: Color) -> Lambertian {
+        return Lambertian {
+            albedo: Texture::new(albedo)
+        };
+    }
+
+    pub fn new_textured(albedo: Texture) -> Lambertian {
+        return Lambertian {
+            albedo: albedo
+        };
+    }
+}
+
+impl Material for Lambertian {
+    fn eval(&self, wo: &Unit<Vector3<f32>>, wi: &Unit<Vector3<f32>>,
+            inter: &Intersection) -> Color {
+        let n = inter.facing_normal(wo);
+        if n.dot(wi) <= 0.0 || !inter.same_side(wo, wi) {
+            return Color::new_black();
+        }
+        return self.albedo.evaluate(&inter.uv) / PI;
+    }
+
+    fn sample(&self, wo: &Unit<Vector3<f32>>, inter: &Intersection,
+              u: f32, v: f32) -> Option<BsdfSample> {
+        let n = inter.facing_normal(wo);
+        let local = cosine_sample_hemisphere(u, v);
+        if local.z <= 0.0 {
+            return None;
+        }
+
+        let wi = local_to_world(&local, &n);
+        if !inter.same_side(wo, &wi) {
+            return None;
+        }
+
+        return Some(BsdfSample {
+            wi: wi,
+            value: self.albedo.evaluate(&inter.uv) / PI,
+            pdf: local.z / PI,
+            is_specular: false
+        });
+    }
+
+    fn pdf(&self, wo: &Unit<Vector3<f32>>, wi: &Unit<Vector3<f32>>,
+           inter: &Intersection) -> f32 {
+        if !inter.same_side(wo, wi) {
+            return 0.0;
+        }
+        let n = inter.facing_normal(wo);
+        return n.dot(wi).max(0.0) / PI;
+    }
+
+    fn albedo(&self, inter: &Intersection) -> Color {
+        return self.albedo.evaluate(&inter.uv);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tracer::utils::ray::Ray;
+
+    use nalgebra::{Point2, Point3};
+
+    // Surface in the z = 0 plane facing +z, hit from above
+    fn surface() -> Intersection {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        return Intersection::new(&ray, 1.0, Unit::new_normalize(Vector3::new(0.0, 0.0, 1.0)),
+                                 Point2::new(0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    // Integral of f over the upper hemisphere, midpoint rule in (cos theta, phi)
+    fn hemisphere_integral<F: Fn(&Unit<Vector3<f32>>) -> f32>(f: F) -> f32 {
+        let n = 200;
+        let mut sum = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let z = (i as f32 + 0.5) / n as f32;
+                let phi = 2.0 * PI * (j as f32 + 0.5) / n as f32;
+                let r = (1.0 - z * z).sqrt();
+                sum += f(&Unit::new_normalize(Vector3::new(r * phi.cos(), r * phi.sin(), z)));
+            }
+        }
+        return sum * 2.0 * PI / (n * n) as f32;
+    }
+
+    #[test]
+    fn reflects_its_albedo() {
+        let material = Lambertian::new(Color::new(0.8, 0.5, 0.2));
+        let inter = surface();
+        let wo = Unit::new_normalize(Vector3::new(0.3, -0.2, 1.0));
+
+        // Cosine sampling cancels every term, each sample is the albedo
+        let mut estimate = Color::new_black();
+        let n = 16;
+        for i in 0..n {
+            for j in 0..n {
+                let sample = material.sample(&wo, &inter, (i as f32 + 0.5) / n as f32,
+                                             (j as f32 + 0.5) / n as f32).unwrap();
+                assert!(!sample.is_specular);
+                assert!((sample.pdf - material.pdf(&wo, &sample.wi, &inter)).abs() < 1e-4);
+                estimate += sample.value * (sample.wi.z / sample.pdf / (n * n) as f32);
+            }
+        }
+        assert!((estimate.red - 0.8).abs() < 1e-3, "red {}", estimate.red);
+        assert!((estimate.green - 0.5).abs() < 1e-3, "green {}", estimate.green);
+        assert!((estimate.blue - 0.2).abs() < 1e-3, "blue {}", estimate.blue);
+
+        let reflected = hemisphere_integral(|wi| material.eval(&wo, wi, &inter).red * wi.z);
+        assert!((reflected - 0.8).abs() < 1e-2, "reflected {}", reflected);
+    }
+
+    #[test]
+    fn pdf_is_a_density() {
+        let material = Lambertian::new(Color::new(0.5, 0.5, 0.5));
+        let inter = surface();
+        let wo = Unit::new_normalize(Vector3::new(0.0, 0.6, 0.8));
+        let total = hemisphere_integral(|wi| material.pdf(&wo, wi, &inter));
+        assert!((total - 1.0).abs() < 1e-2, "total {}", total);
+
+        // Nothing goes through the surface
+        let below = Unit::new_normalize(Vector3::new(0.0, 0.6, -0.8));
+        assert_eq!(material.pdf(&wo, &below, &inter), 0.0);
+        assert!(material.eval(&wo, &below, &inter).is_black());
+    }
+}
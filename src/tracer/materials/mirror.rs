@@ -0,0 +1,56 @@
+use tracer::materials::{Material, BsdfSample};
+use tracer::utils::color::Color;
+use tracer::utils::intersection::Intersection;
+use tracer::utils::sampling::reflect;
+
+use nalgebra::Vector3;
+use nalgebra::core::Unit;
+
+// Perfect specular reflector, only reachable by sampling
+pub struct Mirror {
+    pub reflectance: Color
+}
+
+impl Mirror {
+    pub fn new(reflectance: Color) -> Mirror {
+        return Mirror {
+            reflectance: reflectance
+        };
+    }
+}
+
+#[allow(unused_variables)]
+impl Material for Mirror {
+    fn eval(&self, wo: &Unit<Vector3<f32>>, wi: &Unit<Vector3<f32>>,
+            inter: &Intersection) -> Color {
+        return Color::new_black();
+    }
+
+    // The value is divided by the cosine so that value * cos / pdf is the
+    // reflectance, as for any other sample
+    fn sample(&self, wo: &Unit<Vector3<f32>>, inter: &Intersection,
+              u: f32, v: f32) -> Option<BsdfSample> {
+        let n = inter.facing_normal(wo);
+        let wi = Unit::new_normalize(reflect(wo, &n));
+        let cos_theta = n.dot(&wi);
+        if cos_theta <= 0.0 || !inter.same_side(wo, &wi) {
+            return None;
+        }
+
+        return Some(BsdfSample {
+            wi: wi,
+            value: self.reflectance / cos_theta,
+            pdf: 1.0,
+            is_specular: true
+        });
+    }
+
+    fn pdf(&self, wo: &Unit<Vector3<f32>>, wi: &Unit<Vector3<f32>>,
+           inter: &Intersection) -> f32 {
+        return 0.0;
+    }
+
+    fn albedo(&self, inter: &Intersection) -> Color {
+        return self.reflectance;
+    }
+}
@@ -0,0 +1,60 @@
+pub mod lambertian;
+pub mod mirror;
+pub mod emissive;
+pub mod dielectric;
+pub mod phong;
+pub mod texture;
+
+pub use tracer::utils::color::Color;
+pub use tracer::utils::intersection::Intersection;
+
+pub use tracer::materials::lambertian::Lambertian;
+pub use tracer::materials::mirror::Mirror;
+pub use tracer::materials::emissive::Emissive;
+pub use tracer::materials::dielectric::Dielectric;
+pub use tracer::materials::phong::Phong;
+pub use tracer::materials::texture::{Texture, Image};
+
+use nalgebra::Vector3;
+use nalgebra::core::Unit;
+
+// Direction chosen by Material::sample, with the BSDF value and pdf for it
+pub struct BsdfSample {
+    pub wi: Unit<Vector3<f32>>,
+    pub value: Color,
+    // Solid angle density, meaningless for specular samples
+    pub pdf: f32,
+    // Dirac distributions (mirror, glass) can only be reached by sampling
+    pub is_specular: bool
+}
+
+// Directions point away from the surface: wo toward the viewer and wi toward
+// the light
+pub trait Material: Send + Sync {
+    fn eval(&self, wo: &Unit<Vector3<f32>>, wi: &Unit<Vector3<f32>>,
+            inter: &Intersection) -> Color;
+
+    fn sample(&self, wo: &Unit<Vector3<f32>>, inter: &Intersection,
+              u: f32, v: f32) -> Option<BsdfSample>;
+
+    fn pdf(&self, wo: &Unit<Vector3<f32>>, wi: &Unit<Vector3<f32>>,
+           inter: &Intersection) -> f32;
+
+    #[allow(unused_variables)]
+    fn emitted(&self, wo: &Unit<Vector3<f32>>, inter: &Intersection) -> Color {
+        return Color::new_black();
+    }
+
+    // Radiance leaving the front side, black for materials that do not
+    // emit. Primitives with an emitting material are lights
+    fn emission(&self) -> Color {
+        return Color::new_black();
+    }
+
+    // Color of the surface regardless of the lighting, for the albedo layer
+    // of the film. Colorless materials like glass are white
+    #[allow(unused_variables)]
+    fn albedo(&self, inter: &Intersection) -> Color {
+        return Color::new(1.0, 1.0, 1.0);
+    }
+}
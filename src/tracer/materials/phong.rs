@@ -0,0 +1,200 @@
+use tracer::materials::{Material, BsdfSample, Texture};
+use tracer::utils::color::Color;
+use tracer::utils::intersection::Intersection;
+use tracer::utils::sampling::{cosine_sample_hemisphere, local_to_world, reflect};
+
+use nalgebra::Vector3;
+use nalgebra::core::Unit;
+
+use std::f32::consts::PI;
+
+// Energy conserving (modified) Phong: a Lambertian lobe plus a glossy lobe
+// around the mirror direction, sharper as the exponent grows. The
+// specular color should satisfy diffuse + specular <= 1
+pub struct Phong {
+    pub diffuse: Texture,
+    pub specular: Color,
+    pub exponent: f32
+}
+
+impl Phong {
+    pub fn new(diffuse: Texture, specular: Color, exponent: f32) -> Phong {
+        return Phong {
+            diffuse: diffuse,
+            specular: specular,
+            exponent: exponent
+        };
+    }
+
+    // Probability of sampling the glossy lobe rather than the diffuse one
+    fn specular_probability(&self, diffuse: &Color) -> f32 {
+        let d = diffuse.max_component();
+        let s = self.specular.max_component();
+        if d + s <= 0.0 {
+            return 0.0;
+        }
+        return s / (d + s);
+    }
+}
+
+impl Material for Phong {
+    fn eval(&self, wo: &Unit<Vector3<f32>>, wi: &Unit<Vector3<f32>>,
+            inter: &Intersection) -> Color {
+        let n = inter.facing_normal(wo);
+        if n.dot(wi) <= 0.0 || !inter.same_side(wo, wi) {
+            return Color::new_black();
+        }
+
+        let r = reflect(wo, &n);
+        let cos_alpha = r.dot(wi).max(0.0);
+        let glossy = (self.exponent + 2.0) / (2.0 * PI) * cos_alpha.powf(self.exponent);
+        return self.diffuse.evaluate(&inter.uv) / PI + self.specular * glossy;
+    }
+
+    fn sample(&self, wo: &Unit<Vector3<f32>>, inter: &Intersection,
+              u: f32, v: f32) -> Option<BsdfSample> {
+        let n = inter.facing_normal(wo);
+        let p_specular = self.specular_probability(&self.diffuse.evaluate(&inter.uv));
+
+        // u is reused for the chosen lobe once rescaled to [0, 1)
+        let wi = if u < p_specular {
+            let u = u / p_specular;
+            let cos_alpha = u.powf(1.0 / (self.exponent + 1.0));
+            let sin_alpha = (1.0 - cos_alpha * cos_alpha).max(0.0).sqrt();
+            let phi = 2.0 * PI * v;
+            let local = Vector3::new(sin_alpha * phi.cos(), sin_alpha * phi.sin(), cos_alpha);
+            local_to_world(&local, &Unit::new_normalize(reflect(wo, &n)))
+        }
+        else {
+            let u = (u - p_specular) / (1.0 - p_specular);
+            local_to_world(&cosine_sample_hemisphere(u, v), &n)
+        };
+
+        if n.dot(&wi) <= 0.0 || !inter.same_side(wo, &wi) {
+            return None;
+        }
+
+        let pdf = self.pdf(wo, &wi, inter);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        return Some(BsdfSample {
+            wi: wi,
+            value: self.eval(wo, &wi, inter),
+            pdf: pdf,
+            is_specular: false
+        });
+    }
+
+    fn pdf(&self, wo: &Unit<Vector3<f32>>, wi: &Unit<Vector3<f32>>,
+           inter: &Intersection) -> f32 {
+        let n = inter.facing_normal(wo);
+        let cos_theta = n.dot(wi);
+        if cos_theta <= 0.0 || !inter.same_side(wo, wi) {
+            return 0.0;
+        }
+
+        let p_specular = self.specular_probability(&self.diffuse.evaluate(&inter.uv));
+        let cos_alpha = reflect(wo, &n).dot(wi).max(0.0);
+        return (1.0 - p_specular) * cos_theta / PI +
+               p_specular * (self.exponent + 1.0) / (2.0 * PI) * cos_alpha.powf(self.exponent);
+    }
+
+    fn albedo(&self, inter: &Intersection) -> Color {
+        return self.diffuse.evaluate(&inter.uv) + self.specular;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tracer::utils::ray::Ray;
+
+    use nalgebra::{Point2, Point3};
+
+    // Surface in the z = 0 plane facing +z, hit from above
+    fn surface() -> Intersection {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        return Intersection::new(&ray, 1.0, Unit::new_normalize(Vector3::new(0.0, 0.0, 1.0)),
+                                 Point2::new(0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    // Integral of f over the upper hemisphere, midpoint rule in (cos theta, phi)
+    fn hemisphere_integral<F: Fn(&Unit<Vector3<f32>>) -> f32>(f: F) -> f32 {
+        let n = 400;
+        let mut sum = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let z = (i as f32 + 0.5) / n as f32;
+                let phi = 2.0 * PI * (j as f32 + 0.5) / n as f32;
+                let r = (1.0 - z * z).sqrt();
+                sum += f(&Unit::new_normalize(Vector3::new(r * phi.cos(), r * phi.sin(), z)));
+            }
+        }
+        return sum * 2.0 * PI / (n * n) as f32;
+    }
+
+    // Reflected fraction of the light coming from wo, estimated with sample
+    fn sampled_reflectance(material: &Phong, wo: &Unit<Vector3<f32>>, inter: &Intersection) -> f32 {
+        let n = 64;
+        let mut sum = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                match material.sample(wo, inter, (i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32) {
+                    Some(sample) => {
+                        assert!((sample.pdf - material.pdf(wo, &sample.wi, inter)).abs() <= 1e-4 * sample.pdf);
+                        assert!((sample.value.red - material.eval(wo, &sample.wi, inter).red).abs() < 1e-5);
+                        sum += sample.value.red * sample.wi.z / sample.pdf;
+                    },
+                    None => {}
+                }
+            }
+        }
+        return sum / (n * n) as f32;
+    }
+
+    fn material() -> Phong {
+        return Phong::new(Texture::new(Color::new(0.3, 0.3, 0.3)), Color::new(0.6, 0.6, 0.6), 20.0);
+    }
+
+    #[test]
+    fn conserves_energy() {
+        let material = material();
+        let inter = surface();
+
+        // Head on, both lobes are entirely above the surface and reflect
+        // exactly their color
+        let wo = Unit::new_normalize(Vector3::new(0.0, 0.0, 1.0));
+        let reflected = hemisphere_integral(|wi| material.eval(&wo, wi, &inter).red * wi.z);
+        assert!((reflected - 0.9).abs() < 1e-2, "reflected {}", reflected);
+        let sampled = sampled_reflectance(&material, &wo, &inter);
+        assert!((sampled - 0.9).abs() < 1e-2, "sampled {}", sampled);
+
+        // At grazing angles part of the glossy lobe is under the surface
+        let wo = Unit::new_normalize(Vector3::new(0.0, 0.98, 0.2));
+        let reflected = hemisphere_integral(|wi| material.eval(&wo, wi, &inter).red * wi.z);
+        assert!(reflected < 0.9, "reflected {}", reflected);
+        let sampled = sampled_reflectance(&material, &wo, &inter);
+        assert!((sampled - reflected).abs() < 2e-2, "sampled {} for {}", sampled, reflected);
+    }
+
+    #[test]
+    fn pdf_is_a_density() {
+        let material = material();
+        let inter = surface();
+        let wo = Unit::new_normalize(Vector3::new(0.0, 0.0, 1.0));
+        let total = hemisphere_integral(|wi| material.pdf(&wo, wi, &inter));
+        assert!((total - 1.0).abs() < 1e-2, "total {}", total);
+
+        // The part of the glossy lobe under the surface is lost
+        let wo = Unit::new_normalize(Vector3::new(0.0, 0.98, 0.2));
+        let total = hemisphere_integral(|wi| material.pdf(&wo, wi, &inter));
+        assert!(total < 1.0, "total {}", total);
+
+        let below = Unit::new_normalize(Vector3::new(0.0, -0.98, -0.2));
+        assert_eq!(material.pdf(&wo, &below, &inter), 0.0);
+        assert!(material.eval(&wo, &below, &inter).is_black());
+    }
+}
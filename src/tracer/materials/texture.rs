@@ -0,0 +1,145 @@
+use tracer::utils::color::Color;
+use tracer::io::pfm::read_pfm;
+
+use image;
+use image::{GenericImage, Pixel};
+use image::hdr::HDRDecoder;
+use nalgebra::Point2;
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+// Image files store gamma encoded colors, see Color::to_rgba
+const GAMMA: f32 = 2.2;
+
+// Repeats the image outside of [0, 1]
+fn wrap(i: i64, n: i64) -> i64 {
+    let r = i % n;
+    return if r < 0 { r + n } else { r };
+}
+
+// Texels in linear space, row 0 at the top of the image
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub texels: Vec<Color>
+}
+
+impl Image {
+    // Radiance (.hdr) and PFM files are already linear and keep values
+    // above 1
+    pub fn open(path: &Path) -> Result<Image, String> {
+        let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase());
+        match extension {
+            Some(ref e) if e == "hdr" => return Image::open_radiance(path),
+            Some(ref e) if e == "pfm" => {
+                let (width, height, texels) = try!(read_pfm(path));
+                return Ok(Image {
+                    width: width,
+                    height: height,
+                    texels: texels
+                });
+            },
+            _ => {}
+        }
+
+        let img = match image::open(path) {
+            Ok(img) => img,
+            Err(e) => return Err(format!("cannot load image '{}': {}", path.display(), e))
+        };
+
+        let (width, height) = img.dimensions();
+        let mut texels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let rgb = img.get_pixel(x, y).to_rgb();
+                texels.push(Color::new((rgb[0] as f32 / 255.0).powf(GAMMA),
+                                       (rgb[1] as f32 / 255.0).powf(GAMMA),
+                                       (rgb[2] as f32 / 255.0).powf(GAMMA)));
+            }
+        }
+
+        return Ok(Image {
+            width: width,
+            height: height,
+            texels: texels
+        });
+    }
+
+    fn open_radiance(path: &Path) -> Result<Image, String> {
+        let error = |e: String| format!("cannot load image '{}': {}", path.display(), e);
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => return Err(error(e.to_string()))
+        };
+        let decoder = match HDRDecoder::new(BufReader::new(file)) {
+            Ok(decoder) => decoder,
+            Err(e) => return Err(error(e.to_string()))
+        };
+        let metadata = decoder.metadata();
+        let pixels = match decoder.read_image_hdr() {
+            Ok(pixels) => pixels,
+            Err(e) => return Err(error(e.to_string()))
+        };
+
+        return Ok(Image {
+            width: metadata.width,
+            height: metadata.height,
+            texels: pixels.iter().map(|p| Color::new(p[0], p[1], p[2])).collect()
+        });
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Color {
+        let x = wrap(x, self.width as i64);
+        let y = wrap(y, self.height as i64);
+        return self.texels[(y * self.width as i64 + x) as usize];
+    }
+
+    // Bilinear filtering, v goes up as in OBJ files
+    pub fn lookup(&self, uv: &Point2<f32>) -> Color {
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = (1.0 - uv.y) * self.height as f32 - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let dx = x - x0;
+        let dy = y - y0;
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        return self.texel(x0, y0) * ((1.0 - dx) * (1.0 - dy)) +
+               self.texel(x0 + 1, y0) * (dx * (1.0 - dy)) +
+               self.texel(x0, y0 + 1) * ((1.0 - dx) * dy) +
+               self.texel(x0 + 1, y0 + 1) * (dx * dy);
+    }
+}
+
+// Constant color, optionally modulated by an image
+#[derive(Clone)]
+pub struct Texture {
+    pub color: Color,
+    pub image: Option<Arc<Image>>
+}
+
+impl Texture {
+    pub fn new(color: Color) -> Texture {
+        return Texture {
+            color: color,
+            image: None
+        };
+    }
+
+    pub fn new_image(color: Color, image: Arc<Image>) -> Texture {
+        return Texture {
+            color: color,
+            image: Some(image)
+        };
+    }
+
+    pub fn evaluate(&self, uv: &Point2<f32>) -> Color {
+        match self.image {
+            Some(ref image) => return self.color * image.lookup(uv),
+            None => return self.color
+        }
+    }
+}
@@ -1,3 +1,7 @@
 
 pub mod utils;
-pub mod primitives;
\ No newline at end of file
+pub mod primitives;
+pub mod materials;
+pub mod integrators;
+pub mod lights;
+pub mod io;
\ No newline at end of file
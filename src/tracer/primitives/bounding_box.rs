@@ -1,7 +1,5 @@
 
 use tracer::primitives::Intersectable;
-use tracer::primitives::Primitive;
-use tracer::primitives::HasBoundingBox;
 use tracer::utils::ray::Ray;
 use tracer::primitives::HasCenter;
 
@@ -17,9 +15,40 @@ pub struct BoundingBox {
 
 impl BoundingBox
 {
-    pub fn new(primitive: &Primitive) -> BoundingBox
+    // Inverted box, growing it with anything gives that thing's bounds
+    pub fn new_empty() -> BoundingBox
     {
-      return primitive.get_bounding_box();
+      return BoundingBox {
+         min: Point3::new(f32::MAX, f32::MAX, f32::MAX),
+         max: Point3::new(f32::MIN, f32::MIN, f32::MIN)
+      };
+    }
+
+    pub fn new_from_point(p: &Point3<f32>) -> BoundingBox
+    {
+      return BoundingBox {
+         min: *p,
+         max: *p
+      };
+    }
+
+    pub fn surface_area(&self) -> f32
+    {
+      if self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+      {
+         return 0.0;
+      }
+
+      let dx = self.max.x - self.min.x;
+      let dy = self.max.y - self.min.y;
+      let dz = self.max.z - self.min.z;
+      return 2.0 * (dx * dy + dx * dz + dy * dz);
+    }
+
+    // Extent along axis 0 (x), 1 (y) or 2 (z)
+    pub fn extent(&self, axis: usize) -> f32
+    {
+      return self.max[axis] - self.min[axis];
     }
 
     pub fn new_from(left: &BoundingBox, right: &BoundingBox) -> BoundingBox
@@ -183,8 +212,8 @@ impl Intersectable for BoundingBox {
 impl HasCenter for BoundingBox
 {
    fn get_center(&self) -> Point3<f32> {
-        return Point3::new(self.max.x - self.min.x,
-                           self.max.y - self.min.y,
-                           self.max.z - self.min.z);
+        return Point3::new((self.min.x + self.max.x) * 0.5,
+                           (self.min.y + self.max.y) * 0.5,
+                           (self.min.z + self.max.z) * 0.5);
     }
 }
\ No newline at end of file
@@ -0,0 +1,88 @@
+use tracer::primitives::{HasBoundingBox, Intersectable};
+use tracer::primitives::bounding_box::BoundingBox;
+use tracer::utils::ray::Ray;
+use tracer::utils::transform::Transform;
+use tracer::utils::bounding_volume_hierarchy::{BoundingVolumeHierarchy, BVHPrimitive, HitInfo};
+
+use nalgebra::Point3;
+
+use std::f32;
+use std::sync::Arc;
+
+// A BVH placed in the scene by a transform. Rays are brought to the space of
+// the BVH instead of moving its primitives, so any number of instances share
+// the same geometry. Instances only live in the top level BVH, lights and
+// materials deal with the primitives inside
+#[derive(Clone)]
+pub struct Instance {
+    pub bvh: Arc<BoundingVolumeHierarchy>,
+    pub transform: Transform
+}
+
+impl Instance {
+    pub fn new(bvh: Arc<BoundingVolumeHierarchy>, transform: Transform) -> Instance {
+        return Instance {
+            bvh: bvh,
+            transform: transform
+        };
+    }
+}
+
+impl HasBoundingBox for Instance {
+    fn get_bounding_box(&self) -> BoundingBox {
+        if !self.bvh.is_bounded() {
+            return BoundingBox {
+                min: Point3::new(f32::MIN, f32::MIN, f32::MIN),
+                max: Point3::new(f32::MAX, f32::MAX, f32::MAX)
+            };
+        }
+        return self.transform.transform_bounding_box(&self.bvh.get_bounding_box());
+    }
+
+    fn is_bounded(&self) -> bool {
+        return self.bvh.is_bounded();
+    }
+}
+
+impl Intersectable for Instance {
+    fn intersect(&self, ray: &Ray) -> Option<f32> {
+        let (local_ray, factor) = self.transform.inverse().transform_ray(ray);
+        return self.bvh.intersect_distance(&local_ray).map(|distance| distance / factor);
+    }
+}
+
+// The hit record is the one of the primitive hit inside, with its
+// intersection brought back to scene space
+impl BVHPrimitive for Instance {
+    fn get_hit_info(&self, ray: &Ray) -> Option<HitInfo> {
+        let (local_ray, factor) = self.transform.inverse().transform_ray(ray);
+        let mut h_info = match self.bvh.intersect(&local_ray) {
+            Some(h_info) => h_info,
+            None => return None
+        };
+
+        // The sign of the normals along the ray is preserved, front_face
+        // stays valid
+        {
+            let inter = &mut h_info.intersection;
+            inter.distance = inter.distance / factor;
+            inter.point = self.transform.transform_point(&inter.point);
+            inter.normal = self.transform.transform_normal(&inter.normal);
+            inter.shading_normal = self.transform.transform_normal(&inter.shading_normal);
+            inter.dpdu = self.transform.transform_vector(&inter.dpdu);
+            inter.dpdv = self.transform.transform_vector(&inter.dpdv);
+        }
+        return Some(h_info);
+    }
+
+    // The primitive index is the one inside the instance
+    fn record_index(&self, h_info: &mut HitInfo, index: usize) {
+        h_info.intersection.object_index = index;
+    }
+
+    // Any-hit query of the BVH, the ray is not searched for its closest hit
+    fn occluded(&self, ray: &Ray, max_distance: f32) -> bool {
+        let (local_ray, factor) = self.transform.inverse().transform_ray(ray);
+        return self.bvh.occluded(&local_ray, max_distance * factor);
+    }
+}
@@ -2,10 +2,13 @@
 pub mod sphere;
 pub mod bounding_box;
 pub mod triangle;
-pub mod light;
+pub mod triangle_mesh;
+pub mod plane;
+pub mod instance;
 
-pub use tracer::utils::color::Color;
+pub use tracer::materials::Material;
 pub use tracer::utils::ray::Ray;
+pub use tracer::utils::intersection::Intersection;
 pub use tracer::utils::scene::Scene;
 
 pub use tracer::primitives::bounding_box::BoundingBox;
@@ -13,18 +16,48 @@ pub use tracer::primitives::bounding_box::BoundingBox;
 use nalgebra::{Point3, Vector3};
 use nalgebra::core::Unit;
 
+use std::sync::Arc;
+use std::f32;
+
 pub trait HasBoundingBox {
     fn get_bounding_box(&self) -> BoundingBox;
+
+    // Unbounded primitives (planes) cannot be stored in the BVH tree
+    fn is_bounded(&self) -> bool {
+        return true;
+    }
 }
 
-pub trait HasColor {
-    fn get_color(&self) -> Color;
+// Shared primitives, the top level BVH holds its instances this way
+impl<T: HasBoundingBox> HasBoundingBox for Arc<T> {
+    fn get_bounding_box(&self) -> BoundingBox {
+        return (**self).get_bounding_box();
+    }
+
+    fn is_bounded(&self) -> bool {
+        return (**self).is_bounded();
+    }
+}
+
+pub trait HasMaterial {
+    fn get_material(&self) -> Arc<Material>;
 }
 
 pub trait Intersectable {
     fn intersect(&self, ray: &Ray) -> Option<f32>;
 }
 
+impl<T: Intersectable> Intersectable for Arc<T> {
+    fn intersect(&self, ray: &Ray) -> Option<f32> {
+        return (**self).intersect(ray);
+    }
+}
+
+// Full surface interaction, more expensive than Intersectable::intersect
+pub trait HasIntersection {
+    fn get_intersection(&self, ray: &Ray) -> Option<Intersection>;
+}
+
 pub trait HasCenter {
     fn get_center(&self) -> Point3<f32>;
 }
@@ -33,29 +66,68 @@ pub trait HasNormal {
     fn get_normal(&self, p: Point3<f32>) -> Unit<Vector3<f32>>;
 }
 
+// Uniform sampling by area, the density of get_sample is 1 / get_area
 pub trait CanSample {
     fn get_sample(&self, u: f32, v: f32) -> Point3<f32>;
+
+    fn get_area(&self) -> f32;
+
+    // Point to be seen from p, shapes that can favor the part visible from
+    // p override both this and get_pdf_from
+    #[allow(unused_variables)]
+    fn get_sample_from(&self, p: &Point3<f32>, u: f32, v: f32) -> Point3<f32> {
+        return self.get_sample(u, v);
+    }
+
+    // Solid angle density, at p, of get_sample_from returning inter.point
+    fn get_pdf_from(&self, p: &Point3<f32>, inter: &Intersection) -> f32 {
+        let to_sample: Vector3<f32> = inter.point - p;
+        let distance_squared = to_sample.norm_squared();
+        let cos_sample: f32 = inter.normal.dot(&to_sample).abs() / distance_squared.sqrt();
+        if cos_sample <= 0.0 {
+            return 0.0;
+        }
+        return distance_squared / (cos_sample * self.get_area());
+    }
 }
 
+// Standalone shapes are boxed so the enum is no bigger than a reference to a
+// mesh triangle, the BVH stores one per triangle
+#[derive(Clone)]
 pub enum Primitive {
-    Sphere(sphere::Sphere),
-    Triangle(triangle::Triangle)
+    Sphere(Box<sphere::Sphere>),
+    Triangle(Box<triangle::Triangle>),
+    Plane(Box<plane::Plane>),
+    MeshTriangle(triangle_mesh::MeshTriangle)
 }
 
 impl HasBoundingBox for Primitive {
     fn get_bounding_box(&self) -> BoundingBox {
         match self {
             &Primitive::Sphere(ref s) => s.get_bounding_box(),
-            &Primitive::Triangle(ref t) => t.get_bounding_box()
+            &Primitive::Triangle(ref t) => t.get_bounding_box(),
+            &Primitive::Plane(ref p) => p.get_bounding_box(),
+            &Primitive::MeshTriangle(ref t) => t.get_bounding_box()
+        }
+    }
+
+    fn is_bounded(&self) -> bool {
+        match self {
+            &Primitive::Sphere(ref s) => s.is_bounded(),
+            &Primitive::Triangle(ref t) => t.is_bounded(),
+            &Primitive::Plane(ref p) => p.is_bounded(),
+            &Primitive::MeshTriangle(ref t) => t.is_bounded()
         }
     }
 }
 
-impl HasColor for Primitive {
-    fn get_color(&self) -> Color {
+impl HasMaterial for Primitive {
+    fn get_material(&self) -> Arc<Material> {
         match self {
-            &Primitive::Sphere(ref s) => s.get_color(),
-            &Primitive::Triangle(ref t) => t.get_color()
+            &Primitive::Sphere(ref s) => s.get_material(),
+            &Primitive::Triangle(ref t) => t.get_material(),
+            &Primitive::Plane(ref p) => p.get_material(),
+            &Primitive::MeshTriangle(ref t) => t.get_material()
         }
     }
 }
@@ -64,16 +136,31 @@ impl Intersectable for Primitive {
     fn intersect(&self, ray: &Ray) -> Option<f32> {
         match self {
             &Primitive::Sphere(ref s) => s.intersect(ray),
-            &Primitive::Triangle(ref t) => t.intersect(ray)
+            &Primitive::Triangle(ref t) => t.intersect(ray),
+            &Primitive::Plane(ref p) => p.intersect(ray),
+            &Primitive::MeshTriangle(ref t) => t.intersect(ray)
         }
     }  
 }
 
+impl HasIntersection for Primitive {
+    fn get_intersection(&self, ray: &Ray) -> Option<Intersection> {
+        match self {
+            &Primitive::Sphere(ref s) => s.get_intersection(ray),
+            &Primitive::Triangle(ref t) => t.get_intersection(ray),
+            &Primitive::Plane(ref p) => p.get_intersection(ray),
+            &Primitive::MeshTriangle(ref t) => t.get_intersection(ray)
+        }
+    }
+}
+
 impl HasCenter for Primitive {
     fn get_center(&self) -> Point3<f32> {
         match self {
             &Primitive::Sphere(ref s) => s.get_center(),
-            &Primitive::Triangle(ref t) => t.get_center()
+            &Primitive::Triangle(ref t) => t.get_center(),
+            &Primitive::Plane(ref p) => p.get_center(),
+            &Primitive::MeshTriangle(ref t) => t.get_center()
         }
     }
 }
@@ -82,17 +169,47 @@ impl HasNormal for Primitive {
     fn get_normal(&self, p: Point3<f32>) -> Unit<Vector3<f32>> {
         match self {
             &Primitive::Sphere(ref s) => s.get_normal(p),
-            &Primitive::Triangle(ref t) => t.normal
+            &Primitive::Triangle(ref t) => t.normal,
+            &Primitive::Plane(ref p) => p.normal,
+            &Primitive::MeshTriangle(ref t) => t.get_normal(p)
         }
     }
 }
 
 impl CanSample for Primitive {
-   #[allow(unused_variables)]
    fn get_sample(&self, u: f32, v: f32) -> Point3<f32> {
         match self {
-            &Primitive::Sphere(ref s) => unimplemented!(),
-            &Primitive::Triangle(ref t) => t.get_sample(u,v)
+            &Primitive::Sphere(ref s) => s.get_sample(u,v),
+            &Primitive::Triangle(ref t) => t.get_sample(u,v),
+            // Area lights never hold a plane, the scene loader refuses
+            // emissive planes
+            &Primitive::Plane(_) => unreachable!("an infinite plane cannot be sampled by area"),
+            &Primitive::MeshTriangle(ref t) => t.get_sample(u,v)
+        }
+   }
+
+   fn get_area(&self) -> f32 {
+        match self {
+            &Primitive::Sphere(ref s) => s.get_area(),
+            &Primitive::Triangle(ref t) => t.get_area(),
+            &Primitive::Plane(_) => f32::INFINITY,
+            &Primitive::MeshTriangle(ref t) => t.get_area()
+        }
+   }
+
+   fn get_sample_from(&self, p: &Point3<f32>, u: f32, v: f32) -> Point3<f32> {
+        match self {
+            &Primitive::Sphere(ref s) => s.get_sample_from(p, u, v),
+            _ => self.get_sample(u, v)
+        }
+   }
+
+   fn get_pdf_from(&self, p: &Point3<f32>, inter: &Intersection) -> f32 {
+        match self {
+            &Primitive::Sphere(ref s) => s.get_pdf_from(p, inter),
+            &Primitive::Triangle(ref t) => t.get_pdf_from(p, inter),
+            &Primitive::Plane(_) => 0.0,
+            &Primitive::MeshTriangle(ref t) => t.get_pdf_from(p, inter)
         }
    }
 }
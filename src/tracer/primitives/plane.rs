@@ -0,0 +1,102 @@
+use tracer::primitives::{HasBoundingBox, HasMaterial, Intersectable, HasIntersection, HasCenter, HasNormal};
+use tracer::primitives::bounding_box::BoundingBox;
+use tracer::utils::ray::Ray;
+use tracer::materials::Material;
+use tracer::utils::intersection::Intersection;
+use tracer::utils::sampling::coordinate_system;
+
+use nalgebra::{Point2, Point3, Vector3};
+use nalgebra::core::Unit;
+
+use std::sync::Arc;
+use std::f32;
+
+// Infinite plane, defined by any point on it and its normal
+#[derive(Clone)]
+pub struct Plane {
+    pub point: Point3<f32>,
+    pub normal: Unit<Vector3<f32>>,
+    pub material: Arc<Material>
+}
+
+impl Plane {
+    pub fn new(point: Point3<f32>, normal: Vector3<f32>, material: Arc<Material>) -> Plane {
+        return Plane {
+            point: point,
+            normal: Unit::new_normalize(normal),
+            material: material
+        };
+    }
+}
+
+// A plane has no finite bounds, the BVH keeps it outside of the tree
+impl HasBoundingBox for Plane {
+    fn get_bounding_box(&self) -> BoundingBox {
+        return BoundingBox {
+            min: Point3::new(f32::MIN, f32::MIN, f32::MIN),
+            max: Point3::new(f32::MAX, f32::MAX, f32::MAX)
+        }
+    }
+
+    fn is_bounded(&self) -> bool {
+        return false;
+    }
+}
+
+impl HasMaterial for Plane {
+    fn get_material(&self) -> Arc<Material> {
+        return self.material.clone();
+    }
+}
+
+impl Intersectable for Plane {
+    fn intersect(&self, ray: &Ray) -> Option<f32> {
+        let denom: f32 = self.normal.dot(&ray.direction);
+
+        // Ray is parallel to plane
+        if denom < 0.00001 && denom > -0.00001 {
+            return None;
+        }
+
+        let t: f32 = (self.point - ray.origin).dot(&self.normal) / denom;
+        if t < 0.0 { //intersection is behind ray origin
+            return None;
+        }
+
+        return Some(t);
+    }
+}
+
+// The plane is parametrized by two tangents orthogonal to its normal
+impl HasIntersection for Plane {
+    fn get_intersection(&self, ray: &Ray) -> Option<Intersection> {
+        let t = match self.intersect(ray) {
+            Some(t) => t,
+            None => return None
+        };
+
+        let p_hit = ray.origin + t * ray.direction.as_ref();
+        let (dpdu, dpdv) = coordinate_system(&self.normal);
+        let local: Vector3<f32> = p_hit - self.point;
+
+        return Some(Intersection::new(ray,
+                                      t,
+                                      self.normal,
+                                      Point2::new(local.dot(&dpdu), local.dot(&dpdv)),
+                                      dpdu,
+                                      dpdv));
+    }
+}
+
+impl HasCenter for Plane {
+    fn get_center(&self) -> Point3<f32> {
+        return self.point;
+    }
+}
+
+#[allow(unused_variables)]
+impl HasNormal for Plane {
+    fn get_normal(&self, p: Point3<f32>) -> Unit<Vector3<f32>> {
+        return self.normal;
+    }
+}
@@ -1,28 +1,33 @@
 
-pub use tracer::primitives::{HasBoundingBox, HasColor, Intersectable, HasCenter, HasNormal};
+pub use tracer::primitives::{HasBoundingBox, HasMaterial, Intersectable, HasIntersection, HasCenter, HasNormal, CanSample};
 pub use tracer::primitives::bounding_box::BoundingBox;
 pub use tracer::utils::ray::Ray;
-pub use tracer::utils::color::Color;
+pub use tracer::materials::Material;
+pub use tracer::utils::intersection::Intersection;
+use tracer::utils::sampling::local_to_world;
 
-use nalgebra::{Point3, Vector3, distance};
+use nalgebra::{Point2, Point3, Vector3, distance};
 use nalgebra::core::Unit;
 
+use std::sync::Arc;
 use std::mem;
+use std::f32::consts::PI;
 
+#[derive(Clone)]
 pub struct Sphere {
     pub radius: f32,
     pub origin: Point3<f32>,
-    pub color: Color,
+    pub material: Arc<Material>,
 
     radius2: f32
 }
 
 impl Sphere {
-    pub fn new(radius: f32, origin: Point3<f32>, color: Color) -> Sphere {
+    pub fn new(radius: f32, origin: Point3<f32>, material: Arc<Material>) -> Sphere {
         return Sphere {
             radius: radius,
             origin: origin,
-            color: color,
+            material: material,
             radius2: radius*radius
         };
     }
@@ -41,9 +46,9 @@ impl HasBoundingBox for Sphere {
     }
 }
 
-impl HasColor for Sphere {
-    fn get_color(&self) -> Color {
-        return Color::new_copy(&self.color);
+impl HasMaterial for Sphere {
+    fn get_material(&self) -> Arc<Material> {
+        return self.material.clone();
     }
 }
 
@@ -53,7 +58,9 @@ impl Intersectable for Sphere {
         let mut t1: f32;
         let l: Vector3<f32> = self.origin - ray.origin;
         let tca = l.dot(&ray.direction);
-        if tca < 0.0 { //intersection is behind ray origin
+        // Center behind an origin outside of the sphere, the intersection
+        // is behind too. From inside, the far side is always in front
+        if tca < 0.0 && l.dot(&l) > self.radius2 {
             return None;
         }
         let d2: f32 = l.dot(&l) - tca * tca;
@@ -83,6 +90,39 @@ impl Intersectable for Sphere {
     }
 }
 
+// Spherical coordinates around the y axis: u follows phi, v follows theta
+impl HasIntersection for Sphere {
+    fn get_intersection(&self, ray: &Ray) -> Option<Intersection> {
+        let t = match self.intersect(ray) {
+            Some(t) => t,
+            None => return None
+        };
+
+        let p_hit = ray.origin + t * ray.direction.as_ref();
+        let local: Vector3<f32> = p_hit - self.origin;
+
+        let mut phi: f32 = local.z.atan2(local.x);
+        if phi < 0.0 {
+            phi = phi + 2.0 * PI;
+        }
+        let cos_theta: f32 = (local.y / self.radius).max(-1.0).min(1.0);
+        let theta: f32 = cos_theta.acos();
+        let sin_theta: f32 = (1.0 - cos_theta * cos_theta).sqrt();
+
+        let dpdu = Vector3::new(-2.0 * PI * local.z, 0.0, 2.0 * PI * local.x);
+        let dpdv = PI * self.radius * Vector3::new(cos_theta * phi.cos(),
+                                                   -sin_theta,
+                                                   cos_theta * phi.sin());
+
+        return Some(Intersection::new(ray,
+                                      t,
+                                      self.get_normal(p_hit),
+                                      Point2::new(phi / (2.0 * PI), theta / PI),
+                                      dpdu,
+                                      dpdv));
+    }
+}
+
 impl HasCenter for Sphere {
     fn get_center(&self) -> Point3<f32> {
         return self.origin;
@@ -93,4 +133,155 @@ impl HasNormal for Sphere {
     fn get_normal(&self, p: Point3<f32>) -> Unit<Vector3<f32>> {
         return Unit::new_normalize(p - self.origin);
     }
-}
\ No newline at end of file
+}
+
+impl Sphere {
+    // Cosine of the half angle of the cone the sphere covers seen from p,
+    // None from inside the sphere
+    fn cos_theta_max(&self, p: &Point3<f32>) -> Option<f32> {
+        let distance_squared = (self.origin - p).norm_squared();
+        if distance_squared <= self.radius2 {
+            return None;
+        }
+        let sin2_theta_max = self.radius2 / distance_squared;
+        return Some((1.0 - sin2_theta_max).max(0.0).sqrt());
+    }
+}
+
+impl CanSample for Sphere {
+    fn get_sample(&self, u: f32, v: f32) -> Point3<f32> {
+        let z = 1.0 - 2.0 * u;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        return self.origin + self.radius * Vector3::new(r * phi.cos(), r * phi.sin(), z);
+    }
+
+    fn get_area(&self) -> f32 {
+        return 4.0 * PI * self.radius2;
+    }
+
+    // Uniform direction in the cone the sphere covers, so no sample is
+    // wasted on the hidden side. From inside, uniform by area
+    fn get_sample_from(&self, p: &Point3<f32>, u: f32, v: f32) -> Point3<f32> {
+        let cos_theta_max = match self.cos_theta_max(p) {
+            Some(cos_theta_max) => cos_theta_max,
+            None => return self.get_sample(u, v)
+        };
+
+        let cos_theta = 1.0 - u + u * cos_theta_max;
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        let local = Vector3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        let to_center: Vector3<f32> = self.origin - p;
+        let direction = local_to_world(&local, &Unit::new_normalize(to_center));
+
+        // Closest intersection of the direction with the sphere
+        let d = to_center.norm();
+        let discriminant = (self.radius2 - d * d * sin_theta * sin_theta).max(0.0);
+        let t = d * cos_theta - discriminant.sqrt();
+        return p + t * direction.as_ref();
+    }
+
+    fn get_pdf_from(&self, p: &Point3<f32>, inter: &Intersection) -> f32 {
+        match self.cos_theta_max(p) {
+            Some(cos_theta_max) => return 1.0 / (2.0 * PI * (1.0 - cos_theta_max)),
+            None => {}
+        }
+
+        let to_sample: Vector3<f32> = inter.point - p;
+        let distance_squared = to_sample.norm_squared();
+        let cos_sample: f32 = inter.normal.dot(&to_sample).abs() / distance_squared.sqrt();
+        if cos_sample <= 0.0 {
+            return 0.0;
+        }
+        return distance_squared / (cos_sample * self.get_area());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tracer::materials::Emissive;
+    use tracer::utils::color::Color;
+
+    fn sphere(radius: f32, origin: Point3<f32>) -> Sphere {
+        return Sphere::new(radius, origin, Arc::new(Emissive::new(Color::new(1.0, 1.0, 1.0))));
+    }
+
+    // Stratified directions from p toward points picked by get_sample_from
+    fn sample_directions(sphere: &Sphere, p: &Point3<f32>) -> Vec<Unit<Vector3<f32>>> {
+        let n = 64;
+        let mut directions = Vec::new();
+        for i in 0..n {
+            for j in 0..n {
+                let s = sphere.get_sample_from(p, (i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+                assert!((distance(&s, &sphere.origin) - sphere.radius).abs() < 1e-4, "{:?} is off the sphere", s);
+                let direction = Unit::new_normalize(s - p);
+                // The sample is the visible point in its direction
+                let t = sphere.intersect(&Ray::new(*p, direction.unwrap())).unwrap();
+                assert!((t - distance(p, &s)).abs() < 1e-4, "{:?} is hidden", s);
+                directions.push(direction);
+            }
+        }
+        return directions;
+    }
+
+    // Integral of get_pdf_from over the directions from p with z above
+    // min_z, midpoint rule in (z, phi)
+    fn pdf_integral(sphere: &Sphere, p: &Point3<f32>, min_z: f32) -> f32 {
+        let n = 300;
+        let mut sum = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let z = min_z + (1.0 - min_z) * (i as f32 + 0.5) / n as f32;
+                let phi = 2.0 * PI * (j as f32 + 0.5) / n as f32;
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                let ray = Ray::new(*p, Vector3::new(r * phi.cos(), r * phi.sin(), z));
+                match sphere.get_intersection(&ray) {
+                    Some(inter) => sum += sphere.get_pdf_from(p, &inter),
+                    None => {}
+                }
+            }
+        }
+        return sum * 2.0 * PI * (1.0 - min_z) / (n * n) as f32;
+    }
+
+    #[test]
+    fn cone_sampling_density() {
+        let sphere = sphere(1.0, Point3::new(0.0, 0.0, 3.0));
+        let p = Point3::origin();
+        let directions = sample_directions(&sphere, &p);
+
+        // The density is uniform over the cone the sphere covers
+        let cos_theta_max = (1.0 - 1.0 / 9.0f32).sqrt();
+        let pdf = 1.0 / (2.0 * PI * (1.0 - cos_theta_max));
+        let ray = Ray::new(p, Vector3::new(0.1, 0.2, 1.0));
+        assert!((sphere.get_pdf_from(&p, &sphere.get_intersection(&ray).unwrap()) - pdf).abs() < 1e-4);
+        assert!((pdf_integral(&sphere, &p, cos_theta_max - 0.01) - 1.0).abs() < 1e-2);
+
+        // Samples in cones of growing angle around the center
+        for &angle in &[5.0f32, 10.0, 15.0, 19.0] {
+            let cos_angle = angle.to_radians().cos();
+            let inside = directions.iter().filter(|d| d.z >= cos_angle).count();
+            let expected = pdf * 2.0 * PI * (1.0 - cos_angle);
+            assert!((inside as f32 / directions.len() as f32 - expected).abs() < 1e-2,
+                    "{} of the samples within {} degrees, expected {}",
+                    inside as f32 / directions.len() as f32, angle, expected);
+        }
+    }
+
+    #[test]
+    fn area_sampling_density_from_inside() {
+        // Off center, the near wall covers fewer samples per solid angle
+        let sphere = sphere(1.0, Point3::origin());
+        let p = Point3::new(0.0, 0.0, 0.5);
+        let directions = sample_directions(&sphere, &p);
+        assert!((pdf_integral(&sphere, &p, -1.0) - 1.0).abs() < 1e-2);
+
+        // A quarter of the area, z > 0.5, is seen upward from p
+        let up = directions.iter().filter(|d| d.z > 0.0).count() as f32 / directions.len() as f32;
+        assert!((up - 0.25).abs() < 1e-2, "{} of the samples upward", up);
+        assert!((pdf_integral(&sphere, &p, 0.0) - 0.25).abs() < 1e-2);
+    }
+}
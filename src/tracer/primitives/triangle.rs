@@ -1,25 +1,33 @@
 
 
-use tracer::primitives::{HasBoundingBox, HasColor, Intersectable, HasCenter, HasNormal, CanSample};
+use tracer::primitives::{HasBoundingBox, HasMaterial, Intersectable, HasIntersection, HasCenter, HasNormal, CanSample};
 use tracer::primitives::bounding_box::BoundingBox;
 use tracer::utils::ray::Ray;
-use tracer::utils::color::Color;
+use tracer::materials::Material;
+use tracer::utils::intersection::Intersection;
 
-use nalgebra::{Point3, Vector3};
+use nalgebra::{Point2, Point3, Vector3};
 use nalgebra::core::Unit;
 
+use std::sync::Arc;
+
+#[derive(Clone)]
 pub struct Triangle {
     pub v0: Point3<f32>,
     pub v1: Point3<f32>,
     pub v2: Point3<f32>,
-    pub color: Color,
+    pub material: Arc<Material>,
     pub normal: Unit<Vector3<f32>>,
+    // Normals of v0, v1 and v2, interpolated for shading
+    pub normals: Option<[Vector3<f32>; 3]>,
+    // Texture coordinates of v0, v1 and v2
+    pub uvs: Option<[Point2<f32>; 3]>,
     e1: Vector3<f32>,
     e2: Vector3<f32>
 }
 
 impl Triangle {
-    pub fn new(v0: Point3<f32>, v1: Point3<f32>, v2: Point3<f32>, color: Color) -> Triangle{
+    pub fn new(v0: Point3<f32>, v1: Point3<f32>, v2: Point3<f32>, material: Arc<Material>) -> Triangle{
         let e1 = v1 - v0;
         let e2 = v2 - v0;
         return Triangle {
@@ -27,7 +35,9 @@ impl Triangle {
             v1: v1,
             v2: v2,
             normal: Unit::new_normalize(e1.cross(&e2)),
-            color: color,
+            material: material,
+            normals: None,
+            uvs: None,
             e1: e1,
             e2: e2
         }
@@ -42,64 +52,143 @@ fn max_float(v0: f32, v1: f32) -> f32{
     return if v0 < v1 { v1 } else { v0 }
 }
 
+// Shared with the triangles of a TriangleMesh
+pub fn triangle_bounding_box(v0: &Point3<f32>, v1: &Point3<f32>, v2: &Point3<f32>) -> BoundingBox {
+    return BoundingBox {
+        min: Point3::new(min_float(min_float(v0.x, v1.x), v2.x),
+                         min_float(min_float(v0.y, v1.y), v2.y),
+                         min_float(min_float(v0.z, v1.z), v2.z)),
+        max: Point3::new(max_float(max_float(v0.x, v1.x), v2.x),
+                         max_float(max_float(v0.y, v1.y), v2.y),
+                         max_float(max_float(v0.z, v1.z), v2.z))
+    }
+}
+
 impl HasBoundingBox for Triangle {
     fn get_bounding_box(&self) -> BoundingBox {
-        return BoundingBox {
-            min: Point3::new(min_float(min_float(self.v0.x, self.v1.x), self.v2.x),
-                             min_float(min_float(self.v0.y, self.v1.y), self.v2.y),
-                             min_float(min_float(self.v0.z, self.v1.z), self.v2.z)),
-            max: Point3::new(max_float(max_float(self.v0.x, self.v1.x), self.v2.x),
-                             max_float(max_float(self.v0.y, self.v1.y), self.v2.y),
-                             max_float(max_float(self.v0.z, self.v1.z), self.v2.z))
-        }
+        return triangle_bounding_box(&self.v0, &self.v1, &self.v2);
    }
 }
 
-impl HasColor for Triangle {
-    fn get_color(&self) -> Color {
-        return Color::new_copy(&self.color);
+impl HasMaterial for Triangle {
+    fn get_material(&self) -> Arc<Material> {
+        return self.material.clone();
     }
 }
 
-// Möller–Trumbore
+// Möller–Trumbore, returns the distance and the barycentric (u, v)
+pub fn moller_trumbore(v0: &Point3<f32>, e1: &Vector3<f32>, e2: &Vector3<f32>,
+                     ray: &Ray) -> Option<(f32, f32, f32)> {
+
+    // Calculate planes normal vector
+    let pvec: Vector3<f32> = ray.direction.cross(e2);
+    let det: f32 = e1.dot(&pvec);
+
+    // Ray is parallel to plane. The threshold follows the size of the
+    // triangle, so it holds for meshes in any unit (instances are
+    // intersected in their own space)
+    if det * det < 1e-10 * e1.norm_squared() * e2.norm_squared() {
+        return None;
+    }
+
+    let inv_det: f32 = 1.0 / det;
+    let tvec: Vector3<f32> = ray.origin - *v0;
+    let u: f32 = tvec.dot(&pvec) * inv_det;
+    if u < 0.0 || u > 1.0 {
+        return None;
+    }
+
+    let qvec: Vector3<f32> = tvec.cross(e1);
+    let v: f32 = ray.direction.dot(&qvec) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    // W = 1 - u - v
+
+    let distance = e2.dot(&qvec) * inv_det;
+    return Some((distance, u, v));
+}
+
 impl Intersectable for Triangle {
     fn intersect(&self, ray: &Ray) -> Option<f32> {
+      return moller_trumbore(&self.v0, &self.e1, &self.e2, ray).map(|(distance, _, _)| distance);
+    }
+}
+
+// Surface interaction for a hit returned by moller_trumbore. Without vertex
+// UVs the triangle is parametrized by its barycentrics
+pub fn triangle_intersection(ray: &Ray, hit: (f32, f32, f32),
+                             e1: &Vector3<f32>, e2: &Vector3<f32>,
+                             normal: &Unit<Vector3<f32>>,
+                             normals: &Option<[Vector3<f32>; 3]>,
+                             uvs: &Option<[Point2<f32>; 3]>) -> Intersection {
+    let (distance, u, v) = hit;
+    let barycentrics = Vector3::new(1.0 - u - v, u, v);
+    let (uv, dpdu, dpdv) = match *uvs {
+        Some(uvs) => {
+            let uv = Point2::new(barycentrics.x * uvs[0].x + barycentrics.y * uvs[1].x + barycentrics.z * uvs[2].x,
+                                 barycentrics.x * uvs[0].y + barycentrics.y * uvs[1].y + barycentrics.z * uvs[2].y);
+
+            // Solve e1 = du1 * dpdu + dv1 * dpdv and e2 = du2 * dpdu + dv2 * dpdv
+            let duv1 = uvs[1] - uvs[0];
+            let duv2 = uvs[2] - uvs[0];
+            let det = duv1.x * duv2.y - duv1.y * duv2.x;
+            if det.abs() < 1e-8 {
+                // Degenerate mapping, any frame of the triangle plane will do
+                (uv, *e1, *e2)
+            }
+            else {
+                let inv_det = 1.0 / det;
+                (uv,
+                 (duv2.y * *e1 - duv1.y * *e2) * inv_det,
+                 (duv1.x * *e2 - duv2.x * *e1) * inv_det)
+            }
+        },
+        None => (Point2::new(u, v), *e1, *e2)
+    };
+
+    let shading_normal = match *normals {
+        Some(normals) => {
+            let n = barycentrics.x * normals[0] + barycentrics.y * normals[1] + barycentrics.z * normals[2];
+            if n.norm() > 0.0 { Some(Unit::new_normalize(n)) } else { None }
+        },
+        None => None
+    };
+
+    // Vertex normals are trusted over the winding order, the geometric
+    // normal is flipped to their side so front_face agrees with them
+    let normal = match shading_normal {
+        Some(n) if n.dot(normal) < 0.0 => -*normal,
+        _ => *normal
+    };
+
+    let mut inter = Intersection::new(ray,
+                                      distance,
+                                      normal,
+                                      uv,
+                                      dpdu,
+                                      dpdv);
+    inter.barycentrics = barycentrics;
+    match shading_normal {
+        Some(n) => inter.shading_normal = n,
+        None => {}
+    }
+    return inter;
+}
 
-      // Calculate planes normal vector
-      let pvec: Vector3<f32> = ray.direction.cross(&self.e2);
-      let det: f32 = self.e1.dot(&pvec);
-   
-      // Ray is parallel to plane
-      if det < 0.00001 && det > -0.00001 {
-          return None;
-      }
-
-      let inv_det: f32 = 1.0 / det;
-      let tvec: Vector3<f32> = ray.origin - self.v0;
-      let u: f32 = tvec.dot(&pvec) * inv_det;
-      if u < 0.0 || u > 1.0 {
-          return None;
-      }
-
-      let qvec: Vector3<f32> = tvec.cross(&self.e1);
-      let v: f32 = ray.direction.dot(&qvec) * inv_det;
-      if v < 0.0 || u + v > 1.0 {
-          return None;
-      }
-
-      // W = 1 - u - v
-
-      let distance = self.e2.dot(&qvec) * inv_det;
-      return Some(distance);
+impl HasIntersection for Triangle {
+    fn get_intersection(&self, ray: &Ray) -> Option<Intersection> {
+      return moller_trumbore(&self.v0, &self.e1, &self.e2, ray).map(|hit| {
+          triangle_intersection(ray, hit, &self.e1, &self.e2, &self.normal, &self.normals, &self.uvs)
+      });
     }
 }
 
 impl HasCenter for Triangle {
     fn get_center(&self) -> Point3<f32> {
       let bbox: BoundingBox = self.get_bounding_box();
-        return Point3::new(bbox.max.x - bbox.min.x,
-                           bbox.max.y - bbox.min.y,
-                           bbox.max.z - bbox.min.z);
+        return bbox.get_center();
     }
 }
 
@@ -110,18 +199,26 @@ impl HasNormal for Triangle {
     }
 }
 
+// Uniform point on the triangle
+pub fn sample_triangle(v0: &Point3<f32>, v1: &Point3<f32>, v2: &Point3<f32>, u: f32, v: f32) -> Point3<f32> {
+    let u_sqrt = u.sqrt();
+    let c1 = 1.0 - u_sqrt;
+    let c2 = u_sqrt * (1.0 - v);
+    let c3 = v * u_sqrt;
+
+    let x = c1 * v0.x + c2 * v1.x + c3 * v2.x;
+    let y = c1 * v0.y + c2 * v1.y + c3 * v2.y;
+    let z = c1 * v0.z + c2 * v1.z + c3 * v2.z;
+
+    return Point3::new(x, y, z);
+}
+
 impl CanSample for Triangle {
     fn get_sample(&self, u: f32, v: f32) -> Point3<f32> {
-        let u_sqrt = u.sqrt();
-        let v_sqrt = v.sqrt();
-        let c1 = 1.0 - u_sqrt;
-        let c2 = u_sqrt * (1.0 - v_sqrt);
-        let c3 = v * u_sqrt;
-
-        let x = c1 * self.v0.x + c2 * self.v1.x + c3 * self.v2.x;
-        let y = c1 * self.v0.y + c2 * self.v1.y + c3 * self.v2.y;
-        let z = c1 * self.v0.z + c2 * self.v1.z + c3 * self.v2.z;
+        return sample_triangle(&self.v0, &self.v1, &self.v2, u, v);
+    }
 
-        return Point3::new(x, y, z);
+    fn get_area(&self) -> f32 {
+        return 0.5 * self.e1.cross(&self.e2).norm();
     }
 }
\ No newline at end of file
@@ -0,0 +1,213 @@
+use tracer::primitives::{HasBoundingBox, HasMaterial, Intersectable, HasIntersection, HasCenter, HasNormal, CanSample};
+use tracer::primitives::bounding_box::BoundingBox;
+use tracer::primitives::triangle::{moller_trumbore, triangle_intersection, triangle_bounding_box, sample_triangle};
+use tracer::utils::ray::Ray;
+use tracer::materials::Material;
+use tracer::utils::intersection::Intersection;
+use tracer::utils::transform::Transform;
+
+use nalgebra::{Point2, Point3, Vector3};
+use nalgebra::core::Unit;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Triangles sharing their vertices. Every attribute array is indexed by the
+// same vertex index, normals and uvs are either empty or one per position
+#[derive(Clone)]
+pub struct TriangleMesh {
+    pub positions: Vec<Point3<f32>>,
+    pub normals: Vec<Vector3<f32>>,
+    pub uvs: Vec<Point2<f32>>,
+    pub indices: Vec<[u32; 3]>,
+    pub materials: Vec<Arc<Material>>,
+    // Index in materials of every triangle
+    pub material_indices: Vec<u32>
+}
+
+impl TriangleMesh {
+    pub fn nb_triangles(&self) -> usize {
+        return self.indices.len();
+    }
+
+    // Moves the vertices for good, an Instance moves the mesh without
+    // copying it
+    pub fn transform(&mut self, transform: &Transform) {
+        for p in &mut self.positions {
+            *p = transform.transform_point(p);
+        }
+        for n in &mut self.normals {
+            *n = transform.transform_normal(n).unwrap();
+        }
+    }
+
+    // Mesh of the given triangles, in that order, with only the vertices they
+    // use
+    pub fn extract(&self, triangles: &[usize]) -> TriangleMesh {
+        let mut mesh = TriangleMesh {
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::with_capacity(triangles.len()),
+            materials: self.materials.clone(),
+            material_indices: Vec::with_capacity(triangles.len())
+        };
+        let mut new_vertices: HashMap<u32, u32> = HashMap::new();
+        for &t in triangles {
+            let mut face = [0; 3];
+            for (k, &v) in self.indices[t].iter().enumerate() {
+                let next = mesh.positions.len() as u32;
+                face[k] = *new_vertices.entry(v).or_insert(next);
+                if face[k] == next {
+                    mesh.positions.push(self.positions[v as usize]);
+                    if !self.normals.is_empty() {
+                        mesh.normals.push(self.normals[v as usize]);
+                    }
+                    if !self.uvs.is_empty() {
+                        mesh.uvs.push(self.uvs[v as usize]);
+                    }
+                }
+            }
+            mesh.indices.push(face);
+            mesh.material_indices.push(self.material_indices[t]);
+        }
+        return mesh;
+    }
+
+    // One primitive per triangle, each only holding a reference to the mesh
+    pub fn get_primitives(mesh: &Arc<TriangleMesh>) -> Vec<MeshTriangle> {
+        return (0..mesh.nb_triangles()).map(|i| MeshTriangle {
+            mesh: mesh.clone(),
+            index: i as u32
+        }).collect();
+    }
+}
+
+// Triangle at index in mesh, its edges and normal are computed when needed
+// instead of being stored
+#[derive(Clone)]
+pub struct MeshTriangle {
+    pub mesh: Arc<TriangleMesh>,
+    pub index: u32
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> [usize; 3] {
+        let i = self.mesh.indices[self.index as usize];
+        return [i[0] as usize, i[1] as usize, i[2] as usize];
+    }
+
+    fn positions(&self) -> (Point3<f32>, Point3<f32>, Point3<f32>) {
+        let v = self.vertices();
+        let p = &self.mesh.positions;
+        return (p[v[0]], p[v[1]], p[v[2]]);
+    }
+}
+
+impl HasBoundingBox for MeshTriangle {
+    fn get_bounding_box(&self) -> BoundingBox {
+        let (v0, v1, v2) = self.positions();
+        return triangle_bounding_box(&v0, &v1, &v2);
+    }
+}
+
+impl HasMaterial for MeshTriangle {
+    fn get_material(&self) -> Arc<Material> {
+        let m = self.mesh.material_indices[self.index as usize];
+        return self.mesh.materials[m as usize].clone();
+    }
+}
+
+impl Intersectable for MeshTriangle {
+    fn intersect(&self, ray: &Ray) -> Option<f32> {
+        let (v0, v1, v2) = self.positions();
+        return moller_trumbore(&v0, &(v1 - v0), &(v2 - v0), ray).map(|(distance, _, _)| distance);
+    }
+}
+
+impl HasIntersection for MeshTriangle {
+    fn get_intersection(&self, ray: &Ray) -> Option<Intersection> {
+        let (v0, v1, v2) = self.positions();
+        let e1 = v1 - v0;
+        let e2 = v2 - v0;
+        let hit = match moller_trumbore(&v0, &e1, &e2, ray) {
+            Some(hit) => hit,
+            None => return None
+        };
+
+        let v = self.vertices();
+        let normals = if self.mesh.normals.is_empty() {
+            None
+        }
+        else {
+            Some([self.mesh.normals[v[0]], self.mesh.normals[v[1]], self.mesh.normals[v[2]]])
+        };
+        let uvs = if self.mesh.uvs.is_empty() {
+            None
+        }
+        else {
+            Some([self.mesh.uvs[v[0]], self.mesh.uvs[v[1]], self.mesh.uvs[v[2]]])
+        };
+
+        let normal = Unit::new_normalize(e1.cross(&e2));
+        return Some(triangle_intersection(ray, hit, &e1, &e2, &normal, &normals, &uvs));
+    }
+}
+
+impl HasCenter for MeshTriangle {
+    fn get_center(&self) -> Point3<f32> {
+        return self.get_bounding_box().get_center();
+    }
+}
+
+#[allow(unused_variables)]
+impl HasNormal for MeshTriangle {
+    fn get_normal(&self, p: Point3<f32>) -> Unit<Vector3<f32>> {
+        let (v0, v1, v2) = self.positions();
+        return Unit::new_normalize((v1 - v0).cross(&(v2 - v0)));
+    }
+}
+
+impl CanSample for MeshTriangle {
+    fn get_sample(&self, u: f32, v: f32) -> Point3<f32> {
+        let (v0, v1, v2) = self.positions();
+        return sample_triangle(&v0, &v1, &v2, u, v);
+    }
+
+    fn get_area(&self) -> f32 {
+        let (v0, v1, v2) = self.positions();
+        return 0.5 * (v1 - v0).cross(&(v2 - v0)).norm();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tracer::materials::{Lambertian, Emissive};
+    use tracer::utils::color::Color;
+
+    #[test]
+    fn extract_keeps_only_the_used_vertices() {
+        // Strip of three triangles, the last one emitting
+        let mesh = TriangleMesh {
+            positions: (0..5).map(|i| Point3::new(i as f32, (i % 2) as f32, 0.0)).collect(),
+            normals: Vec::new(),
+            uvs: (0..5).map(|i| Point2::new(i as f32, 0.0)).collect(),
+            indices: vec![[0, 1, 2], [1, 3, 2], [2, 3, 4]],
+            materials: vec![Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+                            Arc::new(Emissive::new(Color::new(1.0, 1.0, 1.0)))],
+            material_indices: vec![0, 0, 1]
+        };
+
+        let part = mesh.extract(&[2, 0]);
+        assert_eq!(part.nb_triangles(), 2);
+        assert!(part.indices == vec![[0, 1, 2], [3, 4, 0]]);
+        assert!(part.positions == vec![mesh.positions[2], mesh.positions[3], mesh.positions[4],
+                                       mesh.positions[0], mesh.positions[1]]);
+        assert!(part.uvs == vec![mesh.uvs[2], mesh.uvs[3], mesh.uvs[4], mesh.uvs[0], mesh.uvs[1]]);
+        assert!(part.normals.is_empty());
+        assert!(part.material_indices == vec![1, 0]);
+        assert_eq!(part.materials.len(), 2);
+    }
+}
@@ -1,32 +1,111 @@
-
-use tracer::primitives::{HasColor, HasCenter, Intersectable, HasNormal};
+use tracer::primitives::{HasMaterial, HasCenter, Intersectable, HasIntersection, HasBoundingBox};
 use tracer::primitives::Primitive;
 use tracer::primitives::BoundingBox;
 use tracer::utils::ray::Ray;
-use tracer::utils::color::Color;
+use tracer::materials::Material;
+use tracer::utils::intersection::Intersection;
 
-use nalgebra::{distance, Point3, Vector3};
-use nalgebra::core::Unit;
+use nalgebra::{distance, Point3};
 
 use std::f32;
 use std::usize;
+use std::cmp;
 use std::cmp::Ordering;
+use std::sync::Arc;
+
+// What a BVH can hold: the primitives of the scene, or the instances of
+// the top level BVH
+pub trait BVHPrimitive: HasBoundingBox + Intersectable
+{
+   // Full record of the hit found by Intersectable::intersect
+   fn get_hit_info(&self, ray: &Ray) -> Option<HitInfo>;
+
+   // Stores in the hit the index of the primitive in the BVH
+   fn record_index(&self, h_info: &mut HitInfo, index: usize)
+   {
+      h_info.intersection.primitive_index = index;
+   }
+
+   // Whether the primitive lies between the ray origin and max_distance,
+   // primitives holding other ones stop at the first hit found inside
+   fn occluded(&self, ray: &Ray, max_distance: f32) -> bool
+   {
+      match self.intersect(ray)
+      {
+         Some(t) => return t > 0.0 && t < max_distance,
+         None => return false
+      }
+   }
+}
+
+// The top level BVH shares its instances with the list of objects
+impl<T: BVHPrimitive> BVHPrimitive for Arc<T>
+{
+   fn get_hit_info(&self, ray: &Ray) -> Option<HitInfo>
+   {
+      return (**self).get_hit_info(ray);
+   }
+
+   fn record_index(&self, h_info: &mut HitInfo, index: usize)
+   {
+      (**self).record_index(h_info, index);
+   }
+
+   fn occluded(&self, ray: &Ray, max_distance: f32) -> bool
+   {
+      return (**self).occluded(ray, max_distance);
+   }
+}
+
+impl BVHPrimitive for Primitive
+{
+   fn get_hit_info(&self, ray: &Ray) -> Option<HitInfo>
+   {
+      match self.get_intersection(ray)
+      {
+         Some(x) => {
+            let h_info = HitInfo {
+               material: self.get_material(),
+               intersection: x
+            };
+
+            return Some(h_info);
+         },
+         None => {
+            return None;
+         }
+      }
+   }
+}
+
+// Only the distance is computed during traversal, the full record is built
+// once for the closest primitive
+fn build_hit_info<T: BVHPrimitive>(p: &T, index: usize, ray: &Ray) -> Option<HitInfo>
+{
+   return p.get_hit_info(ray).map(|mut h_info| {
+      p.record_index(&mut h_info, index);
+      h_info
+   });
+}
 
-struct BVHNode
+struct BVHNode<T>
 {
    pub bbox: BoundingBox,
-   pub primitive: Option<Primitive>,
-   pub left: Option<Box<BVHNode>>,
-   pub right: Option<Box<BVHNode>>
+   // Axis along which the left child comes before the right child
+   pub axis: usize,
+   pub primitives: Vec<(usize, T)>,
+   pub left: Option<Box<BVHNode<T>>>,
+   pub right: Option<Box<BVHNode<T>>>
 }
 
-impl BVHNode
+impl<T> BVHNode<T>
 {
-   pub fn new_leaf(primitive: Primitive) -> BVHNode
+   pub fn new_leaf(primitives: Vec<(usize, T)>, bbox: BoundingBox) -> BVHNode<T>
    {
       let n = BVHNode {
-         bbox: BoundingBox::new(&primitive),
-         primitive: Some(primitive),
+         bbox: bbox,
+         axis: 0,
+         primitives: primitives,
          left: None,
          right: None
       };
@@ -34,199 +113,712 @@ impl BVHNode
       return n;
    }
 
-   pub fn new(left: Box<BVHNode>, right: Box<BVHNode>) -> BVHNode
+   pub fn new(left: Box<BVHNode<T>>, right: Box<BVHNode<T>>) -> BVHNode<T>
    {
+      // Order the children along the axis that separates them the most so
+      // traversal can visit the nearest one first
+      let left_center = left.bbox.get_center();
+      let right_center = right.bbox.get_center();
+      let mut axis = 0;
+      for a in 1..3
+      {
+         if (right_center[a] - left_center[a]).abs() >
+            (right_center[axis] - left_center[axis]).abs()
+         {
+            axis = a;
+         }
+      }
+
+      let (left, right) = if left_center[axis] <= right_center[axis]
+      {
+         (left, right)
+      }
+      else
+      {
+         (right, left)
+      };
 
       let n = BVHNode {
          bbox: BoundingBox::new_from(&left.bbox, &right.bbox),
-         primitive: None,
+         axis: axis,
+         primitives: Vec::new(),
          left: Some(left),
          right: Some(right)
       };
 
       return n;
    }
+}
 
-   pub fn intersect(&self, ray: &Ray) -> Option<HitInfo>
+#[allow(dead_code)]
+#[derive(Clone)]
+pub enum BVHBuildMethod
+{
+   // Greedily pair every node with its closest neighbour, O(n²) per level
+   NearestNeighbour,
+   // Top-down, binned surface area heuristic
+   SurfaceAreaHeuristic
+}
+
+#[derive(Clone)]
+pub struct BVHBuildOptions
+{
+   pub method: BVHBuildMethod,
+   // Number of candidate split planes per node (SAH only)
+   pub nb_bins: usize,
+   // A node with more primitives than this is always split (SAH only)
+   pub max_leaf_size: usize,
+   // Relative costs of visiting a node and of intersecting a primitive
+   pub traversal_cost: f32,
+   pub intersection_cost: f32
+}
+
+impl Default for BVHBuildOptions
+{
+   fn default() -> BVHBuildOptions
+   {
+      return BVHBuildOptions {
+         method: BVHBuildMethod::SurfaceAreaHeuristic,
+         nb_bins: 16,
+         max_leaf_size: 4,
+         traversal_cost: 0.125,
+         intersection_cost: 1.0
+      };
+   }
+}
+
+struct BuildItem<T>
+{
+   index: usize,
+   primitive: T,
+   bbox: BoundingBox,
+   center: Point3<f32>
+}
+
+fn build_leaf<T>(items: Vec<BuildItem<T>>, bbox: BoundingBox) -> Box<BVHNode<T>>
+{
+   let primitives = items.into_iter().map(|item| (item.index, item.primitive)).collect();
+   return Box::new(BVHNode::new_leaf(primitives, bbox));
+}
+
+fn build_halves<T>(items: Vec<BuildItem<T>>, options: &BVHBuildOptions) -> Box<BVHNode<T>>
+{
+   let mut left = items;
+   let half = left.len() / 2;
+   let right = left.split_off(half);
+   return Box::new(BVHNode::new(build_sah(left, options), build_sah(right, options)));
+}
+
+fn build_sah<T>(items: Vec<BuildItem<T>>, options: &BVHBuildOptions) -> Box<BVHNode<T>>
+{
+   let mut bbox = BoundingBox::new_empty();
+   let mut centers = BoundingBox::new_empty();
+   for item in &items
+   {
+      bbox = BoundingBox::new_from(&bbox, &item.bbox);
+      centers = BoundingBox::new_from(&centers, &BoundingBox::new_from_point(&item.center));
+   }
+
+   if items.len() == 1
+   {
+      return build_leaf(items, bbox);
+   }
+
+   // Split along the axis where the centers are the most spread
+   let mut axis = 0;
+   for a in 1..3
+   {
+      if centers.extent(a) > centers.extent(axis)
+      {
+         axis = a;
+      }
+   }
+
+   let axis_min = centers.min[axis];
+   let axis_extent = centers.extent(axis);
+   if axis_extent <= 0.0
+   {
+      // All centers are the same point, no plane can separate them
+      if items.len() <= options.max_leaf_size
+      {
+         return build_leaf(items, bbox);
+      }
+
+      return build_halves(items, options);
+   }
+
+   let nb_bins = cmp::max(options.nb_bins, 2);
+   let bin_of = |center: &Point3<f32>| -> usize {
+      let b = (nb_bins as f32 * (center[axis] - axis_min) / axis_extent) as usize;
+      return cmp::min(b, nb_bins - 1);
+   };
+
+   let mut bin_counts: Vec<usize> = vec![0; nb_bins];
+   let mut bin_boxes: Vec<BoundingBox> = (0..nb_bins).map(|_| BoundingBox::new_empty()).collect();
+   for item in &items
+   {
+      let b = bin_of(&item.center);
+      bin_counts[b] = bin_counts[b] + 1;
+      bin_boxes[b] = BoundingBox::new_from(&bin_boxes[b], &item.bbox);
+   }
+
+   // Sweep from the right to know the area and count on the right of every plane,
+   // then sweep from the left and evaluate the cost of each plane
+   let mut right_areas: Vec<f32> = vec![0.0; nb_bins];
+   let mut right_counts: Vec<usize> = vec![0; nb_bins];
+   let mut right_box = BoundingBox::new_empty();
+   let mut right_count = 0;
+   for b in (1..nb_bins).rev()
+   {
+      right_box = BoundingBox::new_from(&right_box, &bin_boxes[b]);
+      right_count = right_count + bin_counts[b];
+      right_areas[b] = right_box.surface_area();
+      right_counts[b] = right_count;
+   }
+
+   let parent_area = bbox.surface_area();
+   let mut best_cost = f32::MAX;
+   let mut best_split = 0;
+   let mut left_box = BoundingBox::new_empty();
+   let mut left_count = 0;
+   for b in 1..nb_bins
+   {
+      left_box = BoundingBox::new_from(&left_box, &bin_boxes[b - 1]);
+      left_count = left_count + bin_counts[b - 1];
+      if left_count == 0 || right_counts[b] == 0
+      {
+         continue;
+      }
+
+      let cost = options.traversal_cost +
+                 options.intersection_cost *
+                 (left_box.surface_area() * left_count as f32 +
+                  right_areas[b] * right_counts[b] as f32) / parent_area;
+      if cost < best_cost
+      {
+         best_cost = cost;
+         best_split = b;
+      }
+   }
+
+   if best_split == 0
+   {
+      // Degenerate parent box, the costs could not be compared
+      return build_halves(items, options);
+   }
+
+   let leaf_cost = options.intersection_cost * items.len() as f32;
+   if items.len() <= options.max_leaf_size && leaf_cost <= best_cost
+   {
+      return build_leaf(items, bbox);
+   }
+
+   let (left, right): (Vec<BuildItem<T>>, Vec<BuildItem<T>>) =
+      items.into_iter().partition(|item| bin_of(&item.center) < best_split);
+
+   return Box::new(BVHNode::new(build_sah(left, options), build_sah(right, options)));
+}
+
+fn build_nearest_neighbour<T>(items: Vec<BuildItem<T>>) -> Box<BVHNode<T>>
+{
+   // 1. create 1 node per primitive
+   // 2. group node together (closest)
+   // 3. When there is only 1 node, set as root
+   let mut nodes: Vec<Box<BVHNode<T>>> = Vec::with_capacity(items.len());
+   for item in items
    {
-      let bbox_inter = self.bbox.intersect(ray);
-      match bbox_inter
+      let bbox = item.bbox;
+      let node = Box::new(BVHNode::new_leaf(vec![(item.index, item.primitive)], bbox));
+      nodes.push(node);
+   }
+
+   while nodes.len() > 1
+   {
+      let mut merged_nodes = Vec::new();
+
+      // take all nodes and merge them to the closest other node
+      while nodes.len() > 1
       {
-         Some(_) => {
-            match self.primitive
+         let mut min_dist = f32::MAX;
+         let mut min_dist_idx = usize::MAX;
+         let last = nodes.pop().unwrap();
+         let last_center = last.bbox.get_center();
+         for i in 0..nodes.len()
+         {
+            //find closest
+            let d = distance(&last_center, &nodes[i].bbox.get_center());
+            if d < min_dist
             {
-               Some(ref p) => 
-               {
-                  let inter_dist = p.intersect(ray);
-                  match inter_dist
-                  {
-                     Some(x) => {
-                        if x < 1.0 //hit ourselves
-                        {
-                           return None;
-                        }
-
-                        let p_hit = ray.origin + x * ray.direction.as_ref();
-                        let h_info = HitInfo {
-                           color: p.get_color(),
-                           normal: p.get_normal(p_hit),
-                           p_hit: p_hit,
-                           distance: x
-                        };
-
-                        return Some(h_info);
-                     },
-                     None => {
-                        return None;
-                     }
-                  }
-               },
-               None => 
-               {
-                  let mut left_inter = None;
-                  let mut right_inter = None;
-                  match self.left
-                  {
-                     Some(ref left) => {
-                        left_inter = left.intersect(ray);
-
-                     }
-                     None => {
-
-                     }
-                  }
-                  match self.right
-                  {
-                     Some(ref right) => {
-                        right_inter = right.intersect(ray);
-
-                     }
-                     None => {
-
-                     }
-                  }
-
-                  if left_inter.is_none() && right_inter.is_none()
-                  {
-                     return None;
-                  }
-                  else if left_inter.is_none()
-                  {
-                     return right_inter;
-                  }
-                  else if right_inter.is_none()
-                  {
-                     return left_inter;
-                  }
-                  else
-                  {
-                     if left_inter.partial_cmp(&right_inter) == Some(Ordering::Less)
-                     {
-                        return left_inter;
-                     }
-                     else
-                     {
-                        return right_inter;
-                     }
-
-                  }
-               }
+              min_dist = d;
+              min_dist_idx = i;
             }
-         },
-         None => {
          }
+
+         let closest = nodes.swap_remove(min_dist_idx);
+         let t = Box::new(BVHNode::new(last, closest));
+         merged_nodes.push(t);
+      }
+
+      if nodes.len() == 1
+      {
+         merged_nodes.push(nodes.pop().unwrap());
       }
-      
-         
-      return None;
+
+      nodes = merged_nodes;
    }
+
+   assert!(nodes.len() == 1);
+   return nodes.pop().unwrap();
 }
 
-pub struct BoundingVolumeHierarchy
+// Unbounded primitives (planes) have no finite bounding box, they are kept
+// aside from the tree and tested against every ray
+pub struct BoundingVolumeHierarchy<T = Primitive>
 {
-   root: Box<BVHNode>
+   root: Option<Box<BVHNode<T>>>,
+   unbounded: Vec<(usize, T)>
 }
 
 pub struct HitInfo
 {
-   pub color: Color,
-   pub normal: Unit<Vector3<f32>>,
-   pub p_hit: Point3<f32>,
-   pub distance: f32
+   pub material: Arc<Material>,
+   pub intersection: Intersection
 }
 
 impl PartialEq for HitInfo
 {
    fn eq(&self, other: &HitInfo) -> bool {
-        self.distance == other.distance
+        self.intersection.distance == other.intersection.distance
     }
 }
 impl PartialOrd for HitInfo
 {
    fn partial_cmp(&self, other: &HitInfo) -> Option<Ordering> {
-        self.distance.partial_cmp(&other.distance)
+        self.intersection.distance.partial_cmp(&other.intersection.distance)
     }
 }
 
-impl BoundingVolumeHierarchy
+impl<T: BVHPrimitive> BoundingVolumeHierarchy<T>
 {
-   pub fn new(primitives: Vec<Primitive>) -> BoundingVolumeHierarchy
+   pub fn new(primitives: Vec<T>) -> BoundingVolumeHierarchy<T>
    {
-      // 1. create 1 node per primitive
-      // 2. group node together (closest)
-      // 3. When there is only 1 node, set as root
-      let mut nodes: Vec<Box<BVHNode>> = Vec::with_capacity(primitives.len());
-      for p in primitives
+      return BoundingVolumeHierarchy::new_with_options(primitives, &BVHBuildOptions::default());
+   }
+
+   pub fn new_with_options(primitives: Vec<T>,
+                           options: &BVHBuildOptions) -> BoundingVolumeHierarchy<T>
+   {
+      let mut items: Vec<BuildItem<T>> = Vec::with_capacity(primitives.len());
+      let mut unbounded: Vec<(usize, T)> = Vec::new();
+      for (i, p) in primitives.into_iter().enumerate()
       {
-         let node = Box::new(BVHNode::new_leaf(p));
-         nodes.push(node);
+         if !p.is_bounded()
+         {
+            unbounded.push((i, p));
+            continue;
+         }
+
+         let bbox = p.get_bounding_box();
+         let center = bbox.get_center();
+         items.push(BuildItem {
+            index: i,
+            primitive: p,
+            bbox: bbox,
+            center: center
+         });
       }
 
-      while nodes.len() > 1
+      let root = if items.is_empty()
+      {
+         None
+      }
+      else
       {
-         let mut merged_nodes = Vec::new();
+         match options.method
+         {
+            BVHBuildMethod::NearestNeighbour => Some(build_nearest_neighbour(items)),
+            BVHBuildMethod::SurfaceAreaHeuristic => Some(build_sah(items, options))
+         }
+      };
+
+      let m = BoundingVolumeHierarchy {
+         root: root,
+         unbounded: unbounded
+      };
 
-         // take all nodes and merge them to the closest other node
-         while nodes.len() > 1
+      return m;
+   }
+
+   // Box around the bounded primitives, empty if there are none
+   pub fn get_bounding_box(&self) -> BoundingBox
+   {
+      match self.root
+      {
+         Some(ref root) => {
+            return BoundingBox {
+               min: root.bbox.min,
+               max: root.bbox.max
+            };
+         }
+         None => {
+            return BoundingBox::new_empty();
+         }
+      }
+   }
+
+   pub fn is_bounded(&self) -> bool
+   {
+      return self.unbounded.is_empty();
+   }
+
+   // Closest hit in front of the ray origin, rays leaving a surface are
+   // spawned off it so they do not hit it again
+   pub fn intersect(&self, ray: &Ray) -> Option<HitInfo>
+   {
+      match self.closest(ray)
+      {
+         Some((index, p, _)) => build_hit_info(p, index, ray),
+         None => None
+      }
+   }
+
+   // Distance only, without building the hit record
+   pub fn intersect_distance(&self, ray: &Ray) -> Option<f32>
+   {
+      return self.closest(ray).map(|(_, _, t)| t);
+   }
+
+   fn closest(&self, ray: &Ray) -> Option<(usize, &T, f32)>
+   {
+      let mut t_max = f32::MAX;
+      let mut closest: Option<(usize, &T)> = None;
+
+      for &(index, ref p) in &self.unbounded
+      {
+         match p.intersect(ray)
+         {
+            Some(t) if t > 0.0 && t < t_max => {
+               t_max = t;
+               closest = Some((index, p));
+            }
+            _ => {}
+         }
+      }
+
+      // Front to back traversal, a node is skipped as soon as its box is
+      // entered beyond the closest hit found so far
+      let mut stack: Vec<(&BVHNode<T>, f32)> = Vec::with_capacity(64);
+      match self.root
+      {
+         Some(ref root) => {
+            match root.bbox.intersect(ray)
+            {
+               Some(t) => stack.push((root, t)),
+               None => {}
+            }
+         }
+         None => {}
+      }
+
+      while let Some((node, t_entry)) = stack.pop()
+      {
+         if t_entry > t_max
+         {
+            continue;
+         }
+
+         for &(index, ref p) in &node.primitives
          {
-            let mut min_dist = f32::MAX;
-            let mut min_dist_idx = usize::MAX;
-            let last = nodes.pop().unwrap();
-            let last_center = last.bbox.get_center();
-            for i in 0..nodes.len()
+            match p.intersect(ray)
             {
-               //find closest
-               let d = distance(&last_center, &nodes[i].bbox.get_center());
-               if d < min_dist
+               Some(t) if t > 0.0 && t < t_max => {
+                  t_max = t;
+                  closest = Some((index, p));
+               }
+               _ => {}
+            }
+         }
+
+         match (&node.left, &node.right)
+         {
+            (&Some(ref left), &Some(ref right)) => {
+               let (near, far) = if ray.direction[node.axis] >= 0.0
+               {
+                  (left, right)
+               }
+               else
+               {
+                  (right, left)
+               };
+
+               // Pushed last so it is popped first
+               match far.bbox.intersect(ray)
                {
-                 min_dist = d;
-                 min_dist_idx = i;
+                  Some(t) if t <= t_max => stack.push((far, t)),
+                  _ => {}
+               }
+               match near.bbox.intersect(ray)
+               {
+                  Some(t) if t <= t_max => stack.push((near, t)),
+                  _ => {}
                }
             }
+            _ => {}
+         }
+      }
 
-            let closest = nodes.swap_remove(min_dist_idx);
-            let t = Box::new(BVHNode::new(last, closest));
-            merged_nodes.push(t);
+      return closest.map(|(index, p)| (index, p, t_max));
+   }
+
+   // Any-hit query for shadow rays, true as soon as something lies between
+   // the ray origin and max_distance. Shadow rays are spawned off their
+   // surface, any hit in front of the origin counts
+   pub fn occluded(&self, ray: &Ray, max_distance: f32) -> bool
+   {
+      for &(_, ref p) in &self.unbounded
+      {
+         if p.occluded(ray, max_distance)
+         {
+            return true;
+         }
+      }
+
+      let mut stack: Vec<&BVHNode<T>> = Vec::with_capacity(64);
+      match self.root
+      {
+         Some(ref root) => stack.push(root),
+         None => {}
+      }
+
+      while let Some(node) = stack.pop()
+      {
+         match node.bbox.intersect(ray)
+         {
+            Some(t) if t < max_distance => {}
+            _ => continue
          }
 
-         if nodes.len() == 1
+         for &(_, ref p) in &node.primitives
          {
-            merged_nodes.push(nodes.pop().unwrap());
+            if p.occluded(ray, max_distance)
+            {
+               return true;
+            }
          }
 
-         nodes = merged_nodes;
+         match node.left
+         {
+            Some(ref left) => stack.push(left),
+            None => {}
+         }
+         match node.right
+         {
+            Some(ref right) => stack.push(right),
+            None => {}
+         }
       }
 
-      assert!(nodes.len() == 1);
-      let m = BoundingVolumeHierarchy {
-         root: nodes.pop().unwrap()
+      return false;
+   }
+}
+
+#[cfg(test)]
+mod tests
+{
+   use super::*;
+   use tracer::primitives::sphere::Sphere;
+   use tracer::primitives::triangle::Triangle;
+   use tracer::primitives::plane::Plane;
+   use tracer::materials::Lambertian;
+   use tracer::utils::color::Color;
+
+   use nalgebra::Vector3;
+   use rand::{Rng, SeedableRng, XorShiftRng};
+
+   fn material() -> Arc<Material>
+   {
+      return Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+   }
+
+   fn random_point(rng: &mut XorShiftRng, size: f32) -> Point3<f32>
+   {
+      return Point3::new((rng.next_f32() * 2.0 - 1.0) * size,
+                         (rng.next_f32() * 2.0 - 1.0) * size,
+                         (rng.next_f32() * 2.0 - 1.0) * size);
+   }
+
+   // Spheres and triangles of various sizes, overlapping each other
+   fn random_primitives(rng: &mut XorShiftRng, n: usize) -> Vec<Primitive>
+   {
+      let mut primitives: Vec<Primitive> = Vec::with_capacity(n);
+      for i in 0..n
+      {
+         let center = random_point(rng, 10.0);
+         if i % 2 == 0
+         {
+            let radius = 0.1 + 1.5 * rng.next_f32();
+            primitives.push(Primitive::Sphere(Box::new(Sphere::new(radius, center, material()))));
+         }
+         else
+         {
+            let v1 = center + random_point(rng, 2.0).coords;
+            let v2 = center + random_point(rng, 2.0).coords;
+            primitives.push(Primitive::Triangle(Box::new(Triangle::new(center, v1, v2, material()))));
+         }
+      }
+      return primitives;
+   }
+
+   fn random_ray(rng: &mut XorShiftRng) -> Ray
+   {
+      let origin = random_point(rng, 15.0);
+      let mut direction = random_point(rng, 1.0).coords;
+      while direction.norm() < 0.01
+      {
+         direction = random_point(rng, 1.0).coords;
+      }
+      return Ray::new(origin, direction);
+   }
+
+   fn brute_force_closest(primitives: &[Primitive], ray: &Ray) -> Option<(usize, f32)>
+   {
+      let mut closest: Option<(usize, f32)> = None;
+      for (i, p) in primitives.iter().enumerate()
+      {
+         match p.intersect(ray)
+         {
+            Some(t) if t > 0.0 && closest.map_or(true, |(_, c)| t < c) => closest = Some((i, t)),
+            _ => {}
+         }
+      }
+      return closest;
+   }
+
+   fn brute_force_occluded(primitives: &[Primitive], ray: &Ray, max_distance: f32) -> bool
+   {
+      return primitives.iter().any(|p| match p.intersect(ray)
+      {
+         Some(t) => t > 0.0 && t < max_distance,
+         None => false
+      });
+   }
+
+   fn check_against_brute_force(primitives: Vec<Primitive>, options: &BVHBuildOptions,
+                                rng: &mut XorShiftRng)
+   {
+      let bvh = BoundingVolumeHierarchy::new_with_options(primitives.clone(), options);
+      let mut nb_hits = 0;
+      for _ in 0..2000
+      {
+         let ray = random_ray(rng);
+         let expected = brute_force_closest(&primitives, &ray);
+         match (bvh.intersect(&ray), expected)
+         {
+            (Some(h_info), Some((index, t))) => {
+               assert_eq!(h_info.intersection.primitive_index, index);
+               assert!((h_info.intersection.distance - t).abs() <= 1e-3 * t.max(1.0));
+               assert_eq!(bvh.intersect_distance(&ray), Some(t));
+               nb_hits = nb_hits + 1;
+            },
+            (None, None) => assert_eq!(bvh.intersect_distance(&ray), None),
+            (found, _) => panic!("BVH hit: {}, brute force hit: {}", found.is_some(), expected.is_some())
+         }
+
+         for max_distance in &[0.5, 5.0, 20.0, f32::INFINITY]
+         {
+            assert_eq!(bvh.occluded(&ray, *max_distance),
+                       brute_force_occluded(&primitives, &ray, *max_distance));
+         }
+      }
+      // Otherwise the test proves little
+      assert!(nb_hits > 100);
+   }
+
+   fn largest_leaf<T>(node: &BVHNode<T>) -> usize
+   {
+      match (&node.left, &node.right)
+      {
+         (&Some(ref left), &Some(ref right)) => return cmp::max(largest_leaf(left), largest_leaf(right)),
+         _ => return node.primitives.len()
+      }
+   }
+
+   #[test]
+   fn sah_matches_brute_force()
+   {
+      let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+      let primitives = random_primitives(&mut rng, 300);
+      check_against_brute_force(primitives, &BVHBuildOptions::default(), &mut rng);
+   }
+
+   #[test]
+   fn sah_with_few_bins_matches_brute_force()
+   {
+      let mut rng = XorShiftRng::from_seed([5, 6, 7, 8]);
+      let primitives = random_primitives(&mut rng, 100);
+      let options = BVHBuildOptions {
+         nb_bins: 2,
+         max_leaf_size: 1,
+         ..BVHBuildOptions::default()
       };
+      check_against_brute_force(primitives, &options, &mut rng);
+   }
 
-      return m;
+   #[test]
+   fn nearest_neighbour_matches_brute_force()
+   {
+      let mut rng = XorShiftRng::from_seed([9, 10, 11, 12]);
+      let primitives = random_primitives(&mut rng, 100);
+      let options = BVHBuildOptions {
+         method: BVHBuildMethod::NearestNeighbour,
+         ..BVHBuildOptions::default()
+      };
+      check_against_brute_force(primitives, &options, &mut rng);
    }
 
-   pub fn intersect(&self, ray: &Ray) -> Option<HitInfo>
+   #[test]
+   fn unbounded_primitives_match_brute_force()
+   {
+      let mut rng = XorShiftRng::from_seed([13, 14, 15, 16]);
+      let mut primitives = random_primitives(&mut rng, 100);
+      primitives.push(Primitive::Plane(Box::new(Plane::new(Point3::new(0.0, -5.0, 0.0),
+                                                           Vector3::new(0.0, 1.0, 0.0),
+                                                           material()))));
+      let bvh = BoundingVolumeHierarchy::new(primitives.clone());
+      assert!(!bvh.is_bounded());
+      check_against_brute_force(primitives, &BVHBuildOptions::default(), &mut rng);
+   }
+
+   // Concentric spheres share their center, no plane separates them and the
+   // builder splits them in halves
+   #[test]
+   fn identical_centers_are_split_in_halves()
+   {
+      let mut rng = XorShiftRng::from_seed([17, 18, 19, 20]);
+      let center = Point3::new(1.0, 2.0, 3.0);
+      let primitives: Vec<Primitive> = (0..40).map(|i| {
+         Primitive::Sphere(Box::new(Sphere::new(0.5 + i as f32 * 0.25, center, material())))
+      }).collect();
+      let options = BVHBuildOptions::default();
+      let bvh = BoundingVolumeHierarchy::new_with_options(primitives.clone(), &options);
+      assert!(largest_leaf(bvh.root.as_ref().unwrap()) <= options.max_leaf_size);
+
+      // From the center the smallest sphere is hit first, from outside the
+      // largest one
+      let inside = Ray::new(center, Vector3::new(0.0, 1.0, 0.0));
+      assert_eq!(bvh.intersect(&inside).unwrap().intersection.primitive_index, 0);
+      let outside = Ray::new(Point3::new(1.0, 2.0, 100.0), Vector3::new(0.0, 0.0, -1.0));
+      assert_eq!(bvh.intersect(&outside).unwrap().intersection.primitive_index, 39);
+
+      check_against_brute_force(primitives, &options, &mut rng);
+   }
+
+   #[test]
+   fn empty_hierarchy_hits_nothing()
    {
-      return self.root.intersect(ray);
+      let bvh: BoundingVolumeHierarchy = BoundingVolumeHierarchy::new(Vec::new());
+      let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+      assert!(bvh.intersect(&ray).is_none());
+      assert!(!bvh.occluded(&ray, f32::INFINITY));
    }
 }
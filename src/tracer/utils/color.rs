@@ -1,6 +1,8 @@
-
 use image::{Rgba, Pixel};
 
+use std::ops::{Add, AddAssign, Sub, Mul, Div};
+
+#[derive(Clone, Copy)]
 pub struct Color {
     pub red : f32,
     pub green : f32,
@@ -8,21 +10,31 @@ pub struct Color {
 }
 
 const GAMMA: f32 = 2.2;
+// Values above 1 are clamped, casting them to u8 would wrap around
 fn gamma_encode(linear: f32) -> f32 {
-    linear.powf(1.0 / GAMMA)
+    linear.max(0.0).min(1.0).powf(1.0 / GAMMA)
 }
 
 
 impl Color {
     pub fn new(red: f32, green: f32, blue: f32) -> Color {
-        return Color { red: red, green: green, blue: blue }; 
+        return Color { red: red, green: green, blue: blue };
     }
     pub fn new_black() -> Color {
         return Color { red: 0.0, green: 0.0, blue: 0.0 };
     }
 
-    pub fn new_copy(color : &Color) -> Color {
-        return Color { red: color.red, green: color.green, blue: color.blue };
+    pub fn is_black(&self) -> bool {
+        return self.red <= 0.0 && self.green <= 0.0 && self.blue <= 0.0;
+    }
+
+    pub fn max_component(&self) -> f32 {
+        return self.red.max(self.green).max(self.blue);
+    }
+
+    // Perceived brightness (Rec. 709 weights)
+    pub fn luminance(&self) -> f32 {
+        return 0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue;
     }
 
     pub fn to_rgba(&self) -> Rgba<u8> {
@@ -32,3 +44,58 @@ impl Color {
                             255)
     }
 }
+
+impl Add for Color {
+    type Output = Color;
+
+    fn add(self, other: Color) -> Color {
+        return Color::new(self.red + other.red,
+                          self.green + other.green,
+                          self.blue + other.blue);
+    }
+}
+
+impl AddAssign for Color {
+    fn add_assign(&mut self, other: Color) {
+        self.red = self.red + other.red;
+        self.green = self.green + other.green;
+        self.blue = self.blue + other.blue;
+    }
+}
+
+impl Sub for Color {
+    type Output = Color;
+
+    fn sub(self, other: Color) -> Color {
+        return Color::new(self.red - other.red,
+                          self.green - other.green,
+                          self.blue - other.blue);
+    }
+}
+
+// Component-wise, used to filter light through a surface color
+impl Mul for Color {
+    type Output = Color;
+
+    fn mul(self, other: Color) -> Color {
+        return Color::new(self.red * other.red,
+                          self.green * other.green,
+                          self.blue * other.blue);
+    }
+}
+
+impl Mul<f32> for Color {
+    type Output = Color;
+
+    fn mul(self, s: f32) -> Color {
+        return Color::new(self.red * s, self.green * s, self.blue * s);
+    }
+}
+
+impl Div<f32> for Color {
+    type Output = Color;
+
+    fn div(self, s: f32) -> Color {
+        return Color::new(self.red / s, self.green / s, self.blue / s);
+    }
+}
@@ -0,0 +1,266 @@
+use tracer::utils::color::Color;
+use tracer::utils::bounding_volume_hierarchy::HitInfo;
+
+use nalgebra::Vector3;
+
+// Extra images rendered along with the radiance
+#[derive(Clone, Copy, PartialEq)]
+pub enum Layer {
+   // Distance from the camera to the first surface, 0 where rays escape
+   Depth,
+   // Shading normal of the first surface, in world space
+   Normal,
+   // Material::albedo of the first surface
+   Albedo,
+   // Variance of the radiance of the samples of the pixel
+   Variance
+}
+
+impl Layer {
+   pub fn from_name(name: &str) -> Option<Layer> {
+      match name {
+         "depth" => return Some(Layer::Depth),
+         "normal" => return Some(Layer::Normal),
+         "albedo" => return Some(Layer::Albedo),
+         "variance" => return Some(Layer::Variance),
+         _ => return None
+      }
+   }
+
+   pub fn name(&self) -> &'static str {
+      match *self {
+         Layer::Depth => return "depth",
+         Layer::Normal => return "normal",
+         Layer::Albedo => return "albedo",
+         Layer::Variance => return "variance"
+      }
+   }
+
+   // Whether the layer needs the first surface hit by the camera ray
+   pub fn needs_first_hit(&self) -> bool {
+      return *self != Layer::Variance;
+   }
+}
+
+// What one camera sample brings to its pixel
+pub struct FilmSample {
+   pub radiance: Color,
+   // First surface hit, only filled in when a layer needs it
+   pub depth: f32,
+   pub normal: Vector3<f32>,
+   pub albedo: Color
+}
+
+impl FilmSample {
+   pub fn new(radiance: Color) -> FilmSample {
+      return FilmSample {
+         radiance: radiance,
+         depth: 0.0,
+         normal: Vector3::new(0.0, 0.0, 0.0),
+         albedo: Color::new_black()
+      };
+   }
+
+   // Integrators call this with the first surface their camera ray hits
+   pub fn set_first_hit(&mut self, hit_info: &HitInfo) {
+      let inter = &hit_info.intersection;
+      self.depth = inter.distance;
+      self.normal = *inter.shading_normal.as_ref();
+      self.albedo = hit_info.material.albedo(inter);
+   }
+}
+
+// Linear radiance of a rectangle of the image, the whole image or a tile of
+// it. Pixels keep the filter weighted sums of their samples and the sum of
+// the weights so films can be merged, and more samples added, at any time
+pub struct Film {
+   // Position of the first pixel in the image
+   pub x0: u32,
+   pub y0: u32,
+   pub width: u32,
+   pub height: u32,
+   pub layers: Vec<Layer>,
+   radiance: Vec<Color>,
+   weights: Vec<f32>,
+   // One per layer, the variance layer keeps the sum of squared radiances
+   layer_sums: Vec<Vec<Color>>
+}
+
+impl Film {
+   pub fn new(width: u32, height: u32, layers: &[Layer]) -> Film {
+      return Film::new_tile(0, 0, width, height, layers);
+   }
+
+   pub fn new_tile(x0: u32, y0: u32, width: u32, height: u32, layers: &[Layer]) -> Film {
+      let nb_pixels = (width * height) as usize;
+      return Film {
+         x0: x0,
+         y0: y0,
+         width: width,
+         height: height,
+         layers: layers.to_vec(),
+         radiance: vec![Color::new_black(); nb_pixels],
+         weights: vec![0.0; nb_pixels],
+         layer_sums: layers.iter().map(|_| vec![Color::new_black(); nb_pixels]).collect()
+      };
+   }
+
+   // Empty film over a part of this one, with the same layers
+   pub fn tile(&self, x0: u32, y0: u32, width: u32, height: u32) -> Film {
+      return Film::new_tile(x0, y0, width, height, &self.layers);
+   }
+
+   pub fn needs_first_hit(&self) -> bool {
+      return self.layers.iter().any(|l| l.needs_first_hit());
+   }
+
+   // px and py are image coordinates
+   fn index(&self, px: u32, py: u32) -> usize {
+      debug_assert!(px >= self.x0 && px < self.x0 + self.width);
+      debug_assert!(py >= self.y0 && py < self.y0 + self.height);
+      return ((py - self.y0) * self.width + (px - self.x0)) as usize;
+   }
+
+   pub fn add_sample(&mut self, px: u32, py: u32, sample: &FilmSample, weight: f32) {
+      let i = self.index(px, py);
+      self.radiance[i] += sample.radiance * weight;
+      self.weights[i] += weight;
+      for l in 0..self.layers.len() {
+         let value = match self.layers[l] {
+            Layer::Depth => Color::new(sample.depth, sample.depth, sample.depth),
+            Layer::Normal => Color::new(sample.normal.x, sample.normal.y, sample.normal.z),
+            Layer::Albedo => sample.albedo,
+            Layer::Variance => sample.radiance * sample.radiance
+         };
+         self.layer_sums[l][i] += value * weight;
+      }
+   }
+
+   // Adds the samples of a film covering a part of this one
+   pub fn merge_tile(&mut self, tile: &Film) {
+      debug_assert!(tile.layers == self.layers);
+      for y in 0..tile.height {
+         for x in 0..tile.width {
+            let i = self.index(tile.x0 + x, tile.y0 + y);
+            let j = (y * tile.width + x) as usize;
+            self.radiance[i] += tile.radiance[j];
+            self.weights[i] += tile.weights[j];
+            for l in 0..self.layers.len() {
+               self.layer_sums[l][i] += tile.layer_sums[l][j];
+            }
+         }
+      }
+   }
+
+   fn average(&self, sums: &[Color]) -> Vec<Color> {
+      return sums.iter().zip(self.weights.iter()).map(|(sum, weight)| {
+         if *weight == 0.0 { Color::new_black() } else { *sum / *weight }
+      }).collect();
+   }
+
+   // Final radiance of every pixel, row by row
+   pub fn radiance(&self) -> Vec<Color> {
+      return self.average(&self.radiance);
+   }
+
+   // Final values of a layer, None if the film does not have it
+   pub fn layer(&self, layer: Layer) -> Option<Vec<Color>> {
+      let l = match self.layers.iter().position(|other| *other == layer) {
+         Some(l) => l,
+         None => return None
+      };
+      let values = self.average(&self.layer_sums[l]);
+      if layer != Layer::Variance {
+         return Some(values);
+      }
+
+      // E[L²] - E[L]², rounding errors may leave it slightly negative
+      let mean = self.radiance();
+      return Some(values.iter().zip(mean.iter()).map(|(squares, m)| {
+         let variance = *squares - *m * *m;
+         Color::new(variance.red.max(0.0), variance.green.max(0.0), variance.blue.max(0.0))
+      }).collect());
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   fn sample(radiance: f32, depth: f32) -> FilmSample {
+      let mut sample = FilmSample::new(Color::new(radiance, radiance, radiance));
+      sample.depth = depth;
+      sample.normal = Vector3::new(0.0, 0.0, 1.0);
+      sample.albedo = Color::new(0.5, 0.25, 1.0);
+      return sample;
+   }
+
+   fn assert_close(a: &[Color], b: &[Color]) {
+      assert_eq!(a.len(), b.len());
+      for (i, (x, y)) in a.iter().zip(b.iter()).enumerate() {
+         let difference = (*x - *y).max_component().max((*y - *x).max_component());
+         assert!(difference < 1e-5, "pixel {}: ({}, {}, {}) and ({}, {}, {})",
+                 i, x.red, x.green, x.blue, y.red, y.green, y.blue);
+      }
+   }
+
+   #[test]
+   fn pixels_are_weighted_averages() {
+      let mut film = Film::new(2, 1, &[Layer::Depth, Layer::Albedo, Layer::Normal]);
+      film.add_sample(0, 0, &sample(1.0, 2.0), 1.0);
+      film.add_sample(0, 0, &sample(4.0, 8.0), 0.5);
+      // Negative lobes of some filters still average out
+      film.add_sample(0, 0, &sample(10.0, 4.0), -0.25);
+
+      let radiance = film.radiance();
+      let expected = (1.0 + 4.0 * 0.5 - 10.0 * 0.25) / 1.25;
+      assert_close(&radiance, &[Color::new(expected, expected, expected), Color::new_black()]);
+      let depth = (2.0 + 8.0 * 0.5 - 4.0 * 0.25) / 1.25;
+      assert_close(&film.layer(Layer::Depth).unwrap(), &[Color::new(depth, depth, depth), Color::new_black()]);
+      assert_close(&film.layer(Layer::Albedo).unwrap(), &[Color::new(0.5, 0.25, 1.0), Color::new_black()]);
+      assert_close(&film.layer(Layer::Normal).unwrap(), &[Color::new(0.0, 0.0, 1.0), Color::new_black()]);
+      assert!(film.layer(Layer::Variance).is_none());
+   }
+
+   #[test]
+   fn merged_tiles_match_a_single_film() {
+      let layers = [Layer::Depth, Layer::Variance];
+      let mut whole = Film::new(4, 3, &layers);
+      let mut merged = Film::new(4, 3, &layers);
+      // Overlapping tiles, as filters wider than a pixel make them
+      let mut tiles = vec![merged.tile(0, 0, 3, 3), merged.tile(2, 1, 2, 2)];
+      for tile in tiles.iter_mut() {
+         for y in tile.y0..tile.y0 + tile.height {
+            for x in tile.x0..tile.x0 + tile.width {
+               let s = sample((x + 4 * y) as f32, (x * y) as f32);
+               let weight = 0.5 + (x + y) as f32 * 0.1;
+               tile.add_sample(x, y, &s, weight);
+               whole.add_sample(x, y, &s, weight);
+            }
+         }
+      }
+      for tile in &tiles {
+         merged.merge_tile(tile);
+      }
+
+      assert_close(&merged.radiance(), &whole.radiance());
+      for layer in layers.iter() {
+         assert_close(&merged.layer(*layer).unwrap(), &whole.layer(*layer).unwrap());
+      }
+   }
+
+   #[test]
+   fn variance_of_the_samples() {
+      let mut film = Film::new(2, 1, &[Layer::Variance]);
+      film.add_sample(0, 0, &sample(1.0, 0.0), 1.0);
+      film.add_sample(0, 0, &sample(3.0, 0.0), 1.0);
+      // Constant radiance, rounding must not make it negative
+      for _ in 0..3 {
+         film.add_sample(1, 0, &sample(0.1, 0.0), 0.7);
+      }
+
+      let variance = film.layer(Layer::Variance).unwrap();
+      assert_close(&variance, &[Color::new(1.0, 1.0, 1.0), Color::new_black()]);
+      assert!(variance[1].red >= 0.0 && variance[1].green >= 0.0 && variance[1].blue >= 0.0);
+   }
+}
@@ -0,0 +1,356 @@
+use tracer::utils::sampling::Distribution2D;
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+// Cells per side of the table FilterSampler samples from
+const FILTER_TABLE_SIZE: usize = 64;
+
+// Pixel reconstruction filter, the pixel is the average of the samples around
+// its center weighted by evaluate. Weights may be negative
+pub trait Filter: Send + Sync {
+   fn radius(&self) -> f32;
+
+   // x and y are the offsets of the sample from the pixel center, in pixels
+   fn evaluate(&self, x: f32, y: f32) -> f32;
+}
+
+// Filter from its name ("box", "tent", "gaussian", "mitchell" or
+// "lanczos"), None if there is no such filter. Without a radius each filter
+// gets its usual one
+pub fn create_filter(name: &str, radius: Option<f32>) -> Option<Arc<Filter>> {
+   match name {
+      "box" => return Some(Arc::new(BoxFilter::new(radius.unwrap_or(0.5)))),
+      "tent" => return Some(Arc::new(TentFilter::new(radius.unwrap_or(1.0)))),
+      "gaussian" => return Some(Arc::new(GaussianFilter::new(radius.unwrap_or(1.5)))),
+      "mitchell" => return Some(Arc::new(MitchellFilter::new(radius.unwrap_or(2.0)))),
+      "lanczos" => return Some(Arc::new(LanczosFilter::new(radius.unwrap_or(2.0)))),
+      _ => return None
+   }
+}
+
+// Places samples around the pixel center proportionally to the absolute
+// value of the filter, so wide filters and negative lobes do not waste
+// samples where the weights are small
+pub struct FilterSampler {
+   pub filter: Arc<Filter>,
+   distribution: Distribution2D
+}
+
+impl FilterSampler {
+   pub fn new(filter: Arc<Filter>) -> FilterSampler {
+      let radius = filter.radius();
+      let n = FILTER_TABLE_SIZE;
+      let mut weights: Vec<f32> = Vec::with_capacity(n * n);
+      for y in 0..n {
+         for x in 0..n {
+            let dx = ((x as f32 + 0.5) / n as f32 * 2.0 - 1.0) * radius;
+            let dy = ((y as f32 + 0.5) / n as f32 * 2.0 - 1.0) * radius;
+            weights.push(filter.evaluate(dx, dy).abs());
+         }
+      }
+      return FilterSampler {
+         filter: filter,
+         distribution: Distribution2D::new(&weights, n, n)
+      };
+   }
+
+   // Offset from the pixel center and the weight of the sample, the filter
+   // value over the density of the offset
+   pub fn sample(&self, u: f32, v: f32) -> ((f32, f32), f32) {
+      let radius = self.filter.radius();
+      let (st, pdf) = self.distribution.sample(u, v);
+      let (dx, dy) = ((2.0 * st.x - 1.0) * radius, (2.0 * st.y - 1.0) * radius);
+      // The table covers a square of side 2 * radius
+      let pdf = pdf / (4.0 * radius * radius);
+      if pdf <= 0.0 {
+         return ((dx, dy), 0.0);
+      }
+      return ((dx, dy), self.filter.evaluate(dx, dy) / pdf);
+   }
+}
+
+// Every sample counts the same, radius 0.5 covers exactly the pixel
+pub struct BoxFilter {
+   radius: f32
+}
+
+impl BoxFilter {
+   pub fn new(radius: f32) -> BoxFilter {
+      return BoxFilter {
+         radius: radius
+      };
+   }
+}
+
+impl Filter for BoxFilter {
+   fn radius(&self) -> f32 {
+      return self.radius;
+   }
+
+   fn evaluate(&self, x: f32, y: f32) -> f32 {
+      if x.abs() > self.radius || y.abs() > self.radius {
+         return 0.0;
+      }
+      return 1.0;
+   }
+}
+
+// Weights fall linearly to 0 at the radius
+pub struct TentFilter {
+   radius: f32
+}
+
+impl TentFilter {
+   pub fn new(radius: f32) -> TentFilter {
+      return TentFilter {
+         radius: radius
+      };
+   }
+}
+
+impl Filter for TentFilter {
+   fn radius(&self) -> f32 {
+      return self.radius;
+   }
+
+   fn evaluate(&self, x: f32, y: f32) -> f32 {
+      return (self.radius - x.abs()).max(0.0) * (self.radius - y.abs()).max(0.0);
+   }
+}
+
+// Gaussian with a standard deviation of a third of the radius, shifted down
+// so it reaches 0 at the radius
+pub struct GaussianFilter {
+   radius: f32,
+   alpha: f32,
+   edge: f32
+}
+
+impl GaussianFilter {
+   pub fn new(radius: f32) -> GaussianFilter {
+      let sigma = radius / 3.0;
+      let alpha = 1.0 / (2.0 * sigma * sigma);
+      return GaussianFilter {
+         radius: radius,
+         alpha: alpha,
+         edge: (-alpha * radius * radius).exp()
+      };
+   }
+
+   fn gaussian(&self, x: f32) -> f32 {
+      return ((-self.alpha * x * x).exp() - self.edge).max(0.0);
+   }
+}
+
+impl Filter for GaussianFilter {
+   fn radius(&self) -> f32 {
+      return self.radius;
+   }
+
+   fn evaluate(&self, x: f32, y: f32) -> f32 {
+      return self.gaussian(x) * self.gaussian(y);
+   }
+}
+
+// Mitchell-Netravali cubic with B = C = 1/3, the values the authors
+// recommend. Sharper than the Gaussian, with small negative lobes
+pub struct MitchellFilter {
+   radius: f32,
+   b: f32,
+   c: f32
+}
+
+impl MitchellFilter {
+   pub fn new(radius: f32) -> MitchellFilter {
+      return MitchellFilter {
+         radius: radius,
+         b: 1.0 / 3.0,
+         c: 1.0 / 3.0
+      };
+   }
+
+   // x in [-1, 1], the cubic itself is defined on [-2, 2]
+   fn mitchell(&self, x: f32) -> f32 {
+      let x = (2.0 * x).abs();
+      let (b, c) = (self.b, self.c);
+      let value = if x > 2.0 {
+         0.0
+      }
+      else if x > 1.0 {
+         (-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x +
+         (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)
+      }
+      else {
+         (12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)
+      };
+      return value / 6.0;
+   }
+}
+
+impl Filter for MitchellFilter {
+   fn radius(&self) -> f32 {
+      return self.radius;
+   }
+
+   fn evaluate(&self, x: f32, y: f32) -> f32 {
+      return self.mitchell(x / self.radius) * self.mitchell(y / self.radius);
+   }
+}
+
+// Windowed sinc, the window is as wide as the filter so the radius is also
+// the number of lobes kept
+pub struct LanczosFilter {
+   radius: f32
+}
+
+impl LanczosFilter {
+   pub fn new(radius: f32) -> LanczosFilter {
+      return LanczosFilter {
+         radius: radius
+      };
+   }
+
+   fn sinc(x: f32) -> f32 {
+      if x.abs() < 1e-5 {
+         return 1.0;
+      }
+      return (PI * x).sin() / (PI * x);
+   }
+
+   fn lanczos(&self, x: f32) -> f32 {
+      if x.abs() > self.radius {
+         return 0.0;
+      }
+      return LanczosFilter::sinc(x) * LanczosFilter::sinc(x / self.radius);
+   }
+}
+
+impl Filter for LanczosFilter {
+   fn radius(&self) -> f32 {
+      return self.radius;
+   }
+
+   fn evaluate(&self, x: f32, y: f32) -> f32 {
+      return self.lanczos(x) * self.lanczos(y);
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use tracer::utils::sampling::ONE_MINUS_EPSILON;
+
+   // Midpoint rule over the square the filter covers
+   fn integral(filter: &Filter) -> f64 {
+      let n = 500;
+      let radius = filter.radius() as f64;
+      let step = 2.0 * radius / n as f64;
+      let mut sum = 0.0;
+      for y in 0..n {
+         for x in 0..n {
+            let dx = -radius + (x as f64 + 0.5) * step;
+            let dy = -radius + (y as f64 + 0.5) * step;
+            sum += filter.evaluate(dx as f32, dy as f32) as f64;
+         }
+      }
+      return sum * step * step;
+   }
+
+   // Integral of the Gaussian filter in 1D: sigma sqrt(2 pi) erf(3 / sqrt(2))
+   // minus the shift over the width
+   fn gaussian_integral(radius: f64) -> f64 {
+      let sigma = radius / 3.0;
+      let integral = sigma * (2.0 * ::std::f64::consts::PI).sqrt() * 0.9973002039367398 -
+                     2.0 * radius * (-4.5f64).exp();
+      return integral * integral;
+   }
+
+   // Filters with the value they integrate to
+   fn filters() -> Vec<(Arc<Filter>, f64)> {
+      return vec![
+         (create_filter("box", None).unwrap(), 1.0),
+         (create_filter("box", Some(1.5)).unwrap(), 9.0),
+         // r^2 in 1D
+         (create_filter("tent", None).unwrap(), 1.0),
+         (create_filter("tent", Some(2.0)).unwrap(), 16.0),
+         (create_filter("gaussian", None).unwrap(), gaussian_integral(1.5)),
+         (create_filter("gaussian", Some(2.5)).unwrap(), gaussian_integral(2.5)),
+         // The cubic integrates to 1 on [-2, 2], the filter to radius / 2 in 1D
+         (create_filter("mitchell", None).unwrap(), 1.0),
+         (create_filter("mitchell", Some(1.0)).unwrap(), 0.25),
+         // Numerical integration of the 1D sinc product, squared
+         (create_filter("lanczos", None).unwrap(), 1.0196755),
+         (create_filter("lanczos", Some(3.0)).unwrap(), 0.9941194)
+      ];
+   }
+
+   #[test]
+   fn unknown_filter() {
+      assert!(create_filter("sinc", None).is_none());
+   }
+
+   #[test]
+   fn filters_integrate_to_expected_value() {
+      for (filter, expected) in filters() {
+         let value = integral(&*filter);
+         assert!((value - expected).abs() < 1e-3 * expected, "radius {}: {} instead of {}",
+                 filter.radius(), value, expected);
+      }
+   }
+
+   // Stratified (u, v) over the unit square, up to the largest value below 1
+   fn grid(n: usize) -> Vec<(f32, f32)> {
+      let mut points = Vec::with_capacity(n * n);
+      for y in 0..n {
+         for x in 0..n {
+            points.push(((x as f32 + 0.5) / n as f32, (y as f32 + 0.5) / n as f32));
+         }
+      }
+      points.push((0.0, 0.0));
+      points.push((ONE_MINUS_EPSILON, ONE_MINUS_EPSILON));
+      return points;
+   }
+
+   #[test]
+   fn offsets_stay_inside_radius() {
+      for (filter, _) in filters() {
+         let radius = filter.radius();
+         let sampler = FilterSampler::new(filter);
+         for (u, v) in grid(200) {
+            let ((dx, dy), _) = sampler.sample(u, v);
+            assert!(dx.abs() <= radius && dy.abs() <= radius, "({}, {}) outside radius {}", dx, dy, radius);
+         }
+      }
+   }
+
+   #[test]
+   fn weights_are_value_over_density() {
+      for (filter, expected) in filters() {
+         let radius = filter.radius();
+         let sampler = FilterSampler::new(filter.clone());
+         let mut sum = 0.0;
+         // Cells where f changes sign converge slowly
+         let points = grid(512);
+         for &(u, v) in &points {
+            let ((dx, dy), weight) = sampler.sample(u, v);
+            let value = filter.evaluate(dx, dy);
+            // The density follows |f|, the weight keeps the sign of f
+            assert!(weight == 0.0 || weight.signum() == value.signum(),
+                    "radius {}: weight {} for value {}", radius, weight, value);
+            sum += weight as f64;
+         }
+         // f / pdf averages to the integral of f
+         let mean = sum / points.len() as f64;
+         assert!((mean - expected).abs() < 0.01 * expected, "radius {}: mean weight {} instead of {}",
+                 radius, mean, expected);
+      }
+
+      // |f| is constant, so is the density
+      let sampler = FilterSampler::new(create_filter("box", Some(1.5)).unwrap());
+      for (u, v) in grid(50) {
+         let (_, weight) = sampler.sample(u, v);
+         assert!((weight - 9.0).abs() < 1e-3, "{}", weight);
+      }
+   }
+}
@@ -0,0 +1,85 @@
+use tracer::utils::ray::Ray;
+use tracer::utils::sampling::face_forward;
+
+use nalgebra::{Point2, Point3, Vector3};
+use nalgebra::core::Unit;
+
+// Relative distance new rays are pushed off the surface they start from
+const RAY_OFFSET: f32 = 0.0001;
+
+// Surface interaction record, everything known about a ray hitting a primitive
+#[derive(Clone)]
+pub struct Intersection {
+   // Distance along the ray
+   pub distance: f32,
+   pub point: Point3<f32>,
+
+   // Normal of the actual surface and normal used for shading, they only
+   // differ when the primitive has interpolated normals
+   pub normal: Unit<Vector3<f32>>,
+   pub shading_normal: Unit<Vector3<f32>>,
+
+   // Whether the ray hit the side the geometric normal points to, false
+   // when the ray travels inside a closed object
+   pub front_face: bool,
+
+   // Surface parametrization and its partial derivatives
+   pub uv: Point2<f32>,
+   pub dpdu: Vector3<f32>,
+   pub dpdv: Vector3<f32>,
+
+   // Weights of v0, v1 and v2, only meaningful on triangles
+   pub barycentrics: Vector3<f32>,
+
+   // Index of the primitive in the list given to the BVH, filled by the BVH
+   pub primitive_index: usize,
+   // Index of the object holding the primitive, filled by the top level BVH
+   pub object_index: usize
+}
+
+impl Intersection {
+   pub fn new(ray: &Ray,
+              distance: f32,
+              normal: Unit<Vector3<f32>>,
+              uv: Point2<f32>,
+              dpdu: Vector3<f32>,
+              dpdv: Vector3<f32>) -> Intersection {
+      return Intersection {
+         distance: distance,
+         point: ray.origin + distance * ray.direction.as_ref(),
+         normal: normal,
+         shading_normal: normal,
+         front_face: normal.dot(&ray.direction) < 0.0,
+         uv: uv,
+         dpdu: dpdu,
+         dpdv: dpdv,
+         barycentrics: Vector3::new(0.0, 0.0, 0.0),
+         primitive_index: 0,
+         object_index: 0
+      }
+   }
+
+   // Shading normal on the side wo leaves from. The side is decided by the
+   // geometric normal since wo can be under the shading normal
+   pub fn facing_normal(&self, wo: &Vector3<f32>) -> Unit<Vector3<f32>> {
+      return face_forward(&self.shading_normal, &face_forward(&self.normal, wo));
+   }
+
+   // Whether wo and wi are on the same side of the actual surface. A
+   // reflection crossing it would leak light through the geometry, which
+   // interpolated normals make possible
+   pub fn same_side(&self, wo: &Vector3<f32>, wi: &Vector3<f32>) -> bool {
+      return self.normal.dot(wo) * self.normal.dot(wi) > 0.0;
+   }
+
+   // Ray leaving the surface, its origin is pushed to the side it leaves
+   // toward so it does not hit the surface again
+   pub fn spawn_ray(&self, direction: &Vector3<f32>) -> Ray {
+      let scale = 1.0 + self.point.x.abs() + self.point.y.abs() + self.point.z.abs();
+      let mut offset: Vector3<f32> = RAY_OFFSET * scale * self.normal.as_ref();
+      if direction.dot(&self.normal) < 0.0 {
+         offset = -offset;
+      }
+      return Ray::new(self.point + offset, *direction);
+   }
+}
@@ -4,11 +4,24 @@ pub mod intersection;
 pub mod scene;
 pub mod ray;
 pub mod camera;
+pub mod sampling;
+pub mod sampler;
+pub mod filter;
+pub mod film;
 pub mod bounding_volume_hierarchy;
+pub mod transform;
+pub mod top_level_bvh;
 
 pub use tracer::utils::ray::Ray;
 pub use tracer::utils::color::Color;
-pub use tracer::utils::scene::Scene;
+pub use tracer::utils::intersection::Intersection;
+pub use tracer::utils::scene::{Scene, RenderSettings};
 pub use tracer::utils::camera::Camera;
+pub use tracer::utils::sampler::{Sampler, SamplerKind};
+pub use tracer::utils::filter::Filter;
+pub use tracer::utils::film::{Film, Layer};
+pub use tracer::utils::transform::Transform;
 pub use tracer::utils::bounding_volume_hierarchy::BoundingVolumeHierarchy;
 pub use tracer::utils::bounding_volume_hierarchy::HitInfo;
+pub use tracer::utils::bounding_volume_hierarchy::{BVHBuildOptions, BVHBuildMethod};
+pub use tracer::utils::top_level_bvh::TopLevelBVH;
@@ -2,6 +2,7 @@
 use nalgebra::{Point3, Vector3};
 use nalgebra::core::Unit;
 
+#[derive(Clone, Copy)]
 pub struct Ray {
     pub origin: Point3<f32>,
     pub direction: Unit<Vector3<f32>>
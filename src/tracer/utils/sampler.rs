@@ -0,0 +1,495 @@
+use tracer::utils::sampling::ONE_MINUS_EPSILON;
+
+use std::cmp::max;
+
+// Random numbers in [0, 1) for one sample of one pixel. Every sample is a
+// point in as many dimensions as the integrator asks for, next_1d and
+// next_2d hand out its coordinates one after the other. The values only
+// depend on the pixel, the sample index and the dimension, so renders are
+// reproducible whatever the thread that takes the pixel
+pub trait Sampler: Send {
+   // Starts a new sample, dimensions restart from the first one
+   fn start_pixel_sample(&mut self, px: u32, py: u32, sample_index: u32);
+
+   fn next_1d(&mut self) -> f32;
+
+   fn next_2d(&mut self) -> (f32, f32);
+}
+
+#[derive(Clone, Copy)]
+pub enum SamplerKind {
+   // Uncorrelated random numbers
+   Independent,
+   // One sample per stratum, the strata are shuffled for every dimension
+   Stratified,
+   // Halton sequence, randomly shifted for every pixel
+   Halton,
+   // Sobol sequence, Owen scrambled for every pixel. Best with a power of 2
+   // samples per pixel
+   Sobol
+}
+
+pub fn create_sampler(kind: SamplerKind, samples_per_pixel: u32) -> Box<Sampler> {
+   match kind {
+      SamplerKind::Independent => return Box::new(IndependentSampler::new()),
+      SamplerKind::Stratified => return Box::new(StratifiedSampler::new(samples_per_pixel)),
+      SamplerKind::Halton => return Box::new(HaltonSampler::new()),
+      SamplerKind::Sobol => return Box::new(SobolSampler::new())
+   }
+}
+
+// Integer hash with a good avalanche, from Chris Wellons' hash prospector
+fn mix(x: u32) -> u32 {
+   let mut x = x;
+   x ^= x >> 16;
+   x = x.wrapping_mul(0x7feb352d);
+   x ^= x >> 15;
+   x = x.wrapping_mul(0x846ca68b);
+   x ^= x >> 16;
+   return x;
+}
+
+fn hash(values: &[u32]) -> u32 {
+   let mut h: u32 = 0x9e3779b9;
+   for v in values {
+      h = mix(h ^ mix(*v));
+   }
+   return h;
+}
+
+// The 24 high bits fit exactly in a f32
+fn to_unit_float(x: u32) -> f32 {
+   return ((x >> 8) as f32 / (1u32 << 24) as f32).min(ONE_MINUS_EPSILON);
+}
+
+fn reverse_bits(x: u32) -> u32 {
+   let mut x = x;
+   x = (x << 16) | (x >> 16);
+   x = ((x & 0x00ff00ff) << 8) | ((x & 0xff00ff00) >> 8);
+   x = ((x & 0x0f0f0f0f) << 4) | ((x & 0xf0f0f0f0) >> 4);
+   x = ((x & 0x33333333) << 2) | ((x & 0xcccccccc) >> 2);
+   x = ((x & 0x55555555) << 1) | ((x & 0xaaaaaaaa) >> 1);
+   return x;
+}
+
+// Element i of a random permutation of 0..n chosen by seed, from
+// "Correlated Multi-Jittered Sampling" (Kensler)
+fn permutation_element(i: u32, n: u32, seed: u32) -> u32 {
+   let mut w = n - 1;
+   w |= w >> 1;
+   w |= w >> 2;
+   w |= w >> 4;
+   w |= w >> 8;
+   w |= w >> 16;
+   let mut i = i;
+   loop {
+      i ^= seed;
+      i = i.wrapping_mul(0xe170893d);
+      i ^= seed >> 16;
+      i ^= (i & w) >> 4;
+      i ^= seed >> 8;
+      i = i.wrapping_mul(0x0929eb3f);
+      i ^= seed >> 23;
+      i ^= (i & w) >> 1;
+      i = i.wrapping_mul(1 | seed >> 27);
+      i = i.wrapping_mul(0x6935fa69);
+      i ^= (i & w) >> 11;
+      i = i.wrapping_mul(0x74dcb303);
+      i ^= (i & w) >> 2;
+      i = i.wrapping_mul(0x9e501cc3);
+      i ^= (i & w) >> 2;
+      i = i.wrapping_mul(0xc860a3df);
+      i &= w;
+      i ^= i >> 5;
+      // Values past n are permuted again until they land inside
+      if i < n {
+         break;
+      }
+   }
+   return (i.wrapping_add(seed)) % n;
+}
+
+// Position of a sample shared by all the samplers
+struct SampleState {
+   pixel_seed: u32,
+   sample_index: u32,
+   dimension: u32
+}
+
+impl SampleState {
+   fn new() -> SampleState {
+      return SampleState {
+         pixel_seed: 0,
+         sample_index: 0,
+         dimension: 0
+      };
+   }
+
+   fn start(&mut self, px: u32, py: u32, sample_index: u32) {
+      self.pixel_seed = hash(&[px, py]);
+      self.sample_index = sample_index;
+      self.dimension = 0;
+   }
+
+   // Dimension to use next, then skips count dimensions
+   fn take(&mut self, count: u32) -> u32 {
+      let dimension = self.dimension;
+      self.dimension = self.dimension + count;
+      return dimension;
+   }
+
+   // Random value for this pixel, sample and dimension
+   fn random(&self, dimension: u32) -> f32 {
+      return to_unit_float(hash(&[self.pixel_seed, self.sample_index, dimension]));
+   }
+}
+
+pub struct IndependentSampler {
+   state: SampleState
+}
+
+impl IndependentSampler {
+   pub fn new() -> IndependentSampler {
+      return IndependentSampler {
+         state: SampleState::new()
+      };
+   }
+}
+
+impl Sampler for IndependentSampler {
+   fn start_pixel_sample(&mut self, px: u32, py: u32, sample_index: u32) {
+      self.state.start(px, py, sample_index);
+   }
+
+   fn next_1d(&mut self) -> f32 {
+      let d = self.state.take(1);
+      return self.state.random(d);
+   }
+
+   fn next_2d(&mut self) -> (f32, f32) {
+      let d = self.state.take(2);
+      return (self.state.random(d), self.state.random(d + 1));
+   }
+}
+
+// 1D values use samples_per_pixel strata, 2D values a grid of about
+// sqrt(samples_per_pixel) strata per side. The sample index picks the stratum
+// through a permutation that changes with the pixel and the dimension
+pub struct StratifiedSampler {
+   state: SampleState,
+   samples_per_pixel: u32,
+   x_strata: u32,
+   y_strata: u32
+}
+
+impl StratifiedSampler {
+   pub fn new(samples_per_pixel: u32) -> StratifiedSampler {
+      let samples_per_pixel = max(samples_per_pixel, 1);
+      let x_strata = max((samples_per_pixel as f32).sqrt().ceil() as u32, 1);
+      let y_strata = (samples_per_pixel + x_strata - 1) / x_strata;
+      return StratifiedSampler {
+         state: SampleState::new(),
+         samples_per_pixel: samples_per_pixel,
+         x_strata: x_strata,
+         y_strata: y_strata
+      };
+   }
+
+   fn stratum(&self, dimension: u32, nb_strata: u32) -> u32 {
+      let seed = hash(&[self.state.pixel_seed, dimension]);
+      return permutation_element(self.state.sample_index % nb_strata, nb_strata, seed);
+   }
+}
+
+impl Sampler for StratifiedSampler {
+   fn start_pixel_sample(&mut self, px: u32, py: u32, sample_index: u32) {
+      self.state.start(px, py, sample_index);
+   }
+
+   fn next_1d(&mut self) -> f32 {
+      let d = self.state.take(1);
+      let stratum = self.stratum(d, self.samples_per_pixel);
+      let jitter = self.state.random(d);
+      return ((stratum as f32 + jitter) / self.samples_per_pixel as f32).min(ONE_MINUS_EPSILON);
+   }
+
+   fn next_2d(&mut self) -> (f32, f32) {
+      let d = self.state.take(2);
+      let stratum = self.stratum(d, self.x_strata * self.y_strata);
+      let (x, y) = (stratum % self.x_strata, stratum / self.x_strata);
+      return (((x as f32 + self.state.random(d)) / self.x_strata as f32).min(ONE_MINUS_EPSILON),
+              ((y as f32 + self.state.random(d + 1)) / self.y_strata as f32).min(ONE_MINUS_EPSILON));
+   }
+}
+
+// Dimensions past the last prime base are independent random numbers
+const NB_HALTON_DIMENSIONS: usize = 64;
+
+// Radical inverse of the sample index in a prime base per dimension, shifted
+// by a random offset per pixel and dimension (Cranley-Patterson rotation)
+pub struct HaltonSampler {
+   state: SampleState,
+   primes: Vec<u32>
+}
+
+impl HaltonSampler {
+   pub fn new() -> HaltonSampler {
+      let mut primes: Vec<u32> = Vec::with_capacity(NB_HALTON_DIMENSIONS);
+      let mut candidate = 2;
+      while primes.len() < NB_HALTON_DIMENSIONS {
+         if primes.iter().all(|p| candidate % p != 0) {
+            primes.push(candidate);
+         }
+         candidate = candidate + 1;
+      }
+      return HaltonSampler {
+         state: SampleState::new(),
+         primes: primes
+      };
+   }
+
+   fn radical_inverse(base: u32, index: u32) -> f32 {
+      let inverse_base = 1.0 / base as f64;
+      let mut index = index;
+      let mut reversed: u64 = 0;
+      let mut inverse_base_n = 1.0;
+      while index > 0 {
+         reversed = reversed * base as u64 + (index % base) as u64;
+         inverse_base_n *= inverse_base;
+         index /= base;
+      }
+      return ((reversed as f64 * inverse_base_n) as f32).min(ONE_MINUS_EPSILON);
+   }
+
+   fn value(&self, dimension: u32) -> f32 {
+      if dimension as usize >= self.primes.len() {
+         return self.state.random(dimension);
+      }
+      // The offset does not depend on the sample index
+      let offset = to_unit_float(hash(&[self.state.pixel_seed, dimension]));
+      let shifted = HaltonSampler::radical_inverse(self.primes[dimension as usize], self.state.sample_index) +
+                    offset;
+      return (shifted - shifted.floor()).min(ONE_MINUS_EPSILON);
+   }
+}
+
+impl Sampler for HaltonSampler {
+   fn start_pixel_sample(&mut self, px: u32, py: u32, sample_index: u32) {
+      self.state.start(px, py, sample_index);
+   }
+
+   fn next_1d(&mut self) -> f32 {
+      let d = self.state.take(1);
+      return self.value(d);
+   }
+
+   fn next_2d(&mut self) -> (f32, f32) {
+      let d = self.state.take(2);
+      return (self.value(d), self.value(d + 1));
+   }
+}
+
+// First two dimensions of the Sobol sequence, padded: every call uses its own
+// pair with a shuffled sample index and its own Owen scrambling. From
+// "Practical Hash-based Owen Scrambling" (Burley)
+pub struct SobolSampler {
+   state: SampleState
+}
+
+impl SobolSampler {
+   pub fn new() -> SobolSampler {
+      return SobolSampler {
+         state: SampleState::new()
+      };
+   }
+
+   // Owen scrambling of the bits of x, the highest bit first
+   fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+      let mut x = reverse_bits(x);
+      x = x.wrapping_add(seed);
+      x ^= x.wrapping_mul(0x6c50b47c);
+      x ^= x.wrapping_mul(0xb82f1e52);
+      x ^= x.wrapping_mul(0xc7afe638);
+      x ^= x.wrapping_mul(0x8d22f6e6);
+      return reverse_bits(x);
+   }
+
+   fn sobol_2d(index: u32) -> (u32, u32) {
+      // The first dimension is the van der Corput sequence, the direction
+      // numbers of the second one follow v[i] = v[i - 1] ^ (v[i - 1] >> 1)
+      let mut y: u32 = 0;
+      let mut v: u32 = 1 << 31;
+      let mut index_bits = index;
+      while index_bits != 0 {
+         if index_bits & 1 != 0 {
+            y ^= v;
+         }
+         index_bits >>= 1;
+         v ^= v >> 1;
+      }
+      return (reverse_bits(index), y);
+   }
+
+   fn pair(&self, dimension: u32) -> (f32, f32) {
+      let seed = hash(&[self.state.pixel_seed, dimension]);
+      let index = SobolSampler::nested_uniform_scramble(self.state.sample_index, seed);
+      let (x, y) = SobolSampler::sobol_2d(index);
+      return (to_unit_float(SobolSampler::nested_uniform_scramble(x, mix(seed ^ 1))),
+              to_unit_float(SobolSampler::nested_uniform_scramble(y, mix(seed ^ 2))));
+   }
+}
+
+impl Sampler for SobolSampler {
+   fn start_pixel_sample(&mut self, px: u32, py: u32, sample_index: u32) {
+      self.state.start(px, py, sample_index);
+   }
+
+   fn next_1d(&mut self) -> f32 {
+      let d = self.state.take(1);
+      return self.pair(d).0;
+   }
+
+   fn next_2d(&mut self) -> (f32, f32) {
+      let d = self.state.take(2);
+      return self.pair(d);
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   const KINDS: [SamplerKind; 4] = [SamplerKind::Independent, SamplerKind::Stratified,
+                                    SamplerKind::Halton, SamplerKind::Sobol];
+
+   #[test]
+   fn permutation_is_a_bijection() {
+      for &n in &[1, 2, 7, 16, 100, 1000] {
+         for seed in 0..20 {
+            let seed = mix(seed);
+            let mut seen = vec![false; n as usize];
+            for i in 0..n {
+               let p = permutation_element(i, n, seed);
+               assert!(p < n);
+               assert!(!seen[p as usize], "{} appears twice for n = {}", p, n);
+               seen[p as usize] = true;
+            }
+         }
+      }
+   }
+
+   #[test]
+   fn radical_inverse_known_values() {
+      let cases = [(2, 0, 0.0), (2, 1, 0.5), (2, 2, 0.25), (2, 3, 0.75), (2, 6, 0.375),
+                   (3, 1, 1.0 / 3.0), (3, 2, 2.0 / 3.0), (3, 3, 1.0 / 9.0), (3, 5, 7.0 / 9.0),
+                   (5, 7, 0.44)];
+      for &(base, index, expected) in &cases {
+         let value = HaltonSampler::radical_inverse(base, index);
+         assert!((value - expected).abs() < 1e-6, "base {} index {}: {}", base, index, value);
+      }
+      assert!(HaltonSampler::radical_inverse(2, u32::max_value()) < 1.0);
+   }
+
+   #[test]
+   fn sobol_first_points() {
+      let expected = [(0.0, 0.0), (0.5, 0.5), (0.25, 0.75), (0.75, 0.25),
+                      (0.125, 0.625), (0.625, 0.125), (0.375, 0.375), (0.875, 0.875)];
+      for (i, &(x, y)) in expected.iter().enumerate() {
+         let (sx, sy) = SobolSampler::sobol_2d(i as u32);
+         assert_eq!((sx as f64 / 4294967296.0, sy as f64 / 4294967296.0), (x, y), "point {}", i);
+      }
+   }
+
+   #[test]
+   fn values_stay_in_unit_interval() {
+      for &kind in &KINDS {
+         for &spp in &[1, 10, 16] {
+            let mut sampler = create_sampler(kind, spp);
+            for p in 0..16 {
+               for i in 0..spp {
+                  sampler.start_pixel_sample(p % 4, p / 4, i);
+                  for _ in 0..100 {
+                     let u = sampler.next_1d();
+                     let (v, w) = sampler.next_2d();
+                     for x in &[u, v, w] {
+                        assert!(*x >= 0.0 && *x < 1.0, "{}", x);
+                     }
+                  }
+               }
+            }
+         }
+      }
+   }
+
+   #[test]
+   fn samples_are_reproducible() {
+      for &kind in &KINDS {
+         let mut a = create_sampler(kind, 16);
+         let mut b = create_sampler(kind, 16);
+         // Another sample in between must not change anything
+         b.start_pixel_sample(3, 4, 9);
+         b.next_2d();
+         a.start_pixel_sample(1, 2, 5);
+         b.start_pixel_sample(1, 2, 5);
+         for _ in 0..10 {
+            assert_eq!(a.next_1d(), b.next_1d());
+            assert_eq!(a.next_2d(), b.next_2d());
+         }
+      }
+   }
+
+   // Stratum of every value of dimension d for the samples of one pixel
+   fn strata_1d(sampler: &mut Sampler, spp: u32, skip: u32) -> Vec<u32> {
+      return (0..spp).map(|i| {
+         sampler.start_pixel_sample(5, 7, i);
+         for _ in 0..skip {
+            sampler.next_1d();
+         }
+         (sampler.next_1d() * spp as f32) as u32
+      }).collect();
+   }
+
+   #[test]
+   fn stratified_covers_every_stratum_once() {
+      for &spp in &[7, 10, 16] {
+         let mut sampler = create_sampler(SamplerKind::Stratified, spp);
+         for skip in 0..4 {
+            let mut strata = strata_1d(&mut *sampler, spp, skip);
+            strata.sort();
+            assert_eq!(strata, (0..spp).collect::<Vec<u32>>(), "spp {} dimension {}", spp, skip);
+         }
+      }
+
+      // A 4x4 grid for 16 samples
+      let mut sampler = create_sampler(SamplerKind::Stratified, 16);
+      let mut strata: Vec<u32> = (0..16).map(|i| {
+         sampler.start_pixel_sample(5, 7, i);
+         sampler.next_1d();
+         let (u, v) = sampler.next_2d();
+         (v * 4.0) as u32 * 4 + (u * 4.0) as u32
+      }).collect();
+      strata.sort();
+      assert_eq!(strata, (0..16).collect::<Vec<u32>>());
+
+      // 10 samples on a 4x3 grid, no stratum gets two
+      let mut sampler = create_sampler(SamplerKind::Stratified, 10);
+      let mut strata: Vec<u32> = (0..10).map(|i| {
+         sampler.start_pixel_sample(5, 7, i);
+         let (u, v) = sampler.next_2d();
+         (v * 3.0) as u32 * 4 + (u * 4.0) as u32
+      }).collect();
+      strata.sort();
+      strata.dedup();
+      assert_eq!(strata.len(), 10);
+   }
+
+   #[test]
+   fn sobol_is_stratified_for_powers_of_two() {
+      let mut sampler = create_sampler(SamplerKind::Sobol, 16);
+      for skip in 0..4 {
+         let mut strata = strata_1d(&mut *sampler, 16, skip);
+         strata.sort();
+         assert_eq!(strata, (0..16).collect::<Vec<u32>>(), "dimension {}", skip);
+      }
+   }
+}
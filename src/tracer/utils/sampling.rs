@@ -0,0 +1,180 @@
+use nalgebra::{Point2, Vector3};
+use nalgebra::core::Unit;
+
+use std::cmp::min;
+use std::f32::consts::PI;
+
+// Two unit vectors that form an orthonormal basis with n
+pub fn coordinate_system(n: &Unit<Vector3<f32>>) -> (Vector3<f32>, Vector3<f32>) {
+   let t: Vector3<f32> = if n.x.abs() > n.y.abs() {
+      Vector3::new(-n.z, 0.0, n.x).normalize()
+   }
+   else {
+      Vector3::new(0.0, n.z, -n.y).normalize()
+   };
+   let b: Vector3<f32> = n.cross(&t);
+   return (t, b);
+}
+
+// Expresses a direction given around the z axis around n instead
+pub fn local_to_world(local: &Vector3<f32>, n: &Unit<Vector3<f32>>) -> Unit<Vector3<f32>> {
+   let (t, b) = coordinate_system(n);
+   return Unit::new_normalize(local.x * t + local.y * b + local.z * n.as_ref());
+}
+
+// Flips n so it lies in the same hemisphere as v
+pub fn face_forward(n: &Unit<Vector3<f32>>, v: &Vector3<f32>) -> Unit<Vector3<f32>> {
+   if n.dot(v) < 0.0 {
+      return -*n;
+   }
+   return *n;
+}
+
+pub fn reflect(v: &Vector3<f32>, n: &Unit<Vector3<f32>>) -> Vector3<f32> {
+   return 2.0 * v.dot(n) * n.as_ref() - v;
+}
+
+// Malley's method, the pdf is cos(theta) / PI
+pub fn cosine_sample_hemisphere(u: f32, v: f32) -> Vector3<f32> {
+   let r = u.sqrt();
+   let phi = 2.0 * PI * v;
+   let x = r * phi.cos();
+   let y = r * phi.sin();
+   return Vector3::new(x, y, (1.0 - x * x - y * y).max(0.0).sqrt());
+}
+
+// Largest f32 below 1, sample values are kept in [0, 1)
+pub const ONE_MINUS_EPSILON: f32 = 0.99999994;
+
+// Discrete distribution proportional to non negative weights, uniform if they
+// are all 0
+pub struct Distribution1D {
+   // cdf[i] is the probability of picking an index below i, cdf[n] is 1
+   cdf: Vec<f32>
+}
+
+impl Distribution1D {
+   pub fn new(weights: &[f32]) -> Distribution1D {
+      let n = weights.len();
+      let total: f32 = weights.iter().map(|w| w.max(0.0)).sum();
+      let mut cdf: Vec<f32> = Vec::with_capacity(n + 1);
+      cdf.push(0.0);
+      for i in 0..n {
+         let w = if total > 0.0 { weights[i].max(0.0) / total } else { 1.0 / n as f32 };
+         let previous = cdf[i];
+         cdf.push(previous + w);
+      }
+      // Rounding errors must not leave u values without an index
+      if n > 0 {
+         cdf[n] = 1.0;
+      }
+      return Distribution1D {
+         cdf: cdf
+      };
+   }
+
+   pub fn len(&self) -> usize {
+      return self.cdf.len() - 1;
+   }
+
+   pub fn pmf(&self, index: usize) -> f32 {
+      return self.cdf[index + 1] - self.cdf[index];
+   }
+
+   // Index picked with probability pmf(index), along with that probability
+   // and u rescaled to [0, 1) inside its bucket so it can be used again.
+   // Without any weight the probability is 0 and the index is meaningless
+   pub fn sample_discrete(&self, u: f32) -> (usize, f32, f32) {
+      if self.len() == 0 {
+         return (0, 0.0, u);
+      }
+      // First bucket ending after u, empty buckets are never picked
+      let mut lo = 0;
+      let mut hi = self.len() - 1;
+      while lo < hi {
+         let mid = (lo + hi) / 2;
+         if self.cdf[mid + 1] <= u {
+            lo = mid + 1;
+         }
+         else {
+            hi = mid;
+         }
+      }
+
+      let pmf = self.pmf(lo);
+      let remapped = ((u - self.cdf[lo]) / pmf).max(0.0).min(ONE_MINUS_EPSILON);
+      return (lo, pmf, remapped);
+   }
+}
+
+// Distribution over [0, 1)² proportional to a grid of weights, rows along v.
+// A row is picked first (marginal) then a column inside it (conditional)
+pub struct Distribution2D {
+   conditionals: Vec<Distribution1D>,
+   marginal: Distribution1D,
+   width: usize,
+   height: usize
+}
+
+impl Distribution2D {
+   // weights[y * width + x]
+   pub fn new(weights: &[f32], width: usize, height: usize) -> Distribution2D {
+      let mut conditionals = Vec::with_capacity(height);
+      let mut row_weights = Vec::with_capacity(height);
+      for y in 0..height {
+         let row = &weights[y * width..(y + 1) * width];
+         conditionals.push(Distribution1D::new(row));
+         row_weights.push(row.iter().map(|w| w.max(0.0)).sum());
+      }
+      return Distribution2D {
+         conditionals: conditionals,
+         marginal: Distribution1D::new(&row_weights),
+         width: width,
+         height: height
+      };
+   }
+
+   // Point in [0, 1)² and its density with respect to area
+   pub fn sample(&self, u: f32, v: f32) -> (Point2<f32>, f32) {
+      let (y, pmf_y, v) = self.marginal.sample_discrete(v);
+      let (x, pmf_x, u) = self.conditionals[y].sample_discrete(u);
+      let point = Point2::new((x as f32 + u) / self.width as f32, (y as f32 + v) / self.height as f32);
+      return (point, pmf_x * pmf_y * (self.width * self.height) as f32);
+   }
+
+   pub fn pdf(&self, point: &Point2<f32>) -> f32 {
+      let x = min((point.x * self.width as f32) as usize, self.width - 1);
+      let y = min((point.y * self.height as f32) as usize, self.height - 1);
+      return self.marginal.pmf(y) * self.conditionals[y].pmf(x) * (self.width * self.height) as f32;
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn discrete_sampling_follows_weights() {
+      let distribution = Distribution1D::new(&[1.0, 0.0, 3.0]);
+      assert_eq!(distribution.pmf(1), 0.0);
+      assert_eq!(distribution.sample_discrete(0.1), (0, 0.25, 0.4));
+      assert_eq!(distribution.sample_discrete(0.25).0, 2);
+      let (index, pmf, u) = distribution.sample_discrete(ONE_MINUS_EPSILON);
+      assert_eq!((index, pmf), (2, 0.75));
+      assert!(u < 1.0);
+   }
+
+   #[test]
+   fn zero_weights_are_uniform() {
+      let distribution = Distribution1D::new(&[0.0, 0.0]);
+      assert_eq!(distribution.sample_discrete(0.75), (1, 0.5, 0.5));
+   }
+
+   #[test]
+   fn empty_distribution_has_no_index() {
+      let distribution = Distribution1D::new(&[]);
+      assert_eq!(distribution.len(), 0);
+      let (_, pmf, _) = distribution.sample_discrete(0.5);
+      assert_eq!(pmf, 0.0);
+   }
+}
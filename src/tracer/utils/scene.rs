@@ -1,12 +1,26 @@
 
-use tracer::primitives::light::Light;
+use tracer::lights::LightSet;
 use tracer::utils::camera::Camera;
-use tracer::utils::bounding_volume_hierarchy::BoundingVolumeHierarchy;
+use tracer::utils::top_level_bvh::TopLevelBVH;
+use tracer::utils::sampler::SamplerKind;
+use tracer::utils::filter::Filter;
+use tracer::utils::film::Layer;
+
+use std::sync::Arc;
 
 pub struct Scene {
     pub width: u32,
     pub height: u32,
-    pub light: Light,
+    pub lights: LightSet,
     pub camera: Camera,
-    pub bvh: BoundingVolumeHierarchy
-}
\ No newline at end of file
+    pub bvh: TopLevelBVH
+}
+
+// How the pixels of the scene are sampled and what is kept of them
+pub struct RenderSettings {
+    pub sampler: SamplerKind,
+    pub samples_per_pixel: u32,
+    pub filter: Arc<Filter>,
+    // Rendered along with the radiance
+    pub layers: Vec<Layer>
+}
@@ -0,0 +1,139 @@
+use tracer::primitives::{Primitive, BoundingBox};
+use tracer::primitives::instance::Instance;
+use tracer::utils::ray::Ray;
+use tracer::utils::transform::Transform;
+use tracer::utils::bounding_volume_hierarchy::{BoundingVolumeHierarchy, HitInfo, BVHBuildOptions};
+
+use nalgebra::distance;
+
+use std::sync::Arc;
+
+// Two level acceleration structure. Every object of the scene has its own
+// BVH (bottom level) placed by a transform, and a BVH over the objects (top
+// level) finds which ones a ray may hit. Moving an object only rebuilds the
+// top level, changing its geometry only rebuilds its own BVH.
+//
+// Changes are applied by update(), the structure must not be traversed
+// before it is called
+pub struct TopLevelBVH
+{
+   // Shared with the top level, which is rebuilt without copying them
+   objects: Vec<Arc<Instance>>,
+   options: BVHBuildOptions,
+   top: BoundingVolumeHierarchy<Arc<Instance>>,
+   dirty: bool
+}
+
+impl TopLevelBVH
+{
+   pub fn new(options: &BVHBuildOptions) -> TopLevelBVH
+   {
+      return TopLevelBVH {
+         objects: Vec::new(),
+         options: options.clone(),
+         top: BoundingVolumeHierarchy::new(Vec::new()),
+         dirty: false
+      };
+   }
+
+   pub fn nb_objects(&self) -> usize
+   {
+      return self.objects.len();
+   }
+
+   // None if there is no such object
+   pub fn get_object(&self, index: usize) -> Option<&Instance>
+   {
+      return self.objects.get(index).map(|object| &**object);
+   }
+
+   fn check_index(&self, index: usize) -> Result<(), String>
+   {
+      if index >= self.objects.len()
+      {
+         return Err(format!("no object {}, the top level BVH has {}", index, self.objects.len()));
+      }
+      return Ok(());
+   }
+
+   // Builds the BVH of the primitives, returns the index of the object
+   pub fn add_object(&mut self, primitives: Vec<Primitive>, transform: Transform) -> usize
+   {
+      let bvh = Arc::new(BoundingVolumeHierarchy::new_with_options(primitives, &self.options));
+      return self.add_instance(bvh, transform);
+   }
+
+   // New object sharing an existing BVH
+   pub fn add_instance(&mut self, bvh: Arc<BoundingVolumeHierarchy>, transform: Transform) -> usize
+   {
+      self.objects.push(Arc::new(Instance::new(bvh, transform)));
+      self.dirty = true;
+      return self.objects.len() - 1;
+   }
+
+   pub fn set_transform(&mut self, index: usize, transform: Transform) -> Result<(), String>
+   {
+      try!(self.check_index(index));
+      let bvh = self.objects[index].bvh.clone();
+      self.objects[index] = Arc::new(Instance::new(bvh, transform));
+      self.dirty = true;
+      return Ok(());
+   }
+
+   // Only the BVH of this object is rebuilt, the other instances of its
+   // former BVH are left untouched
+   pub fn set_primitives(&mut self, index: usize, primitives: Vec<Primitive>) -> Result<(), String>
+   {
+      try!(self.check_index(index));
+      let bvh = Arc::new(BoundingVolumeHierarchy::new_with_options(primitives, &self.options));
+      let transform = self.objects[index].transform;
+      self.objects[index] = Arc::new(Instance::new(bvh, transform));
+      self.dirty = true;
+      return Ok(());
+   }
+
+   // Box around the bounded objects
+   pub fn get_bounding_box(&self) -> BoundingBox
+   {
+      debug_assert!(!self.dirty);
+      return self.top.get_bounding_box();
+   }
+
+   // Radius of a sphere around the bounded objects
+   pub fn get_radius(&self) -> f32
+   {
+      let bbox = self.get_bounding_box();
+      if bbox.min.x > bbox.max.x
+      {
+         return 0.0;
+      }
+      return 0.5 * distance(&bbox.min, &bbox.max);
+   }
+
+   // Rebuilds the top level if objects were added, moved or changed
+   pub fn update(&mut self)
+   {
+      if !self.dirty
+      {
+         return;
+      }
+
+      // Only the references to the objects are copied
+      self.top = BoundingVolumeHierarchy::new_with_options(self.objects.clone(), &self.options);
+      self.dirty = false;
+   }
+
+   // object_index of the hit is the index of the object, primitive_index
+   // the index of the primitive in the object
+   pub fn intersect(&self, ray: &Ray) -> Option<HitInfo>
+   {
+      debug_assert!(!self.dirty);
+      return self.top.intersect(ray);
+   }
+
+   pub fn occluded(&self, ray: &Ray, max_distance: f32) -> bool
+   {
+      debug_assert!(!self.dirty);
+      return self.top.occluded(ray, max_distance);
+   }
+}
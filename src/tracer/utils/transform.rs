@@ -0,0 +1,284 @@
+use tracer::primitives::BoundingBox;
+use tracer::utils::ray::Ray;
+
+use nalgebra::{Matrix4, Point3, Vector3, Rotation3};
+use nalgebra::core::Unit;
+
+use std::ops::Mul;
+
+// Affine transform, the inverse is kept along the matrix since rays are
+// transformed by it and normals by its transpose
+#[derive(Clone, Copy)]
+pub struct Transform
+{
+   pub matrix: Matrix4<f32>,
+   pub inverse: Matrix4<f32>
+}
+
+impl Transform
+{
+   // None when the matrix cannot be inverted
+   pub fn new(matrix: Matrix4<f32>) -> Option<Transform>
+   {
+      return matrix.try_inverse().map(|inverse| Transform {
+         matrix: matrix,
+         inverse: inverse
+      });
+   }
+
+   pub fn identity() -> Transform
+   {
+      return Transform {
+         matrix: Matrix4::identity(),
+         inverse: Matrix4::identity()
+      };
+   }
+
+   pub fn translation(t: &Vector3<f32>) -> Transform
+   {
+      return Transform {
+         matrix: Matrix4::new_translation(t),
+         inverse: Matrix4::new_translation(&-t)
+      };
+   }
+
+   // Every component must be non zero
+   pub fn scaling(s: &Vector3<f32>) -> Transform
+   {
+      return Transform {
+         matrix: Matrix4::new_nonuniform_scaling(s),
+         inverse: Matrix4::new_nonuniform_scaling(&Vector3::new(1.0 / s.x, 1.0 / s.y, 1.0 / s.z))
+      };
+   }
+
+   // Angles in radians around x, y then z
+   pub fn rotation(angles: &Vector3<f32>) -> Transform
+   {
+      let rotation = Rotation3::from_euler_angles(angles.x, angles.y, angles.z);
+      return Transform {
+         matrix: rotation.to_homogeneous(),
+         inverse: rotation.transpose().to_homogeneous()
+      };
+   }
+
+   pub fn inverse(&self) -> Transform
+   {
+      return Transform {
+         matrix: self.inverse,
+         inverse: self.matrix
+      };
+   }
+
+   pub fn transform_point(&self, p: &Point3<f32>) -> Point3<f32>
+   {
+      let m = &self.matrix;
+      let x = m[(0, 0)] * p.x + m[(0, 1)] * p.y + m[(0, 2)] * p.z + m[(0, 3)];
+      let y = m[(1, 0)] * p.x + m[(1, 1)] * p.y + m[(1, 2)] * p.z + m[(1, 3)];
+      let z = m[(2, 0)] * p.x + m[(2, 1)] * p.y + m[(2, 2)] * p.z + m[(2, 3)];
+      return Point3::new(x, y, z);
+   }
+
+   // Ignores the translation
+   pub fn transform_vector(&self, v: &Vector3<f32>) -> Vector3<f32>
+   {
+      let m = &self.matrix;
+      return Vector3::new(m[(0, 0)] * v.x + m[(0, 1)] * v.y + m[(0, 2)] * v.z,
+                          m[(1, 0)] * v.x + m[(1, 1)] * v.y + m[(1, 2)] * v.z,
+                          m[(2, 0)] * v.x + m[(2, 1)] * v.y + m[(2, 2)] * v.z);
+   }
+
+   // Normals go through the inverse transpose to stay perpendicular to the
+   // surface under non uniform scaling
+   pub fn transform_normal(&self, n: &Vector3<f32>) -> Unit<Vector3<f32>>
+   {
+      let m = &self.inverse;
+      return Unit::new_normalize(Vector3::new(m[(0, 0)] * n.x + m[(1, 0)] * n.y + m[(2, 0)] * n.z,
+                                              m[(0, 1)] * n.x + m[(1, 1)] * n.y + m[(2, 1)] * n.z,
+                                              m[(0, 2)] * n.x + m[(1, 2)] * n.y + m[(2, 2)] * n.z));
+   }
+
+   // The direction is normalized again, distances along the transformed
+   // ray are the original ones times the returned factor
+   pub fn transform_ray(&self, ray: &Ray) -> (Ray, f32)
+   {
+      let direction = self.transform_vector(&ray.direction);
+      let factor = direction.norm();
+      return (Ray::new(self.transform_point(&ray.origin), direction), factor);
+   }
+
+   // Box around the 8 transformed corners
+   pub fn transform_bounding_box(&self, bbox: &BoundingBox) -> BoundingBox
+   {
+      let mut result = BoundingBox::new_empty();
+      for i in 0..8
+      {
+         let corner = Point3::new(if i & 1 == 0 { bbox.min.x } else { bbox.max.x },
+                                  if i & 2 == 0 { bbox.min.y } else { bbox.max.y },
+                                  if i & 4 == 0 { bbox.min.z } else { bbox.max.z });
+         let p = self.transform_point(&corner);
+         result = BoundingBox::new_from(&result, &BoundingBox::new_from_point(&p));
+      }
+      return result;
+   }
+}
+
+// a * b applies b first
+impl Mul for Transform
+{
+   type Output = Transform;
+
+   fn mul(self, other: Transform) -> Transform
+   {
+      return Transform {
+         matrix: self.matrix * other.matrix,
+         inverse: other.inverse * self.inverse
+      };
+   }
+}
+
+#[cfg(test)]
+mod tests
+{
+   use super::*;
+
+   fn assert_identity(m: &Matrix4<f32>)
+   {
+      for i in 0..4
+      {
+         for j in 0..4
+         {
+            let expected = if i == j { 1.0 } else { 0.0 };
+            assert!((m[(i, j)] - expected).abs() < 1e-5, "{:?}", m);
+         }
+      }
+   }
+
+   // Non uniform scale, rotation and translation together, like an instance
+   fn placement() -> Transform
+   {
+      return Transform::translation(&Vector3::new(1.0, -2.0, 3.0)) *
+             Transform::rotation(&Vector3::new(0.3, -1.1, 2.0)) *
+             Transform::scaling(&Vector3::new(2.0, 0.5, 3.0));
+   }
+
+   fn transforms() -> Vec<Transform>
+   {
+      let general = Matrix4::new(2.0, 0.5, 0.0, 1.0,
+                                 0.0, 1.0, -1.0, 2.0,
+                                 1.0, 0.0, 3.0, -1.0,
+                                 0.0, 0.0, 0.0, 1.0);
+      return vec![Transform::identity(),
+                  Transform::translation(&Vector3::new(1.0, 2.0, 3.0)),
+                  Transform::scaling(&Vector3::new(2.0, -0.5, 4.0)),
+                  Transform::rotation(&Vector3::new(0.5, 1.0, -0.25)),
+                  placement(),
+                  placement() * placement(),
+                  Transform::new(general).unwrap()];
+   }
+
+   #[test]
+   fn inverse_times_matrix_is_identity()
+   {
+      for t in transforms()
+      {
+         assert_identity(&(t.inverse * t.matrix));
+         assert_identity(&(t.matrix * t.inverse));
+         let inverse = t.inverse();
+         assert_identity(&(inverse.matrix * t.matrix));
+      }
+   }
+
+   #[test]
+   fn singular_matrix_has_no_transform()
+   {
+      let mut m = Matrix4::identity();
+      m[(1, 1)] = 0.0;
+      assert!(Transform::new(m).is_none());
+   }
+
+   #[test]
+   fn composition_applies_right_first()
+   {
+      let t = Transform::translation(&Vector3::new(1.0, 0.0, 0.0)) *
+              Transform::scaling(&Vector3::new(2.0, 2.0, 2.0));
+      let p = t.transform_point(&Point3::new(1.0, 1.0, 1.0));
+      assert!((p - Point3::new(3.0, 2.0, 2.0)).norm() < 1e-6);
+      let back = t.inverse().transform_point(&p);
+      assert!((back - Point3::new(1.0, 1.0, 1.0)).norm() < 1e-6);
+   }
+
+   #[test]
+   fn normals_stay_perpendicular()
+   {
+      // Plane through the origin with normal (1, 1, 0), stretched along x:
+      // the tangent (1, -1, 0) becomes (2, -1, 0) and the normal (1, 2, 0)
+      let scale = Transform::scaling(&Vector3::new(2.0, 1.0, 1.0));
+      let n = scale.transform_normal(&Vector3::new(1.0, 1.0, 0.0));
+      let expected = Vector3::new(1.0, 2.0, 0.0).normalize();
+      assert!((n.unwrap() - expected).norm() < 1e-6, "{:?}", n);
+
+      for t in transforms()
+      {
+         let normal = Vector3::new(0.3, -0.7, 0.4);
+         let tangents = [Vector3::new(0.7, 0.3, 0.0), Vector3::new(0.0, 0.4, 0.7),
+                         normal.cross(&Vector3::new(1.0, 2.0, 3.0))];
+         let n = t.transform_normal(&normal);
+         assert!((n.norm() - 1.0).abs() < 1e-5);
+         for tangent in &tangents
+         {
+            let moved = t.transform_vector(tangent);
+            assert!(n.dot(&moved).abs() < 1e-5 * moved.norm(), "{:?}", n);
+         }
+         // Facing the same side as before
+         assert!(n.dot(&t.transform_vector(&normal)) > 0.0);
+      }
+   }
+
+   #[test]
+   fn ray_distances_scale_by_factor()
+   {
+      let world_ray = Ray::new(Point3::new(-1.0, 4.0, 2.0), Vector3::new(0.2, -1.0, 0.6));
+      for t in transforms()
+      {
+         let (local_ray, factor) = t.transform_ray(&world_ray);
+         assert!((local_ray.direction.norm() - 1.0).abs() < 1e-5);
+         for &distance in &[0.5f32, 1.0, 7.0]
+         {
+            let world_point = world_ray.origin + world_ray.direction.unwrap() * distance;
+            let local_point = t.transform_point(&world_point);
+            assert!(((local_point - local_ray.origin).norm() - distance * factor).abs() < 1e-4 * distance * factor);
+            let along = local_ray.origin + local_ray.direction.unwrap() * (distance * factor);
+            assert!((along - local_point).norm() < 1e-4 * distance * factor, "{:?}", along);
+         }
+      }
+
+      // A ray going into an object scaled by 2 travels half as far in it
+      let (_, factor) = Transform::scaling(&Vector3::new(2.0, 2.0, 2.0)).inverse().transform_ray(&world_ray);
+      assert!((factor - 0.5).abs() < 1e-6);
+   }
+
+   #[test]
+   fn bounding_box_contains_transformed_corners()
+   {
+      let bbox = BoundingBox {
+         min: Point3::new(-1.0, 0.0, 2.0),
+         max: Point3::new(1.0, 3.0, 2.5)
+      };
+      for t in transforms()
+      {
+         let moved = t.transform_bounding_box(&bbox);
+         for i in 0..27
+         {
+            let f = |k: u32| (i / 3u32.pow(k) % 3) as f32 / 2.0;
+            let p = Point3::new(bbox.min.x + f(0) * (bbox.max.x - bbox.min.x),
+                                bbox.min.y + f(1) * (bbox.max.y - bbox.min.y),
+                                bbox.min.z + f(2) * (bbox.max.z - bbox.min.z));
+            let q = t.transform_point(&p);
+            for axis in 0..3
+            {
+               assert!(q[axis] >= moved.min[axis] - 1e-5 && q[axis] <= moved.max[axis] + 1e-5);
+            }
+         }
+      }
+   }
+}